@@ -0,0 +1,129 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// The format of an archive from its file name (`.zip`, `.tar.gz` or `.tgz`), tar.gz
+    /// when written to stdout (`-`).
+    pub fn from_file_name(name: &str) -> Result<Self, String> {
+        if name == "-" || name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else {
+            Err(format!(
+                "unknown archive format for {} (expected .zip, .tar.gz or .tgz)",
+                name
+            ))
+        }
+    }
+}
+
+/// An archive file, `-` for stdout, with its format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    pub file: String,
+    pub format: ArchiveFormat,
+}
+
+impl Archive {
+    pub fn is_stdout(&self) -> bool {
+        self.file == "-"
+    }
+}
+
+impl FromStr for Archive {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Archive {
+            file: s.to_string(),
+            format: ArchiveFormat::from_file_name(s)?,
+        })
+    }
+}
+
+/// The files of a directory, sorted by name.
+fn files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut files: Vec<(PathBuf, String)> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (path, name)
+        })
+        .collect();
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    files
+}
+
+fn write_zip<W: Write + Seek>(files: &[(PathBuf, String)], wtr: W) {
+    let mut zip = zip::ZipWriter::new(wtr);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (path, name) in files {
+        zip.start_file(name.as_str(), options).unwrap();
+        std::io::copy(&mut File::open(path).unwrap(), &mut zip).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn write_tar_gz<W: Write>(files: &[(PathBuf, String)], wtr: W) {
+    let mut tar = tar::Builder::new(GzEncoder::new(wtr, Compression::default()));
+    for (path, name) in files {
+        tar.append_path_with_name(path, name).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+/// Writes the files of `input_dir` in a zip or tar.gz archive, on stdout for `-`.
+pub fn write_archive<P: AsRef<Path>>(input_dir: P, archive: &Archive) {
+    let files = files(input_dir.as_ref());
+    match archive.format {
+        ArchiveFormat::TarGz if archive.is_stdout() => {
+            write_tar_gz(&files, std::io::stdout().lock())
+        }
+        ArchiveFormat::TarGz => write_tar_gz(&files, File::create(&archive.file).unwrap()),
+        ArchiveFormat::Zip => write_zip(&files, File::create(&archive.file).unwrap()),
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+/// The CSS named colours (https://www.w3.org/TR/css-color-4/#named-colors).
+const NAMED_COLOURS: [(&str, &str); 148] = [
+    ("aliceblue", "F0F8FF"),
+    ("antiquewhite", "FAEBD7"),
+    ("aqua", "00FFFF"),
+    ("aquamarine", "7FFFD4"),
+    ("azure", "F0FFFF"),
+    ("beige", "F5F5DC"),
+    ("bisque", "FFE4C4"),
+    ("black", "000000"),
+    ("blanchedalmond", "FFEBCD"),
+    ("blue", "0000FF"),
+    ("blueviolet", "8A2BE2"),
+    ("brown", "A52A2A"),
+    ("burlywood", "DEB887"),
+    ("cadetblue", "5F9EA0"),
+    ("chartreuse", "7FFF00"),
+    ("chocolate", "D2691E"),
+    ("coral", "FF7F50"),
+    ("cornflowerblue", "6495ED"),
+    ("cornsilk", "FFF8DC"),
+    ("crimson", "DC143C"),
+    ("cyan", "00FFFF"),
+    ("darkblue", "00008B"),
+    ("darkcyan", "008B8B"),
+    ("darkgoldenrod", "B8860B"),
+    ("darkgray", "A9A9A9"),
+    ("darkgreen", "006400"),
+    ("darkgrey", "A9A9A9"),
+    ("darkkhaki", "BDB76B"),
+    ("darkmagenta", "8B008B"),
+    ("darkolivegreen", "556B2F"),
+    ("darkorange", "FF8C00"),
+    ("darkorchid", "9932CC"),
+    ("darkred", "8B0000"),
+    ("darksalmon", "E9967A"),
+    ("darkseagreen", "8FBC8F"),
+    ("darkslateblue", "483D8B"),
+    ("darkslategray", "2F4F4F"),
+    ("darkslategrey", "2F4F4F"),
+    ("darkturquoise", "00CED1"),
+    ("darkviolet", "9400D3"),
+    ("deeppink", "FF1493"),
+    ("deepskyblue", "00BFFF"),
+    ("dimgray", "696969"),
+    ("dimgrey", "696969"),
+    ("dodgerblue", "1E90FF"),
+    ("firebrick", "B22222"),
+    ("floralwhite", "FFFAF0"),
+    ("forestgreen", "228B22"),
+    ("fuchsia", "FF00FF"),
+    ("gainsboro", "DCDCDC"),
+    ("ghostwhite", "F8F8FF"),
+    ("gold", "FFD700"),
+    ("goldenrod", "DAA520"),
+    ("gray", "808080"),
+    ("green", "008000"),
+    ("greenyellow", "ADFF2F"),
+    ("grey", "808080"),
+    ("honeydew", "F0FFF0"),
+    ("hotpink", "FF69B4"),
+    ("indianred", "CD5C5C"),
+    ("indigo", "4B0082"),
+    ("ivory", "FFFFF0"),
+    ("khaki", "F0E68C"),
+    ("lavender", "E6E6FA"),
+    ("lavenderblush", "FFF0F5"),
+    ("lawngreen", "7CFC00"),
+    ("lemonchiffon", "FFFACD"),
+    ("lightblue", "ADD8E6"),
+    ("lightcoral", "F08080"),
+    ("lightcyan", "E0FFFF"),
+    ("lightgoldenrodyellow", "FAFAD2"),
+    ("lightgray", "D3D3D3"),
+    ("lightgreen", "90EE90"),
+    ("lightgrey", "D3D3D3"),
+    ("lightpink", "FFB6C1"),
+    ("lightsalmon", "FFA07A"),
+    ("lightseagreen", "20B2AA"),
+    ("lightskyblue", "87CEFA"),
+    ("lightslategray", "778899"),
+    ("lightslategrey", "778899"),
+    ("lightsteelblue", "B0C4DE"),
+    ("lightyellow", "FFFFE0"),
+    ("lime", "00FF00"),
+    ("limegreen", "32CD32"),
+    ("linen", "FAF0E6"),
+    ("magenta", "FF00FF"),
+    ("maroon", "800000"),
+    ("mediumaquamarine", "66CDAA"),
+    ("mediumblue", "0000CD"),
+    ("mediumorchid", "BA55D3"),
+    ("mediumpurple", "9370DB"),
+    ("mediumseagreen", "3CB371"),
+    ("mediumslateblue", "7B68EE"),
+    ("mediumspringgreen", "00FA9A"),
+    ("mediumturquoise", "48D1CC"),
+    ("mediumvioletred", "C71585"),
+    ("midnightblue", "191970"),
+    ("mintcream", "F5FFFA"),
+    ("mistyrose", "FFE4E1"),
+    ("moccasin", "FFE4B5"),
+    ("navajowhite", "FFDEAD"),
+    ("navy", "000080"),
+    ("oldlace", "FDF5E6"),
+    ("olive", "808000"),
+    ("olivedrab", "6B8E23"),
+    ("orange", "FFA500"),
+    ("orangered", "FF4500"),
+    ("orchid", "DA70D6"),
+    ("palegoldenrod", "EEE8AA"),
+    ("palegreen", "98FB98"),
+    ("paleturquoise", "AFEEEE"),
+    ("palevioletred", "DB7093"),
+    ("papayawhip", "FFEFD5"),
+    ("peachpuff", "FFDAB9"),
+    ("peru", "CD853F"),
+    ("pink", "FFC0CB"),
+    ("plum", "DDA0DD"),
+    ("powderblue", "B0E0E6"),
+    ("purple", "800080"),
+    ("rebeccapurple", "663399"),
+    ("red", "FF0000"),
+    ("rosybrown", "BC8F8F"),
+    ("royalblue", "4169E1"),
+    ("saddlebrown", "8B4513"),
+    ("salmon", "FA8072"),
+    ("sandybrown", "F4A460"),
+    ("seagreen", "2E8B57"),
+    ("seashell", "FFF5EE"),
+    ("sienna", "A0522D"),
+    ("silver", "C0C0C0"),
+    ("skyblue", "87CEEB"),
+    ("slateblue", "6A5ACD"),
+    ("slategray", "708090"),
+    ("slategrey", "708090"),
+    ("snow", "FFFAFA"),
+    ("springgreen", "00FF7F"),
+    ("steelblue", "4682B4"),
+    ("tan", "D2B48C"),
+    ("teal", "008080"),
+    ("thistle", "D8BFD8"),
+    ("tomato", "FF6347"),
+    ("turquoise", "40E0D0"),
+    ("violet", "EE82EE"),
+    ("wheat", "F5DEB3"),
+    ("white", "FFFFFF"),
+    ("whitesmoke", "F5F5F5"),
+    ("yellow", "FFFF00"),
+    ("yellowgreen", "9ACD32"),
+];
+
+/// Normalises a colour (a CSS named colour, or a 3 or 6 digits hexadecimal colour with
+/// or without `#`) to `#RRGGBB`. Returns `None` for an empty or invalid colour.
+pub fn normalize_colour(colour: &str) -> Option<String> {
+    let colour = colour.trim().to_ascii_lowercase();
+    if let Some((_, hex)) = NAMED_COLOURS.iter().find(|(name, _)| *name == colour) {
+        return Some(format!("#{}", hex));
+    }
+    let hex = colour.strip_prefix('#').unwrap_or(&colour);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        3 => Some(hex.chars().flat_map(|c| [c, c]).collect()),
+        6 => Some(hex.to_string()),
+        _ => None,
+    }
+    .map(|hex| format!("#{}", hex.to_ascii_uppercase()))
+}
+
+/// Black or white, whichever contrasts the most with a `#RRGGBB` colour
+/// (relative luminance of WCAG 2.0).
+pub fn contrasting_text_colour(colour: &str) -> String {
+    let channel = |idx: usize| {
+        let value = colour
+            .get(idx..idx + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .unwrap_or(0) as f64
+            / 255.;
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance = 0.2126 * channel(1) + 0.7152 * channel(3) + 0.0722 * channel(5);
+    // same contrast ratio with black and white for a luminance of 0.179
+    if luminance > 0.179 {
+        "#000000".to_string()
+    } else {
+        "#FFFFFF".to_string()
+    }
+}
+
+/// The normalised colour and text colour of a Route or a Line, from its `colour` and
+/// `text_colour` tags (empty when not tagged or invalid).
+pub fn colours_from_tags(tags: &osmpbfreader::Tags) -> (String, String) {
+    let tag = |key: &str| {
+        tags.get(key)
+            .and_then(|v| normalize_colour(v))
+            .unwrap_or_default()
+    };
+    (tag("colour"), tag("text_colour"))
+}
+
+/// Sets a text colour contrasting with the colour when there is a colour but no text
+/// colour. It is done once the Lines are consolidated, so that the computed text colours
+/// are neither inherited by the Lines nor compared with the tagged ones.
+pub fn set_contrasting_text_colour(colour: &str, text_colour: &mut String) {
+    if text_colour.is_empty() && !colour.is_empty() {
+        *text_colour = contrasting_text_colour(colour);
+    }
+}
+
+/// The `colour` and `text_colour` tags that are not valid colours.
+pub fn invalid_colour_tags(tags: &osmpbfreader::Tags) -> Vec<(&'static str, &str)> {
+    ["colour", "text_colour"]
+        .iter()
+        .filter_map(|key| {
+            tags.get(*key)
+                .filter(|v| !v.trim().is_empty() && normalize_colour(v).is_none())
+                .map(|v| (*key, v.as_str()))
+        })
+        .collect()
+}
@@ -0,0 +1,303 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::colour::{colours_from_tags, set_contrasting_text_colour};
+use crate::direction::compute_directions;
+use crate::metrics::compute_lines_metrics;
+use crate::{Line, OsmTcResponse, Route, StopArea};
+use log::{info, warn};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Fields of a Line that can be inherited from its Routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InheritedField {
+    Colour,
+    TextColour,
+    Operator,
+    Network,
+    Code,
+}
+
+const INHERITED_FIELDS: [InheritedField; 5] = [
+    InheritedField::Colour,
+    InheritedField::TextColour,
+    InheritedField::Operator,
+    InheritedField::Network,
+    InheritedField::Code,
+];
+
+impl InheritedField {
+    fn of_line(self, line: &Line) -> &str {
+        match self {
+            InheritedField::Colour => &line.colour,
+            InheritedField::TextColour => &line.text_colour,
+            InheritedField::Operator => &line.operator,
+            InheritedField::Network => &line.network,
+            InheritedField::Code => &line.code,
+        }
+    }
+
+    fn of_line_mut(self, line: &mut Line) -> &mut String {
+        match self {
+            InheritedField::Colour => &mut line.colour,
+            InheritedField::TextColour => &mut line.text_colour,
+            InheritedField::Operator => &mut line.operator,
+            InheritedField::Network => &mut line.network,
+            InheritedField::Code => &mut line.code,
+        }
+    }
+
+    fn of_route(self, route: &Route) -> &str {
+        match self {
+            InheritedField::Colour => &route.colour,
+            InheritedField::TextColour => &route.text_colour,
+            InheritedField::Operator => &route.operator,
+            InheritedField::Network => &route.network,
+            InheritedField::Code => &route.code,
+        }
+    }
+
+    /// The value of a field as tagged : a text colour computed to contrast with the
+    /// colour is left out.
+    fn tagged<'a>(self, value: &'a str, tags: &osmpbfreader::Tags) -> Cow<'a, str> {
+        match self {
+            InheritedField::TextColour => Cow::Owned(colours_from_tags(tags).1),
+            _ => Cow::Borrowed(value),
+        }
+    }
+
+    fn tagged_of_line(self, line: &Line) -> Cow<'_, str> {
+        self.tagged(self.of_line(line), &line.all_osm_tags)
+    }
+
+    fn tagged_of_route(self, route: &Route) -> Cow<'_, str> {
+        self.tagged(self.of_route(route), &route.all_osm_tags)
+    }
+}
+
+impl fmt::Display for InheritedField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            InheritedField::Colour => "colour",
+            InheritedField::TextColour => "text_colour",
+            InheritedField::Operator => "operator",
+            InheritedField::Network => "network",
+            InheritedField::Code => "code",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub line_id: String,
+    pub route_id: String,
+    pub field: InheritedField,
+    pub line_value: String,
+    pub route_value: String,
+}
+
+fn line_routes<'a>(line: &Line, routes_by_id: &BTreeMap<&str, &'a Route>) -> Vec<&'a Route> {
+    line.routes_id
+        .iter()
+        .filter_map(|id| routes_by_id.get(id.as_str()).copied())
+        .collect()
+}
+
+/// Lists the routes whose value for an inherited field differs from the one of their line.
+/// Routes without value are not in conflict, and the computed text colours are ignored.
+pub fn find_line_conflicts(lines: &[Line], routes: &[Route]) -> Vec<Conflict> {
+    let routes_by_id: BTreeMap<&str, &Route> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+    let mut conflicts = vec![];
+    for line in lines {
+        for route in line_routes(line, &routes_by_id) {
+            for field in INHERITED_FIELDS.iter().copied() {
+                let line_value = field.tagged_of_line(line);
+                let route_value = field.tagged_of_route(route);
+                if !line_value.is_empty() && !route_value.is_empty() && line_value != route_value {
+                    conflicts.push(Conflict {
+                        line_id: line.id.clone(),
+                        route_id: route.id.clone(),
+                        field,
+                        line_value: line_value.to_string(),
+                        route_value: route_value.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+fn served_stop_areas(line_routes: &[&Route], stop_areas: &[StopArea]) -> Vec<String> {
+    let mut stop_area_of_stop_point = BTreeMap::new();
+    for sa in stop_areas {
+        for sp_id in &sa.stop_point_ids {
+            stop_area_of_stop_point
+                .entry(sp_id.as_str())
+                .or_insert_with(|| sa.id.as_str());
+        }
+    }
+    let mut seen = BTreeSet::new();
+    line_routes
+        .iter()
+        .flat_map(|r| &r.ordered_route_points)
+        .filter_map(|rp| stop_area_of_stop_point.get(rp.stop_point_id.as_str()))
+        .filter(|sa_id| seen.insert(**sa_id))
+        .map(|sa_id| sa_id.to_string())
+        .collect()
+}
+
+/// Fills the empty fields of each line with the value shared by all its routes (their
+/// tagged text colour, not a computed one), and computes the stop areas served by the
+/// line. A line with a colour but without text colour then gets a contrasting one.
+pub fn consolidate_lines(lines: &mut [Line], routes: &[Route], stop_areas: &[StopArea]) {
+    let routes_by_id: BTreeMap<&str, &Route> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+    for line in lines.iter_mut() {
+        let line_routes = line_routes(line, &routes_by_id);
+        for field in INHERITED_FIELDS.iter().copied() {
+            if !field.of_line(line).is_empty() {
+                continue;
+            }
+            let values: BTreeSet<Cow<str>> = line_routes
+                .iter()
+                .map(|r| field.tagged_of_route(r))
+                .filter(|v| !v.is_empty())
+                .collect();
+            match values.len() {
+                0 => {}
+                1 => {
+                    let value = values.into_iter().next().unwrap();
+                    info!(
+                        "{} of line {} set to {} from its routes",
+                        field, line.id, value
+                    );
+                    *field.of_line_mut(line) = value.to_string();
+                }
+                _ => warn!(
+                    "routes of line {} have different values for {} : {:?}",
+                    line.id, field, values
+                ),
+            }
+        }
+        set_contrasting_text_colour(&line.colour, &mut line.text_colour);
+        line.stop_area_ids = served_stop_areas(&line_routes, stop_areas);
+    }
+    for conflict in find_line_conflicts(lines, routes) {
+        warn!(
+            "{} of route {} ({}) is different from the one of its line {} ({})",
+            conflict.field,
+            conflict.route_id,
+            conflict.route_value,
+            conflict.line_id,
+            conflict.line_value
+        );
+    }
+}
+
+pub const SYNTHETIC_LINE_PREFIX: &str = "synthetic:";
+
+/// Groups the routes that are not members of any line by (network, operator, ref, mode)
+/// and creates a line for each group. The routes of a synthetic line are sorted by id,
+/// and its id is the id of its first route prefixed by `synthetic:`. Without `ref`,
+/// the line is named after this first route.
+pub fn add_synthetic_lines(response: &mut OsmTcResponse) {
+    let (routes, lines) = match (response.routes.as_mut(), response.lines.as_mut()) {
+        (Some(routes), Some(lines)) => (routes, lines),
+        _ => return,
+    };
+    let mut groups: BTreeMap<(&str, &str, &str, &str), Vec<usize>> = BTreeMap::new();
+    for (idx, r) in routes.iter().enumerate() {
+        if r.line_id.is_empty() {
+            groups
+                .entry((&r.network, &r.operator, &r.code, &r.mode))
+                .or_default()
+                .push(idx);
+        }
+    }
+    let mut synthetic_lines: Vec<Line> = groups
+        .into_iter()
+        .map(|((network, operator, code, mode), mut routes_idx)| {
+            routes_idx.sort_by(|a, b| routes[*a].id.cmp(&routes[*b].id));
+            let routes_id: Vec<String> = routes_idx
+                .iter()
+                .map(|idx| routes[*idx].id.clone())
+                .collect();
+            let name = if code.is_empty() {
+                routes[routes_idx[0]].name.clone()
+            } else {
+                code.to_string()
+            };
+            Line {
+                id: format!("{}{}", SYNTHETIC_LINE_PREFIX, routes_id[0]),
+                name,
+                code: code.to_string(),
+                colour: String::new(),
+                text_colour: String::new(),
+                operator: operator.to_string(),
+                network: network.to_string(),
+                mode: mode.to_string(),
+                transport_mode: routes[routes_idx[0]].transport_mode,
+                frequency: String::new(),
+                opening_hours: String::new(),
+                calendar: None,
+                frequency_exceptions: String::new(),
+                headways: vec![],
+                all_osm_tags: Default::default(),
+                shape: routes_idx
+                    .iter()
+                    .flat_map(|idx| routes[*idx].shape.iter().cloned())
+                    .collect(),
+                routes_id,
+                dangling_routes_id: vec![],
+                stop_area_ids: vec![],
+                metrics: Default::default(),
+            }
+        })
+        .collect();
+    info!("{} synthetic lines created", synthetic_lines.len());
+
+    let route_index: BTreeMap<String, usize> = routes
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| (r.id.clone(), idx))
+        .collect();
+    for line in &synthetic_lines {
+        for route_id in &line.routes_id {
+            routes[route_index[route_id]].line_id = line.id.clone();
+        }
+    }
+    consolidate_lines(&mut synthetic_lines, routes, &response.stop_areas);
+    compute_lines_metrics(&mut synthetic_lines, routes);
+    lines.extend(synthetic_lines);
+    compute_directions(routes, lines, &response.stop_points, &response.stop_areas);
+}
@@ -0,0 +1,456 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::frequency::parse_headway_windows;
+use crate::metrics::{BoundingBox, Metrics};
+use crate::mode::TransportMode;
+use crate::StopPointType;
+use crate::{wkt_format, Coord, Line, OsmTcResponse, Route, RoutePoint, StopArea, StopPoint};
+use geo_types::Geometry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// A row of a CSV file, whose fields are read by column name.
+struct Row<'a> {
+    columns: &'a HashMap<String, usize>,
+    record: csv::StringRecord,
+}
+
+impl<'a> Row<'a> {
+    fn get(&self, column: &str) -> Result<&str, String> {
+        self.columns
+            .get(column)
+            .and_then(|&i| self.record.get(i))
+            .ok_or_else(|| format!("no column {}", column))
+    }
+
+    fn string(&self, column: &str) -> Result<String, String> {
+        self.get(column).map(|value| value.to_string())
+    }
+
+    fn id(&self, column: &str, prefix: &str) -> Result<String, String> {
+        self.get(column).map(|id| remove_prefix(id, prefix))
+    }
+
+    fn float(&self, column: &str) -> Result<Option<f64>, String> {
+        match self.get(column)? {
+            "" => Ok(None),
+            value => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid number {} in column {}", value, column)),
+        }
+    }
+
+    fn required_float(&self, column: &str) -> Result<f64, String> {
+        self.float(column)?
+            .ok_or_else(|| format!("empty column {}", column))
+    }
+
+    fn coord(&self) -> Result<Coord, String> {
+        Ok(Coord::new(
+            self.required_float("lat")?,
+            self.required_float("lon")?,
+        ))
+    }
+
+    /// The tags written as `osm:<key>` columns (an empty value is no tag) or as
+    /// a JSON object in an `osm_tags` column.
+    fn tags(&self) -> Result<osmpbfreader::objects::Tags, String> {
+        if self.columns.contains_key("osm_tags") {
+            return serde_json::from_str(self.get("osm_tags")?)
+                .map_err(|e| format!("invalid osm_tags: {}", e));
+        }
+        Ok(self
+            .columns
+            .iter()
+            .filter_map(|(column, &i)| Some((column.strip_prefix("osm:")?, self.record.get(i)?)))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect())
+    }
+
+    fn metrics(&self) -> Result<Metrics, String> {
+        let bbox = match (
+            self.float("bbox_min_lat")?,
+            self.float("bbox_min_lon")?,
+            self.float("bbox_max_lat")?,
+            self.float("bbox_max_lon")?,
+        ) {
+            (Some(min_lat), Some(min_lon), Some(max_lat), Some(max_lon)) => Some(BoundingBox {
+                min_lat,
+                min_lon,
+                max_lat,
+                max_lon,
+            }),
+            _ => None,
+        };
+        let stop_count = self.get("stop_count")?;
+        Ok(Metrics {
+            length: self.required_float("length")?,
+            stop_count: stop_count
+                .parse()
+                .map_err(|_| format!("invalid stop_count {}", stop_count))?,
+            mean_stop_distance: self.float("mean_stop_distance")?,
+            max_stop_distance: self.float("max_stop_distance")?,
+            bbox,
+            origin_destination_distance: self.float("origin_destination_distance")?,
+        })
+    }
+
+    /// A shape written as WKT (the other geometry encodings can not be read back).
+    fn shape(&self) -> Result<Vec<Vec<Coord>>, String> {
+        wkt_format::wkt_to_shape(self.get("shape")?)
+    }
+
+    /// An optional value parsed with `FromStr`, empty when missing.
+    fn optional<T: std::str::FromStr>(&self, column: &str) -> Result<Option<T>, String> {
+        match self.get(column)? {
+            "" => Ok(None),
+            value => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid {} {}", column, value)),
+        }
+    }
+}
+
+fn remove_prefix(id: &str, prefix: &str) -> String {
+    id.strip_prefix(prefix)
+        .and_then(|id| id.strip_prefix(':'))
+        .unwrap_or(id)
+        .to_string()
+}
+
+/// Reads the rows of a file, the errors being reported with the file and the line.
+fn read_rows<P: AsRef<Path>, F: FnMut(Row) -> Result<(), String>>(
+    file: P,
+    mut f: F,
+) -> Result<(), String> {
+    let file = file.as_ref();
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", file.display(), e);
+    let mut rdr = csv::Reader::from_path(file).map_err(|e| error(&e))?;
+    let columns: HashMap<String, usize> = rdr
+        .headers()
+        .map_err(|e| error(&e))?
+        .iter()
+        .enumerate()
+        .map(|(i, column)| (column.to_string(), i))
+        .collect();
+    for record in rdr.records() {
+        let record = record.map_err(|e| error(&e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        f(Row {
+            columns: &columns,
+            record,
+        })
+        .map_err(|e| format!("{}:{}: {}", file.display(), line, e))?;
+    }
+    Ok(())
+}
+
+fn coords(linestring: &geo_types::LineString<f64>) -> Vec<Coord> {
+    linestring.0.iter().map(|c| Coord::new(c.y, c.x)).collect()
+}
+
+/// Reads the stop points written by `write_stop_points_to_csv` (the pairs are
+/// read by `read_stop_point_pairs_from_csv`).
+pub fn read_stop_points_from_csv<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<Vec<StopPoint>, String> {
+    let file = input_dir
+        .as_ref()
+        .join(format!("{}stop_points.csv", prefix));
+    let mut stop_points = vec![];
+    read_rows(file, |row| {
+        let stop_point_type = match row.get("stop_point_type")? {
+            "StopPosition" => StopPointType::StopPosition,
+            "Platform" => StopPointType::Platform,
+            _ => StopPointType::Unknown,
+        };
+        // the stop points without geometry are written as their coord
+        let geometry = match wkt_format::wkt_to_geometry(row.get("geometry")?)? {
+            Geometry::Point(_) => None,
+            geometry => Some(geometry),
+        };
+        stop_points.push(StopPoint {
+            id: row.id("stop_point_id", "StopPoint")?,
+            stop_point_type,
+            coord: row.coord()?,
+            name: row.string("name")?,
+            all_osm_tags: row.tags()?,
+            geometry,
+            paired_stop_point_id: String::new(),
+        });
+        Ok(())
+    })?;
+    Ok(stop_points)
+}
+
+/// Sets the `paired_stop_point_id` of the stop points from the file written by
+/// `write_stop_point_pairs_to_csv`.
+pub fn read_stop_point_pairs_from_csv<P: AsRef<Path>>(
+    stop_points: &mut [StopPoint],
+    input_dir: P,
+    prefix: &str,
+) -> Result<(), String> {
+    let file = input_dir
+        .as_ref()
+        .join(format!("{}stop_point_pairs.csv", prefix));
+    let mut pairs = BTreeMap::new();
+    read_rows(file, |row| {
+        let platform_id = row.id("platform_id", "StopPoint")?;
+        let stop_position_id = row.id("stop_position_id", "StopPoint")?;
+        pairs.insert(stop_position_id.clone(), platform_id.clone());
+        pairs.insert(platform_id, stop_position_id);
+        Ok(())
+    })?;
+    for sp in stop_points {
+        if let Some(paired_id) = pairs.remove(&sp.id) {
+            sp.paired_stop_point_id = paired_id;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the stop areas written by `write_stop_areas_to_csv` (the members are read
+/// by `read_stop_areas_stop_point_from_csv`).
+pub fn read_stop_areas_from_csv<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<Vec<StopArea>, String> {
+    let file = input_dir.as_ref().join(format!("{}stop_areas.csv", prefix));
+    let mut stop_areas = vec![];
+    read_rows(file, |row| {
+        // the hull column is only written when the hulls are computed
+        let hull = if row.columns.contains_key("hull") {
+            match wkt_format::wkt_to_geometry(row.get("hull")?)? {
+                Geometry::Point(point) => vec![Coord::new(point.y(), point.x())],
+                Geometry::LineString(linestring) => coords(&linestring),
+                Geometry::Polygon(polygon) => coords(polygon.exterior()),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        };
+        stop_areas.push(StopArea {
+            id: row.id("stop_area_id", "StopArea")?,
+            coord: row.coord()?,
+            name: row.string("name")?,
+            all_osm_tags: row.tags()?,
+            stop_point_ids: vec![],
+            stop_position_ids: vec![],
+            hull,
+        });
+        Ok(())
+    })?;
+    Ok(stop_areas)
+}
+
+/// Sets the `stop_point_ids` of the stop areas from the file written by
+/// `write_stop_areas_stop_point_to_csv` (the `stop_position_ids` are not written).
+pub fn read_stop_areas_stop_point_from_csv<P: AsRef<Path>>(
+    stop_areas: &mut [StopArea],
+    input_dir: P,
+    prefix: &str,
+) -> Result<(), String> {
+    let file = input_dir
+        .as_ref()
+        .join(format!("{}stop_areas_stop_point.csv", prefix));
+    let mut members: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    read_rows(file, |row| {
+        members
+            .entry(row.id("stop_area_id", "StopArea")?)
+            .or_default()
+            .push(row.id("stop_point_id", "StopPoint")?);
+        Ok(())
+    })?;
+    for sa in stop_areas {
+        sa.stop_point_ids = members.remove(&sa.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Reads the routes and their stops written by `write_routes_to_csv`. The distances of
+/// the route points and the metrics are read as written (rounded to the decimeter).
+pub fn read_routes_from_csv<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<Vec<Route>, String> {
+    let input_dir = input_dir.as_ref();
+    let mut route_points: BTreeMap<String, Vec<RoutePoint>> = BTreeMap::new();
+    read_rows(
+        input_dir.join(format!("{}route_points.csv", prefix)),
+        |row| {
+            route_points
+                .entry(row.id("route_id", "Route")?)
+                .or_default()
+                .push(RoutePoint {
+                    role: row.string("role")?,
+                    stop_point_id: row.id("stop_id", "StopPoint")?,
+                    shape_dist_traveled: row.float("shape_dist_traveled")?,
+                    distance_to_shape: row.float("distance_to_shape")?,
+                });
+            Ok(())
+        },
+    )?;
+    let mut routes = vec![];
+    read_rows(input_dir.join(format!("{}routes.csv", prefix)), |row| {
+        let id = row.id("route_id", "Route")?;
+        routes.push(Route {
+            ordered_route_points: route_points.remove(&id).unwrap_or_default(),
+            id,
+            name: row.string("name")?,
+            code: row.string("code")?,
+            destination: row.string("destination")?,
+            origin: row.string("origin")?,
+            colour: row.string("colour")?,
+            text_colour: row.string("text_colour")?,
+            operator: row.string("operator")?,
+            network: row.string("network")?,
+            mode: row.string("mode")?,
+            transport_mode: TransportMode::new(row.get("mode")?, row.get("sub_mode")?.parse().ok()),
+            frequency: row.string("frequency")?,
+            opening_hours: row.string("opening_hours")?,
+            calendar: row.get("opening_hours")?.parse().ok(),
+            frequency_exceptions: row.string("frequency_exceptions")?,
+            headways: parse_headway_windows(
+                row.get("frequency")?,
+                row.get("frequency_exceptions")?,
+            )
+            .unwrap_or_default(),
+            travel_time: row.string("travel_time")?,
+            all_osm_tags: row.tags()?,
+            shape: row.shape()?,
+            line_id: row.id("line_id", "Line")?,
+            metrics: row.metrics()?,
+            direction_id: row.optional("direction_id")?,
+            variant: row.optional("variant")?,
+            roundtrip: row.get("roundtrip")? == "true",
+        });
+        Ok(())
+    })?;
+    Ok(routes)
+}
+
+/// Reads the lines, their routes and stop areas written by `write_lines_to_csv`
+/// (the `dangling_routes_id` are not written).
+pub fn read_lines_from_csv<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<Vec<Line>, String> {
+    let input_dir = input_dir.as_ref();
+    let mut routes_id: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    read_rows(
+        input_dir.join(format!("{}line_routes.csv", prefix)),
+        |row| {
+            routes_id
+                .entry(row.id("line_id", "Line")?)
+                .or_default()
+                .push(row.id("route_id", "Route")?);
+            Ok(())
+        },
+    )?;
+    let mut stop_area_ids: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    read_rows(
+        input_dir.join(format!("{}line_stop_areas.csv", prefix)),
+        |row| {
+            stop_area_ids
+                .entry(row.id("line_id", "Line")?)
+                .or_default()
+                .push(row.id("stop_area_id", "StopArea")?);
+            Ok(())
+        },
+    )?;
+    let mut lines = vec![];
+    read_rows(input_dir.join(format!("{}lines.csv", prefix)), |row| {
+        let id = row.id("line_id", "Line")?;
+        lines.push(Line {
+            routes_id: routes_id.remove(&id).unwrap_or_default(),
+            stop_area_ids: stop_area_ids.remove(&id).unwrap_or_default(),
+            id,
+            name: row.string("name")?,
+            code: row.string("code")?,
+            colour: row.string("colour")?,
+            text_colour: row.string("text_colour")?,
+            operator: row.string("operator")?,
+            network: row.string("network")?,
+            mode: row.string("mode")?,
+            transport_mode: TransportMode::new(row.get("mode")?, row.get("sub_mode")?.parse().ok()),
+            frequency: row.string("frequency")?,
+            opening_hours: row.string("opening_hours")?,
+            calendar: row.get("opening_hours")?.parse().ok(),
+            frequency_exceptions: row.string("frequency_exceptions")?,
+            headways: parse_headway_windows(
+                row.get("frequency")?,
+                row.get("frequency_exceptions")?,
+            )
+            .unwrap_or_default(),
+            all_osm_tags: row.tags()?,
+            shape: row.shape()?,
+            dangling_routes_id: vec![],
+            metrics: row.metrics()?,
+        });
+        Ok(())
+    })?;
+    Ok(lines)
+}
+
+/// Reads all the CSV files written by the `extract` subcommand. The routes and lines
+/// are `None` when their files are missing (extraction of the stops only). The errors
+/// give the file and the line of the invalid row. The `prefix` of the file names is
+/// `sink::DEFAULT_PREFIX` unless they have been written with another one.
+pub fn read_osm_tcobjects_from_csv<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<OsmTcResponse, String> {
+    let input_dir = input_dir.as_ref();
+    let mut stop_points = read_stop_points_from_csv(input_dir, prefix)?;
+    read_stop_point_pairs_from_csv(&mut stop_points, input_dir, prefix)?;
+    let mut stop_areas = read_stop_areas_from_csv(input_dir, prefix)?;
+    read_stop_areas_stop_point_from_csv(&mut stop_areas, input_dir, prefix)?;
+    let routes = if input_dir.join(format!("{}routes.csv", prefix)).exists() {
+        Some(read_routes_from_csv(input_dir, prefix)?)
+    } else {
+        None
+    };
+    let lines = if input_dir.join(format!("{}lines.csv", prefix)).exists() {
+        Some(read_lines_from_csv(input_dir, prefix)?)
+    } else {
+        None
+    };
+    Ok(OsmTcResponse {
+        stop_points,
+        stop_areas,
+        routes,
+        lines,
+    })
+}
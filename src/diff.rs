@@ -0,0 +1,99 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::{Id, OsmTcResponse};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub object_id: String,
+    pub kind: ChangeKind,
+}
+
+fn diff_objects<T: Id<T> + PartialEq>(prefix: &str, old: &[T], new: &[T]) -> Vec<Change> {
+    let old_by_id: BTreeMap<&str, &T> = old.iter().map(|o| (o.id(), o)).collect();
+    let new_by_id: BTreeMap<&str, &T> = new.iter().map(|o| (o.id(), o)).collect();
+    let removed = old_by_id
+        .keys()
+        .filter(|id| !new_by_id.contains_key(*id))
+        .map(|id| (*id, ChangeKind::Removed));
+    let added_or_modified = new_by_id
+        .iter()
+        .filter_map(|(id, obj)| match old_by_id.get(id) {
+            None => Some((*id, ChangeKind::Added)),
+            Some(old_obj) if old_obj != obj => Some((*id, ChangeKind::Modified)),
+            Some(_) => None,
+        });
+    let mut changes: Vec<Change> = removed
+        .chain(added_or_modified)
+        .map(|(id, kind)| Change {
+            object_id: format!("{}:{}", prefix, id),
+            kind,
+        })
+        .collect();
+    changes.sort_by(|a, b| a.object_id.cmp(&b.object_id));
+    changes
+}
+
+pub fn diff_osm_tcobjects(old: &OsmTcResponse, new: &OsmTcResponse) -> Vec<Change> {
+    let mut changes = diff_objects("StopPoint", &old.stop_points, &new.stop_points);
+    changes.extend(diff_objects("StopArea", &old.stop_areas, &new.stop_areas));
+    changes.extend(diff_objects(
+        "Route",
+        old.routes.as_deref().unwrap_or(&[]),
+        new.routes.as_deref().unwrap_or(&[]),
+    ));
+    changes.extend(diff_objects(
+        "Line",
+        old.lines.as_deref().unwrap_or(&[]),
+        new.lines.as_deref().unwrap_or(&[]),
+    ));
+    changes
+}
+
+pub fn write_changes_to_csv<P: AsRef<Path>>(changes: &[Change], output_dir: P, prefix: &str) {
+    let output_dir = output_dir.as_ref();
+    let csv_file = output_dir.join(format!("{}diff.csv", prefix));
+
+    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
+    wtr.serialize(("object_id", "change")).unwrap();
+    for change in changes {
+        wtr.serialize((&change.object_id, format!("{:?}", change.kind)))
+            .unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::distance;
+use crate::{Coord, Line, Route, StopArea, StopPoint, StopPointType};
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Variant of a Route compared to the main Route of its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RouteVariant {
+    /// the Route with the most stops of its direction
+    Main,
+    /// all the stops of the Route are served by the main Route
+    ShortTurn,
+    /// the Route serves stops that are not served by the main Route
+    Branch,
+}
+
+impl fmt::Display for RouteVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = match self {
+            RouteVariant::Main => "main",
+            RouteVariant::ShortTurn => "short_turn",
+            RouteVariant::Branch => "branch",
+        };
+        write!(f, "{}", variant)
+    }
+}
+
+impl FromStr for RouteVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "main" => Ok(RouteVariant::Main),
+            "short_turn" => Ok(RouteVariant::ShortTurn),
+            "branch" => Ok(RouteVariant::Branch),
+            _ => Err(format!("unknown route variant {}", s)),
+        }
+    }
+}
+
+/// The stops of a Route as places: a StopArea for its members, a StopPosition for its
+/// paired Platform, the stop itself otherwise. Consecutive stops at the same place
+/// (the StopPosition and the Platform of a halt) are a single place.
+fn places<'a>(route: &'a Route, place_of: &HashMap<&'a str, &'a str>) -> Vec<&'a str> {
+    let mut places: Vec<&str> = route
+        .ordered_route_points
+        .iter()
+        .map(|rp| rp.stop_point_id.as_str())
+        .map(|id| place_of.get(id).cloned().unwrap_or(id))
+        .collect();
+    places.dedup();
+    places
+}
+
+/// Coordinates of the first and last stops of a Route.
+fn terminus<'a>(
+    route: &Route,
+    coords: &HashMap<&str, &'a Coord>,
+) -> Option<(&'a Coord, &'a Coord)> {
+    let mut stop_coords = route
+        .ordered_route_points
+        .iter()
+        .filter_map(|rp| coords.get(rp.stop_point_id.as_str()).cloned());
+    let first = stop_coords.next()?;
+    Some((first, stop_coords.next_back().unwrap_or(first)))
+}
+
+struct RouteStops<'a> {
+    places: Vec<&'a str>,
+    terminus: Option<(&'a Coord, &'a Coord)>,
+    length: f64,
+    roundtrip: bool,
+}
+
+/// If a Route goes the same way as the reference Route: the order of their common
+/// stops, or when it does not decide, the distances between their terminus. Along a
+/// circular Route, the order of the stops is the shortest way around the loop.
+fn same_direction(route: &RouteStops, reference: &RouteStops) -> bool {
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, place) in reference.places.iter().enumerate() {
+        index.entry(place).or_insert(i);
+    }
+    let positions: Vec<usize> = route
+        .places
+        .iter()
+        .filter_map(|place| index.get(place).cloned())
+        .collect();
+    // the last place of a closed loop is its first one
+    let loop_length = match (reference.places.first(), reference.places.last()) {
+        (Some(first), Some(last)) if first == last => reference.places.len() - 1,
+        _ => reference.places.len(),
+    }
+    .max(1);
+    let is_forward = |w: &[usize]| {
+        if reference.roundtrip {
+            w[1] != w[0] && (w[1] + loop_length - w[0]) % loop_length <= loop_length / 2
+        } else {
+            w[1] > w[0]
+        }
+    };
+    let forward = positions.windows(2).filter(|w| is_forward(w)).count();
+    let backward = positions
+        .windows(2)
+        .filter(|w| w[1] != w[0] && !is_forward(w))
+        .count();
+    if forward != backward {
+        return forward > backward;
+    }
+    match (route.terminus, reference.terminus) {
+        _ if route.roundtrip || reference.roundtrip => true,
+        (Some((first, last)), Some((ref_first, ref_last))) => {
+            distance(first, ref_first) + distance(last, ref_last)
+                <= distance(first, ref_last) + distance(last, ref_first)
+        }
+        _ => true,
+    }
+}
+
+/// Index of the Route with the most stops (then the longest one), the first one on ties.
+fn main_route(routes: &[&RouteStops]) -> usize {
+    let mut main = 0;
+    for (i, r) in routes.iter().enumerate().skip(1) {
+        let m = routes[main];
+        if (r.places.len(), r.length) > (m.places.len(), m.length) {
+            main = i;
+        }
+    }
+    main
+}
+
+/// Sets the direction (0 or 1) and the variant of the Routes of the Lines. The Route of a
+/// Line with the most stops defines the direction 0, and each Route gets the direction
+/// of the order of its stops along this Route. The Routes that are not part of any Line
+/// have no direction.
+pub fn compute_directions(
+    routes: &mut [Route],
+    lines: &[Line],
+    stop_points: &[StopPoint],
+    stop_areas: &[StopArea],
+) {
+    let mut place_of: HashMap<&str, &str> = HashMap::new();
+    for sp in stop_points {
+        if sp.stop_point_type == StopPointType::Platform && !sp.paired_stop_point_id.is_empty() {
+            place_of.insert(&sp.id, &sp.paired_stop_point_id);
+        }
+    }
+    for sa in stop_areas {
+        for sp_id in sa.stop_point_ids.iter().chain(&sa.stop_position_ids) {
+            place_of.insert(sp_id, &sa.id);
+        }
+    }
+    let coords: HashMap<&str, &Coord> = stop_points
+        .iter()
+        .map(|sp| (sp.id.as_str(), &sp.coord))
+        .collect();
+    let route_index: BTreeMap<&str, usize> = routes
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| (r.id.as_str(), idx))
+        .collect();
+
+    let mut directions: Vec<(usize, u8, RouteVariant)> = vec![];
+    for line in lines {
+        let line_routes: Vec<usize> = line
+            .routes_id
+            .iter()
+            .filter_map(|id| route_index.get(id.as_str()).cloned())
+            .filter(|idx| routes[*idx].line_id == line.id)
+            .collect();
+        if line_routes.is_empty() {
+            continue;
+        }
+        let stops: Vec<RouteStops> = line_routes
+            .iter()
+            .map(|idx| RouteStops {
+                places: places(&routes[*idx], &place_of),
+                terminus: terminus(&routes[*idx], &coords),
+                length: routes[*idx].metrics.length,
+                roundtrip: routes[*idx].roundtrip,
+            })
+            .collect();
+        let reference = &stops[main_route(&stops.iter().collect::<Vec<_>>())];
+        let route_directions: Vec<u8> = stops
+            .iter()
+            .map(|s| if same_direction(s, reference) { 0 } else { 1 })
+            .collect();
+        for direction in 0..=1 {
+            let members: Vec<usize> = (0..stops.len())
+                .filter(|i| route_directions[*i] == direction)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let main = members[main_route(&members.iter().map(|i| &stops[*i]).collect::<Vec<_>>())];
+            let main_places: HashSet<&str> = stops[main].places.iter().cloned().collect();
+            for i in members {
+                let variant = if i == main {
+                    RouteVariant::Main
+                } else if stops[i].places.iter().all(|p| main_places.contains(p)) {
+                    RouteVariant::ShortTurn
+                } else {
+                    RouteVariant::Branch
+                };
+                directions.push((line_routes[i], direction, variant));
+            }
+        }
+    }
+
+    for r in routes.iter_mut() {
+        r.direction_id = None;
+        r.variant = None;
+    }
+    let count = |variant: RouteVariant| directions.iter().filter(|d| d.2 == variant).count();
+    info!(
+        "{} routes with a direction ({} short turns, {} branches)",
+        directions.len(),
+        count(RouteVariant::ShortTurn),
+        count(RouteVariant::Branch)
+    );
+    for (idx, direction, variant) in directions {
+        routes[idx].direction_id = Some(direction);
+        routes[idx].variant = Some(variant);
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geojson::multi_line_string_geometry;
+use crate::wkt_format::{shape_to_wkt, ways_to_multi_line_string};
+use crate::Coord;
+use geo_types::{Geometry, LineString, Polygon};
+use std::str::FromStr;
+
+/// Encoding of the `shape` column of the routes and lines CSV files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeometryEncoding {
+    #[default]
+    Wkt,
+    /// Google encoded polylines (precision 5), one per way, separated by a space
+    Polyline,
+    /// GeoJSON MultiLineString geometry
+    GeoJson,
+    /// hexadecimal little endian WKB MultiLineString
+    WkbHex,
+}
+
+impl FromStr for GeometryEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wkt" => Ok(GeometryEncoding::Wkt),
+            "polyline" => Ok(GeometryEncoding::Polyline),
+            "geojson" => Ok(GeometryEncoding::GeoJson),
+            "wkb" => Ok(GeometryEncoding::WkbHex),
+            _ => Err(format!(
+                "unknown geometry encoding {} (expected wkt, polyline, geojson or wkb)",
+                s
+            )),
+        }
+    }
+}
+
+fn encode_polyline_value(value: f64, previous: &mut i64, encoded: &mut String) {
+    let value = (value * 1e5).round() as i64;
+    let delta = value - *previous;
+    *previous = value;
+    let mut bits = if delta < 0 { !(delta << 1) } else { delta << 1 };
+    while bits >= 0x20 {
+        encoded.push(char::from((((bits & 0x1f) | 0x20) + 63) as u8));
+        bits >>= 5;
+    }
+    encoded.push(char::from((bits + 63) as u8));
+}
+
+/// Google encoded polyline of a way (precision 5, latitude first).
+pub fn encode_polyline(way: &[Coord]) -> String {
+    let mut encoded = String::new();
+    let (mut lat, mut lon) = (0, 0);
+    for coord in way {
+        encode_polyline_value(coord.lat, &mut lat, &mut encoded);
+        encode_polyline_value(coord.lon, &mut lon, &mut encoded);
+    }
+    encoded
+}
+
+fn push_wkb_header(wkb: &mut Vec<u8>, geometry_type: u32, count: usize) {
+    wkb.push(1);
+    wkb.extend(&geometry_type.to_le_bytes());
+    wkb.extend(&(count as u32).to_le_bytes());
+}
+
+fn push_wkb_points(wkb: &mut Vec<u8>, linestring: &LineString<f64>) {
+    wkb.extend(&(linestring.0.len() as u32).to_le_bytes());
+    for c in &linestring.0 {
+        wkb.extend(&c.x.to_le_bytes());
+        wkb.extend(&c.y.to_le_bytes());
+    }
+}
+
+fn push_wkb_polygon(wkb: &mut Vec<u8>, polygon: &Polygon<f64>) {
+    let rings: Vec<&LineString<f64>> = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .filter(|ring| !ring.0.is_empty())
+        .collect();
+    push_wkb_header(wkb, 3, rings.len());
+    for ring in rings {
+        push_wkb_points(wkb, ring);
+    }
+}
+
+fn push_wkb(wkb: &mut Vec<u8>, geometry: &Geometry<f64>) {
+    match geometry {
+        Geometry::Point(point) => {
+            wkb.push(1);
+            wkb.extend(&1u32.to_le_bytes());
+            wkb.extend(&point.x().to_le_bytes());
+            wkb.extend(&point.y().to_le_bytes());
+        }
+        Geometry::Line(line) => {
+            push_wkb(wkb, &LineString::from(vec![line.start, line.end]).into());
+        }
+        Geometry::LineString(linestring) => {
+            wkb.push(1);
+            wkb.extend(&2u32.to_le_bytes());
+            push_wkb_points(wkb, linestring);
+        }
+        Geometry::Polygon(polygon) => push_wkb_polygon(wkb, polygon),
+        Geometry::Rect(rect) => push_wkb_polygon(wkb, &rect.to_polygon()),
+        Geometry::Triangle(triangle) => push_wkb_polygon(wkb, &triangle.to_polygon()),
+        Geometry::MultiPoint(multi_point) => {
+            push_wkb_header(wkb, 4, multi_point.0.len());
+            for point in &multi_point.0 {
+                push_wkb(wkb, &(*point).into());
+            }
+        }
+        Geometry::MultiLineString(multi_line_string) => {
+            push_wkb_header(wkb, 5, multi_line_string.0.len());
+            for linestring in &multi_line_string.0 {
+                wkb.push(1);
+                wkb.extend(&2u32.to_le_bytes());
+                push_wkb_points(wkb, linestring);
+            }
+        }
+        Geometry::MultiPolygon(multi_polygon) => {
+            push_wkb_header(wkb, 6, multi_polygon.0.len());
+            for polygon in &multi_polygon.0 {
+                push_wkb_polygon(wkb, polygon);
+            }
+        }
+        Geometry::GeometryCollection(collection) => {
+            push_wkb_header(wkb, 7, collection.0.len());
+            for geometry in &collection.0 {
+                push_wkb(wkb, geometry);
+            }
+        }
+    }
+}
+
+/// WKB (little endian) of a geometry. Lines are written as LineStrings, rectangles and
+/// triangles as Polygons.
+pub fn geometry_to_wkb(geometry: &Geometry<f64>) -> Vec<u8> {
+    let mut wkb = vec![];
+    push_wkb(&mut wkb, geometry);
+    wkb
+}
+
+/// Hexadecimal WKB (little endian) of a shape as a MultiLineString.
+pub fn shape_to_wkb_hex(shape: &[Vec<Coord>]) -> String {
+    geometry_to_wkb(&ways_to_multi_line_string(shape).into())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// Encodes a shape, without its degenerate ways (less than 2 points).
+pub fn encode_shape(shape: &[Vec<Coord>], encoding: GeometryEncoding) -> String {
+    let shape: Vec<Vec<Coord>> = shape.iter().filter(|way| way.len() >= 2).cloned().collect();
+    let shape = shape.as_slice();
+    match encoding {
+        GeometryEncoding::Wkt => shape_to_wkt(shape),
+        GeometryEncoding::Polyline => shape
+            .iter()
+            .map(|way| encode_polyline(way))
+            .collect::<Vec<String>>()
+            .join(" "),
+        GeometryEncoding::GeoJson => multi_line_string_geometry(shape).to_string(),
+        GeometryEncoding::WkbHex => shape_to_wkb_hex(shape),
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::opening_hours::{
+    format_days, OpeningHours, TimeRange, Weekday, DAY_MINUTES, WEEKDAYS, WHOLE_DAY,
+};
+use serde_derive::{Deserialize, Serialize};
+
+/// Headway of a Route or a Line on some days, in a time range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadwayWindow {
+    pub headway_secs: u32,
+    pub weekdays: Vec<Weekday>,
+    pub public_holidays: bool,
+    pub school_holidays: bool,
+    pub time_range: TimeRange,
+    /// from `interval:conditional`, overriding the `interval` in this window
+    pub conditional: bool,
+}
+
+impl HeadwayWindow {
+    pub fn days(&self) -> String {
+        format_days(&self.weekdays, self.public_holidays, self.school_holidays)
+    }
+}
+
+/// Parses an `interval` value (`mm`, `hh:mm` or `hh:mm:ss`) as seconds.
+pub fn parse_interval(interval: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid interval {}", interval);
+    let parts: Vec<u32> = interval
+        .trim()
+        .split(':')
+        .map(|p| {
+            if p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            p.parse().map_err(|_| invalid())
+        })
+        .collect::<Result<_, _>>()?;
+    if parts.iter().skip(1).any(|p| *p >= 60) {
+        return Err(invalid());
+    }
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [minutes] => (0, *minutes, 0),
+        [hours, minutes] => (*hours, *minutes, 0),
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        _ => return Err(invalid()),
+    };
+    // the overflows are invalid intervals
+    let secs = hours
+        .checked_mul(3600)
+        .and_then(|h| minutes.checked_mul(60).and_then(|m| h.checked_add(m)))
+        .and_then(|hm| hm.checked_add(seconds))
+        .ok_or_else(invalid)?;
+    if secs == 0 {
+        return Err(invalid());
+    }
+    Ok(secs)
+}
+
+/// A headway on a single day: the index of a weekday, or `PUBLIC_HOLIDAYS` or
+/// `SCHOOL_HOLIDAYS`.
+struct DayHeadway {
+    day: usize,
+    time_range: TimeRange,
+    headway_secs: u32,
+    conditional: bool,
+}
+
+const PUBLIC_HOLIDAYS: usize = 7;
+const SCHOOL_HOLIDAYS: usize = 8;
+
+/// The part of a time range of a day that falls on another day, when it is the same day,
+/// the next weekday (after midnight) or the previous one (before midnight of this one).
+fn time_range_on(day: usize, time_range: &TimeRange, other_day: usize) -> Option<TimeRange> {
+    if day == other_day {
+        Some(*time_range)
+    } else if day < 7 && other_day == (day + 1) % 7 && time_range.end > DAY_MINUTES {
+        Some(TimeRange {
+            start: time_range.start.saturating_sub(DAY_MINUTES),
+            end: time_range.end - DAY_MINUTES,
+        })
+    } else if other_day < 7 && day == (other_day + 1) % 7 {
+        Some(TimeRange {
+            start: time_range.start + DAY_MINUTES,
+            end: time_range.end + DAY_MINUTES,
+        })
+    } else {
+        None
+    }
+}
+
+/// Makes the windows distinct : each window overrides the earlier ones on its days and
+/// time range (the ones of the `interval` are split around the ones of its conditions),
+/// then the days with the same time range and headway are grouped again.
+fn split_windows(windows: Vec<HeadwayWindow>) -> Vec<HeadwayWindow> {
+    let mut day_headways: Vec<DayHeadway> = vec![];
+    for window in windows {
+        let days = window
+            .weekdays
+            .iter()
+            .map(|d| *d as usize)
+            .chain(window.public_holidays.then_some(PUBLIC_HOLIDAYS))
+            .chain(window.school_holidays.then_some(SCHOOL_HOLIDAYS));
+        for day in days {
+            day_headways = day_headways
+                .into_iter()
+                .flat_map(|h| {
+                    let time_ranges = match time_range_on(day, &window.time_range, h.day) {
+                        Some(overridden) => h.time_range.minus(&overridden),
+                        None => vec![h.time_range],
+                    };
+                    time_ranges
+                        .into_iter()
+                        .map(move |time_range| DayHeadway { time_range, ..h })
+                })
+                .collect();
+            day_headways.push(DayHeadway {
+                day,
+                time_range: window.time_range,
+                headway_secs: window.headway_secs,
+                conditional: window.conditional,
+            });
+        }
+    }
+    let mut split: Vec<HeadwayWindow> = vec![];
+    for h in day_headways {
+        let idx = match split.iter().position(|w| {
+            w.time_range == h.time_range
+                && w.headway_secs == h.headway_secs
+                && w.conditional == h.conditional
+        }) {
+            Some(idx) => idx,
+            None => {
+                split.push(HeadwayWindow {
+                    headway_secs: h.headway_secs,
+                    weekdays: vec![],
+                    public_holidays: false,
+                    school_holidays: false,
+                    time_range: h.time_range,
+                    conditional: h.conditional,
+                });
+                split.len() - 1
+            }
+        };
+        match h.day {
+            PUBLIC_HOLIDAYS => split[idx].public_holidays = true,
+            SCHOOL_HOLIDAYS => split[idx].school_holidays = true,
+            day => split[idx].weekdays.push(WEEKDAYS[day]),
+        }
+    }
+    for window in split.iter_mut() {
+        window.weekdays.sort();
+        window.weekdays.dedup();
+    }
+    split
+}
+
+/// Splits on `;` outside of parentheses.
+fn split_conditions(value: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (idx, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth == 0 => {
+                parts.push(&value[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+        .into_iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Parses the `interval` and `interval:conditional` tags as headway windows: the
+/// `interval` every day and the whole day, then each `<interval> @ (<opening_hours>)`
+/// of `interval:conditional` on the days and time ranges of its condition. The windows
+/// do not overlap : the later ones override the earlier ones, which are split around them.
+pub fn parse_headway_windows(
+    interval: &str,
+    interval_conditional: &str,
+) -> Result<Vec<HeadwayWindow>, String> {
+    let mut windows = vec![];
+    if !interval.trim().is_empty() {
+        windows.push(HeadwayWindow {
+            headway_secs: parse_interval(interval)?,
+            weekdays: WEEKDAYS.to_vec(),
+            public_holidays: false,
+            school_holidays: false,
+            time_range: WHOLE_DAY,
+            conditional: false,
+        });
+    }
+    for condition in split_conditions(interval_conditional) {
+        let (interval, condition) = condition
+            .split_once('@')
+            .ok_or_else(|| format!("missing condition in {}", condition))?;
+        let headway_secs = parse_interval(interval)?;
+        let condition = condition.trim();
+        let condition = condition
+            .strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .unwrap_or(condition);
+        let calendar: OpeningHours = condition.parse()?;
+        for rule in calendar.rules {
+            if rule.off {
+                return Err(format!("off in condition {}", condition));
+            }
+            for time_range in &rule.time_ranges {
+                windows.push(HeadwayWindow {
+                    headway_secs,
+                    weekdays: rule.weekdays.clone(),
+                    public_holidays: rule.public_holidays,
+                    school_holidays: rule.school_holidays,
+                    time_range: *time_range,
+                    conditional: true,
+                });
+            }
+        }
+    }
+    Ok(split_windows(windows))
+}
+
+/// The headway windows of a Route or a Line from its tags, empty when they are invalid.
+pub fn headways_from_tags(tags: &osmpbfreader::Tags) -> Vec<HeadwayWindow> {
+    let tag = |key: &str| tags.get(key).map_or("", |v| v.as_str());
+    parse_headway_windows(tag("interval"), tag("interval:conditional")).unwrap_or_default()
+}
@@ -0,0 +1,336 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::metrics::Metrics;
+use crate::tags::TagsMode;
+use crate::wkt_format::hull_to_geometry;
+use crate::{Coord, Line, Route, StopArea, StopPoint};
+use geo_types::Geometry;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+fn point_geometry(coord: &Coord) -> Value {
+    json!({
+        "type": "Point",
+        "coordinates": [coord.lon, coord.lat],
+    })
+}
+
+fn rings_coordinates(polygon: &geo_types::Polygon<f64>) -> Vec<Vec<[f64; 2]>> {
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(|ring| ring.0.iter().map(|c| [c.x, c.y]).collect())
+        .collect()
+}
+
+/// GeoJSON geometry of a Point, LineString, Polygon or MultiPolygon.
+fn geo_geometry(geometry: &Geometry<f64>) -> Value {
+    match geometry {
+        Geometry::Point(point) => json!({
+            "type": "Point",
+            "coordinates": [point.x(), point.y()],
+        }),
+        Geometry::LineString(linestring) => {
+            let coordinates: Vec<[f64; 2]> = linestring.0.iter().map(|c| [c.x, c.y]).collect();
+            json!({
+                "type": "LineString",
+                "coordinates": coordinates,
+            })
+        }
+        Geometry::Polygon(polygon) => json!({
+            "type": "Polygon",
+            "coordinates": rings_coordinates(polygon),
+        }),
+        Geometry::MultiPolygon(multi_polygon) => {
+            let coordinates: Vec<Vec<Vec<[f64; 2]>>> =
+                multi_polygon.0.iter().map(rings_coordinates).collect();
+            json!({
+                "type": "MultiPolygon",
+                "coordinates": coordinates,
+            })
+        }
+        _ => Value::Null,
+    }
+}
+
+pub(crate) fn multi_line_string_geometry(shape: &[Vec<Coord>]) -> Value {
+    let coordinates: Vec<Vec<[f64; 2]>> = shape
+        .iter()
+        .map(|way| way.iter().map(|c| [c.lon, c.lat]).collect())
+        .collect();
+    json!({
+        "type": "MultiLineString",
+        "coordinates": coordinates,
+    })
+}
+
+/// Adds the tags to the properties: with `TagsMode::Columns` the tags of the object
+/// as `osm:<key>` properties, with `TagsMode::Whitelist` the given tags (null when missing),
+/// and with `TagsMode::Json` all the tags as an `osm_tags` object.
+fn feature(
+    geometry: Value,
+    mut properties: Map<String, Value>,
+    all_osm_tags: &osmpbfreader::objects::Tags,
+    tags_mode: &TagsMode,
+) -> Value {
+    match tags_mode {
+        TagsMode::None => {}
+        TagsMode::Columns => {
+            for (k, v) in all_osm_tags.iter() {
+                properties.insert(format!("osm:{}", k), Value::String(v.to_string()));
+            }
+        }
+        TagsMode::Whitelist(keys) => {
+            for k in keys {
+                let value = all_osm_tags.get(k.as_str()).map(|v| v.to_string());
+                properties.insert(format!("osm:{}", k), json!(value));
+            }
+        }
+        TagsMode::Json => {
+            let tags: Map<String, Value> = all_osm_tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                .collect();
+            properties.insert("osm_tags".to_string(), Value::Object(tags));
+        }
+    }
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+fn properties(values: Vec<(&str, Value)>) -> Map<String, Value> {
+    values
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+}
+
+fn with_metrics(mut properties: Map<String, Value>, metrics: &Metrics) -> Map<String, Value> {
+    let bbox = metrics
+        .bbox
+        .as_ref()
+        .map(|b| [b.min_lon, b.min_lat, b.max_lon, b.max_lat]);
+    properties.insert("length".to_string(), json!(metrics.length));
+    properties.insert("stop_count".to_string(), json!(metrics.stop_count));
+    properties.insert(
+        "mean_stop_distance".to_string(),
+        json!(metrics.mean_stop_distance),
+    );
+    properties.insert(
+        "max_stop_distance".to_string(),
+        json!(metrics.max_stop_distance),
+    );
+    properties.insert("bbox".to_string(), json!(bbox));
+    properties.insert(
+        "origin_destination_distance".to_string(),
+        json!(metrics.origin_destination_distance),
+    );
+    properties
+}
+
+pub(crate) const STOP_POINTS_FILE: &str = "osm-transit-extractor_stop_points.geojson";
+pub(crate) const STOP_AREAS_FILE: &str = "osm-transit-extractor_stop_areas.geojson";
+pub(crate) const ROUTES_FILE: &str = "osm-transit-extractor_routes.geojson";
+pub(crate) const LINES_FILE: &str = "osm-transit-extractor_lines.geojson";
+
+/// Writes a feature collection one feature at a time.
+pub(crate) struct FeatureCollectionWriter {
+    wtr: BufWriter<File>,
+    empty: bool,
+}
+
+impl FeatureCollectionWriter {
+    pub(crate) fn new(file: &Path) -> Self {
+        let mut wtr = BufWriter::new(File::create(file).unwrap());
+        write!(wtr, r#"{{"type":"FeatureCollection","features":["#).unwrap();
+        FeatureCollectionWriter { wtr, empty: true }
+    }
+
+    pub(crate) fn write(&mut self, feature: &Value) {
+        if !self.empty {
+            write!(self.wtr, ",").unwrap();
+        }
+        serde_json::to_writer(&mut self.wtr, feature).unwrap();
+        self.empty = false;
+    }
+
+    pub(crate) fn finish(&mut self) {
+        write!(self.wtr, "]}}").unwrap();
+        self.wtr.flush().unwrap();
+    }
+}
+
+pub(crate) fn stop_point_feature(sp: &StopPoint, tags_mode: &TagsMode) -> Value {
+    let props = properties(vec![
+        ("stop_point_id", json!(format!("StopPoint:{}", sp.id))),
+        ("name", json!(sp.name)),
+        (
+            "stop_point_type",
+            json!(format!("{:?}", sp.stop_point_type)),
+        ),
+    ]);
+    let geometry = match &sp.geometry {
+        Some(geometry) => geo_geometry(geometry),
+        None => point_geometry(&sp.coord),
+    };
+    feature(geometry, props, &sp.all_osm_tags, tags_mode)
+}
+
+pub(crate) fn stop_area_feature(sa: &StopArea, tags_mode: &TagsMode) -> Value {
+    let stop_point_ids: Vec<String> = sa
+        .stop_point_ids
+        .iter()
+        .map(|id| format!("StopPoint:{}", id))
+        .collect();
+    let props = properties(vec![
+        ("stop_area_id", json!(format!("StopArea:{}", sa.id))),
+        ("name", json!(sa.name)),
+        ("stop_point_ids", json!(stop_point_ids)),
+    ]);
+    // the hull of the members when it is an area, the coord of the stop area otherwise
+    let geometry = match hull_to_geometry(&sa.hull) {
+        hull @ Geometry::Polygon(_) if !sa.hull.is_empty() => geo_geometry(&hull),
+        _ => point_geometry(&sa.coord),
+    };
+    feature(geometry, props, &sa.all_osm_tags, tags_mode)
+}
+
+pub(crate) fn route_feature(r: &Route, tags_mode: &TagsMode) -> Value {
+    let props = properties(vec![
+        ("route_id", json!(format!("Route:{}", r.id))),
+        ("name", json!(r.name)),
+        ("code", json!(r.code)),
+        ("destination", json!(r.destination)),
+        ("origin", json!(r.origin)),
+        ("colour", json!(r.colour)),
+        ("text_colour", json!(r.text_colour)),
+        ("operator", json!(r.operator)),
+        ("network", json!(r.network)),
+        ("mode", json!(r.mode)),
+        (
+            "sub_mode",
+            json!(r.transport_mode.sub_mode.map(|s| s.to_string())),
+        ),
+        ("physical_mode", json!(r.transport_mode.physical_mode())),
+        ("commercial_mode", json!(r.transport_mode.commercial_mode())),
+        ("netex_mode", json!(r.transport_mode.netex_mode())),
+        ("netex_sub_mode", json!(r.transport_mode.netex_sub_mode())),
+        ("gtfs_route_type", json!(r.transport_mode.gtfs_route_type())),
+        ("direction_id", json!(r.direction_id)),
+        ("variant", json!(r.variant.map(|v| v.to_string()))),
+        ("roundtrip", json!(r.roundtrip)),
+    ]);
+    let props = with_metrics(props, &r.metrics);
+    feature(
+        multi_line_string_geometry(&r.shape),
+        props,
+        &r.all_osm_tags,
+        tags_mode,
+    )
+}
+
+pub(crate) fn line_feature(l: &Line, tags_mode: &TagsMode) -> Value {
+    let props = properties(vec![
+        ("line_id", json!(format!("Line:{}", l.id))),
+        ("name", json!(l.name)),
+        ("code", json!(l.code)),
+        ("colour", json!(l.colour)),
+        ("text_colour", json!(l.text_colour)),
+        ("operator", json!(l.operator)),
+        ("network", json!(l.network)),
+        ("mode", json!(l.mode)),
+        (
+            "sub_mode",
+            json!(l.transport_mode.sub_mode.map(|s| s.to_string())),
+        ),
+        ("physical_mode", json!(l.transport_mode.physical_mode())),
+        ("commercial_mode", json!(l.transport_mode.commercial_mode())),
+        ("netex_mode", json!(l.transport_mode.netex_mode())),
+        ("netex_sub_mode", json!(l.transport_mode.netex_sub_mode())),
+        ("gtfs_route_type", json!(l.transport_mode.gtfs_route_type())),
+    ]);
+    let props = with_metrics(props, &l.metrics);
+    feature(
+        multi_line_string_geometry(&l.shape),
+        props,
+        &l.all_osm_tags,
+        tags_mode,
+    )
+}
+
+fn write_features<T, F: Fn(&T, &TagsMode) -> Value>(
+    objects: &[T],
+    file: &Path,
+    to_feature: F,
+    tags_mode: &TagsMode,
+) {
+    let mut wtr = FeatureCollectionWriter::new(file);
+    for object in objects {
+        wtr.write(&to_feature(object, tags_mode));
+    }
+    wtr.finish();
+}
+
+pub fn write_stop_points_to_geojson<P: AsRef<Path>>(
+    stop_points: &[StopPoint],
+    output_dir: P,
+    tags_mode: &TagsMode,
+) {
+    let file = output_dir.as_ref().join(STOP_POINTS_FILE);
+    write_features(stop_points, &file, stop_point_feature, tags_mode);
+}
+
+pub fn write_stop_areas_to_geojson<P: AsRef<Path>>(
+    stop_areas: &[StopArea],
+    output_dir: P,
+    tags_mode: &TagsMode,
+) {
+    let file = output_dir.as_ref().join(STOP_AREAS_FILE);
+    write_features(stop_areas, &file, stop_area_feature, tags_mode);
+}
+
+pub fn write_routes_to_geojson<P: AsRef<Path>>(
+    routes: &[Route],
+    output_dir: P,
+    tags_mode: &TagsMode,
+) {
+    let file = output_dir.as_ref().join(ROUTES_FILE);
+    write_features(routes, &file, route_feature, tags_mode);
+}
+
+pub fn write_lines_to_geojson<P: AsRef<Path>>(lines: &[Line], output_dir: P, tags_mode: &TagsMode) {
+    let file = output_dir.as_ref().join(LINES_FILE);
+    write_features(lines, &file, line_feature, tags_mode);
+}
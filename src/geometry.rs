@@ -0,0 +1,454 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::pairing::MAX_PAIRING_DISTANCE;
+use crate::{Coord, Route, StopArea, StopPoint};
+use geo_types::{Geometry, LineString};
+use log::warn;
+use std::collections::BTreeMap;
+
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Distance (in meters) above which a stop is considered too far from the shape of its route.
+pub const MAX_STOP_DISTANCE_TO_SHAPE: f64 = 100.;
+
+/// Great-circle distance in meters between two coordinates (haversine formula).
+pub fn distance(a: &Coord, b: &Coord) -> f64 {
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.lon - a.lon).to_radians();
+    let h = (d_lat / 2.).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Length in meters of a linestring.
+pub fn length(linestring: &[Coord]) -> f64 {
+    linestring.windows(2).map(|w| distance(&w[0], &w[1])).sum()
+}
+
+/// Joins the ways of a shape into a single linestring, reversing the ways when needed
+/// so that each one starts where the previous one ends.
+pub fn stitch_shape(shape: &[Vec<Coord>]) -> Vec<Coord> {
+    let mut ways = shape.iter().filter(|way| !way.is_empty());
+    let mut stitched: Vec<Coord> = match ways.next() {
+        Some(first) => first.clone(),
+        None => return vec![],
+    };
+    let mut is_first_way = true;
+    for way in ways {
+        let (first, last) = (&way[0], &way[way.len() - 1]);
+        if is_first_way {
+            // the first way is oriented towards the second one
+            let start = &stitched[0];
+            let end = &stitched[stitched.len() - 1];
+            let gap_from_end = distance(end, first).min(distance(end, last));
+            let gap_from_start = distance(start, first).min(distance(start, last));
+            if gap_from_start < gap_from_end {
+                stitched.reverse();
+            }
+            is_first_way = false;
+        }
+        let end = stitched[stitched.len() - 1].clone();
+        if distance(&end, last) < distance(&end, first) {
+            stitched.extend(way.iter().rev().skip_while(|c| **c == end).cloned());
+        } else {
+            stitched.extend(way.iter().skip_while(|c| **c == end).cloned());
+        }
+    }
+    stitched
+}
+
+fn cross(o: &Coord, a: &Coord, b: &Coord) -> f64 {
+    (a.lon - o.lon) * (b.lat - o.lat) - (a.lat - o.lat) * (b.lon - o.lon)
+}
+
+/// Convex hull of points (monotone chain), as a closed ring in counterclockwise order.
+/// When the points are all the same or aligned, the hull is the point or the two
+/// ends of the segment.
+pub fn convex_hull(points: &[Coord]) -> Vec<Coord> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.lon.total_cmp(&b.lon).then(a.lat.total_cmp(&b.lat)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut lower: Vec<Coord> = vec![];
+    for p in &points {
+        while lower.len() >= 2 && cross(&lower[lower.len() - 2], &lower[lower.len() - 1], p) <= 0. {
+            lower.pop();
+        }
+        lower.push(p.clone());
+    }
+    let mut upper: Vec<Coord> = vec![];
+    for p in points.iter().rev() {
+        while upper.len() >= 2 && cross(&upper[upper.len() - 2], &upper[upper.len() - 1], p) <= 0. {
+            upper.pop();
+        }
+        upper.push(p.clone());
+    }
+    lower.pop();
+    upper.pop();
+    let mut hull = lower;
+    hull.extend(upper);
+    if hull.len() < 3 {
+        // aligned points
+        return vec![points[0].clone(), points[points.len() - 1].clone()];
+    }
+    hull.push(hull[0].clone());
+    hull
+}
+
+/// Coordinates of the outline of a geometry (the holes of polygons are left out).
+pub fn geometry_coords(geometry: &Geometry<f64>) -> Vec<Coord> {
+    let to_coords = |linestring: &LineString<f64>| -> Vec<Coord> {
+        linestring
+            .0
+            .iter()
+            .map(|c| Coord { lat: c.y, lon: c.x })
+            .collect()
+    };
+    match geometry {
+        Geometry::Point(point) => vec![Coord {
+            lat: point.y(),
+            lon: point.x(),
+        }],
+        Geometry::LineString(linestring) => to_coords(linestring),
+        Geometry::Polygon(polygon) => to_coords(polygon.exterior()),
+        Geometry::MultiLineString(multi_line_string) => {
+            multi_line_string.0.iter().flat_map(to_coords).collect()
+        }
+        Geometry::MultiPolygon(multi_polygon) => multi_polygon
+            .0
+            .iter()
+            .flat_map(|polygon| to_coords(polygon.exterior()))
+            .collect(),
+        Geometry::MultiPoint(multi_point) => multi_point
+            .0
+            .iter()
+            .flat_map(|point| geometry_coords(&(*point).into()))
+            .collect(),
+        Geometry::GeometryCollection(collection) => {
+            collection.0.iter().flat_map(geometry_coords).collect()
+        }
+        Geometry::Line(line) => to_coords(&LineString::from(vec![line.start, line.end])),
+        Geometry::Rect(rect) => to_coords(rect.to_polygon().exterior()),
+        Geometry::Triangle(triangle) => to_coords(triangle.to_polygon().exterior()),
+    }
+}
+
+/// Coordinates of a stop point: the outline of its geometry when it has one,
+/// its coord otherwise.
+pub fn stop_point_coords(stop_point: &StopPoint) -> Vec<Coord> {
+    match &stop_point.geometry {
+        Some(geometry) => geometry_coords(geometry),
+        None => vec![stop_point.coord.clone()],
+    }
+}
+
+/// Sets the hull of each stop area from the geometries of its members.
+pub fn compute_stop_areas_hull(stop_areas: &mut [StopArea], stop_points: &[StopPoint]) {
+    let stop_points: BTreeMap<&str, &StopPoint> =
+        stop_points.iter().map(|sp| (sp.id.as_str(), sp)).collect();
+    for sa in stop_areas.iter_mut() {
+        let members: Vec<Coord> = sa
+            .stop_point_ids
+            .iter()
+            .chain(&sa.stop_position_ids)
+            .filter_map(|id| stop_points.get(id.as_str()))
+            .flat_map(|sp| stop_point_coords(sp))
+            .collect();
+        sa.hull = convex_hull(&members);
+    }
+}
+
+/// Joins ways sharing their ends into rings. The ways that can't be closed are dropped.
+pub fn assemble_rings(ways: &[Vec<Coord>]) -> Vec<Vec<Coord>> {
+    let mut remaining: Vec<Vec<Coord>> = ways.iter().filter(|w| w.len() >= 2).cloned().collect();
+    let mut rings = vec![];
+    while let Some(mut ring) = remaining.pop() {
+        while ring.first() != ring.last() {
+            let end = ring[ring.len() - 1].clone();
+            let next = remaining
+                .iter()
+                .position(|w| w[0] == end || w[w.len() - 1] == end);
+            match next {
+                Some(idx) => {
+                    let mut way = remaining.remove(idx);
+                    if way[0] != end {
+                        way.reverse();
+                    }
+                    ring.extend(way.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+        if ring.len() >= 4 && ring.first() == ring.last() {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Tells if a point is inside a ring (ray casting).
+pub fn is_in_ring(point: &Coord, ring: &[Coord]) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let (a, b) = (&w[0], &w[1]);
+        if (a.lat > point.lat) != (b.lat > point.lat)
+            && point.lon < a.lon + (point.lat - a.lat) / (b.lat - a.lat) * (b.lon - a.lon)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Projection of a point on a linestring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    /// distance in meters from the start of the linestring to the projected point
+    pub distance_along: f64,
+    /// distance in meters between the point and the projected point
+    pub distance_to_line: f64,
+}
+
+fn project_on_segment(point: &Coord, a: &Coord, b: &Coord) -> (Coord, f64) {
+    // local equirectangular approximation, good enough for a segment of a shape
+    let scale = point.lat.to_radians().cos();
+    let (ax, ay) = (a.lon * scale, a.lat);
+    let (bx, by) = (b.lon * scale, b.lat);
+    let (px, py) = (point.lon * scale, point.lat);
+    let (dx, dy) = (bx - ax, by - ay);
+    let norm = dx * dx + dy * dy;
+    let t = if norm == 0. {
+        0.
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / norm).clamp(0., 1.)
+    };
+    let projected = Coord {
+        lat: a.lat + t * (b.lat - a.lat),
+        lon: a.lon + t * (b.lon - a.lon),
+    };
+    (projected, t)
+}
+
+/// Projects a point on a linestring, only considering the part of the linestring
+/// located after `from_distance` meters.
+pub fn project(point: &Coord, linestring: &[Coord], from_distance: f64) -> Option<Projection> {
+    let mut best: Option<Projection> = None;
+    let mut segment_start = 0.;
+    for w in linestring.windows(2) {
+        let segment_length = distance(&w[0], &w[1]);
+        let segment_end = segment_start + segment_length;
+        if segment_end >= from_distance {
+            let (projected, t) = project_on_segment(point, &w[0], &w[1]);
+            let distance_along = (segment_start + t * segment_length).max(from_distance);
+            let distance_to_line = distance(point, &projected);
+            if best
+                .as_ref()
+                .map_or(true, |b| distance_to_line < b.distance_to_line)
+            {
+                best = Some(Projection {
+                    distance_along,
+                    distance_to_line,
+                });
+            }
+        }
+        segment_start = segment_end;
+    }
+    best
+}
+
+/// Rotates a closed linestring so that it starts (and ends) `distance_along` meters
+/// from its start.
+fn rotate_ring(ring: &[Coord], distance_along: f64) -> Vec<Coord> {
+    let start = match interpolate(ring, distance_along) {
+        Some(start) => start,
+        None => return ring.to_vec(),
+    };
+    let mut before = vec![];
+    let mut rotated = vec![start.clone()];
+    let mut distance_from_start = 0.;
+    for (i, coord) in ring.iter().enumerate() {
+        if i > 0 {
+            distance_from_start += distance(&ring[i - 1], coord);
+        }
+        if distance_from_start > distance_along {
+            rotated.push(coord.clone());
+        } else if distance_from_start < distance_along {
+            before.push(coord.clone());
+        }
+    }
+    rotated.extend(before);
+    rotated.push(start);
+    rotated.dedup();
+    rotated
+}
+
+/// The stitched shape of a route. The shape of a circular route, when it is closed,
+/// starts at its first stop and goes towards its next stops.
+pub fn route_linestring(route: &Route, stop_points: &BTreeMap<&str, &StopPoint>) -> Vec<Coord> {
+    let linestring = stitch_shape(&route.shape);
+    if !route.roundtrip
+        || linestring.len() < 3
+        || distance(&linestring[0], &linestring[linestring.len() - 1]) > MAX_STOP_DISTANCE_TO_SHAPE
+    {
+        return linestring;
+    }
+    let mut stop_coords = route
+        .ordered_route_points
+        .iter()
+        .filter_map(|rp| stop_points.get(rp.stop_point_id.as_str()))
+        .map(|sp| &sp.coord);
+    let first = match stop_coords.next() {
+        Some(first) => first,
+        None => return linestring,
+    };
+    let start = match project(first, &linestring, 0.) {
+        Some(start) => start,
+        None => return linestring,
+    };
+    let mut ring = rotate_ring(&linestring, start.distance_along);
+    // the next stop must be closer from the start going forward than going backward
+    if let Some(next) = stop_coords.find(|c| distance(first, c) > MAX_PAIRING_DISTANCE) {
+        let forward = project(next, &ring, 0.);
+        ring.reverse();
+        let backward = project(next, &ring, 0.);
+        let is_forward = match (forward, backward) {
+            (Some(f), Some(b)) => f.distance_along <= b.distance_along,
+            _ => true,
+        };
+        if is_forward {
+            ring.reverse();
+        }
+    }
+    ring
+}
+
+/// Sets on each route point its distance along the stitched shape of the route
+/// and its distance to the shape.
+///
+/// Each stop is searched on the part of the shape located after the previous stop,
+/// which keeps the distances increasing on routes going several times through the
+/// same place. When the stop is too far from this part of the shape it is projected
+/// on the whole shape, and a warning is logged if its distance along the shape goes backwards.
+pub fn locate_route_points(route: &mut Route, stop_points: &BTreeMap<&str, &StopPoint>) {
+    let linestring = route_linestring(route, stop_points);
+    if linestring.len() < 2 {
+        return;
+    }
+    let mut previous_distance = 0.;
+    for rp in route.ordered_route_points.iter_mut() {
+        let coord = match stop_points.get(rp.stop_point_id.as_str()) {
+            Some(sp) => &sp.coord,
+            None => continue,
+        };
+        let mut projection = project(coord, &linestring, previous_distance);
+        if projection
+            .as_ref()
+            .map_or(true, |p| p.distance_to_line > MAX_STOP_DISTANCE_TO_SHAPE)
+        {
+            let global_projection = project(coord, &linestring, 0.);
+            let is_closer = match (&global_projection, &projection) {
+                (Some(global), Some(forward)) => global.distance_to_line < forward.distance_to_line,
+                (global, _) => global.is_some(),
+            };
+            if is_closer {
+                projection = global_projection;
+            }
+        }
+        if let Some(projection) = projection {
+            if projection.distance_to_line > MAX_STOP_DISTANCE_TO_SHAPE {
+                warn!(
+                    "stop {} of route {} is {:.0}m away from the route shape",
+                    rp.stop_point_id, route.id, projection.distance_to_line
+                );
+            }
+            if projection.distance_along < previous_distance {
+                warn!(
+                    "stop {} of route {} is located before the previous stop on the route shape",
+                    rp.stop_point_id, route.id
+                );
+            }
+            previous_distance = projection.distance_along;
+            rp.shape_dist_traveled = Some(projection.distance_along);
+            rp.distance_to_shape = Some(projection.distance_to_line);
+        }
+    }
+}
+
+pub fn locate_routes_points(routes: &mut [Route], stop_points: &[StopPoint]) {
+    let stop_points: BTreeMap<&str, &StopPoint> =
+        stop_points.iter().map(|sp| (sp.id.as_str(), sp)).collect();
+    for route in routes.iter_mut() {
+        locate_route_points(route, &stop_points);
+    }
+}
+
+impl Route {
+    /// Distances in meters along the shape between each located stop and the previous one.
+    pub fn inter_stop_distances(&self) -> Vec<Option<f64>> {
+        let mut previous: Option<f64> = None;
+        self.ordered_route_points
+            .iter()
+            .map(|rp| {
+                let inter_stop_distance = match (previous, rp.shape_dist_traveled) {
+                    (Some(p), Some(d)) => Some(d - p),
+                    _ => None,
+                };
+                if rp.shape_dist_traveled.is_some() {
+                    previous = rp.shape_dist_traveled;
+                }
+                inter_stop_distance
+            })
+            .collect()
+    }
+}
+
+/// Point located `distance_along` meters from the start of a linestring.
+pub fn interpolate(linestring: &[Coord], distance_along: f64) -> Option<Coord> {
+    let mut segment_start = 0.;
+    for w in linestring.windows(2) {
+        let segment_length = distance(&w[0], &w[1]);
+        if segment_start + segment_length >= distance_along {
+            let t = if segment_length == 0. {
+                0.
+            } else {
+                ((distance_along - segment_start) / segment_length).clamp(0., 1.)
+            };
+            return Some(Coord {
+                lat: w[0].lat + t * (w[1].lat - w[0].lat),
+                lon: w[0].lon + t * (w[1].lon - w[0].lon),
+            });
+        }
+        segment_start += segment_length;
+    }
+    linestring.last().cloned()
+}
@@ -0,0 +1,687 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::encoding::geometry_to_wkb;
+use crate::geometry::geometry_coords;
+use crate::metrics::Metrics;
+use crate::tags::{TagColumns, TagsMode};
+use crate::wkt_format::{hull_to_geometry, ways_to_multi_line_string};
+use crate::{OsmTcResponse, StopArea};
+use geo_types::{Geometry, Point};
+use log::warn;
+use rusqlite::types::Value;
+use rusqlite::{params, Transaction};
+use std::path::Path;
+
+const WGS84_DEFINITION: &str = "GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563]],PRIMEM[\"Greenwich\",0],UNIT[\"degree\",0.0174532925199433],AUTHORITY[\"EPSG\",\"4326\"]]";
+
+const METADATA_TABLES: &str = "
+    PRAGMA application_id = 1196444487;
+    PRAGMA user_version = 10300;
+    CREATE TABLE gpkg_spatial_ref_sys (
+        srs_name TEXT NOT NULL,
+        srs_id INTEGER NOT NULL PRIMARY KEY,
+        organization TEXT NOT NULL,
+        organization_coordsys_id INTEGER NOT NULL,
+        definition TEXT NOT NULL,
+        description TEXT
+    );
+    CREATE TABLE gpkg_contents (
+        table_name TEXT NOT NULL PRIMARY KEY,
+        data_type TEXT NOT NULL,
+        identifier TEXT UNIQUE,
+        description TEXT DEFAULT '',
+        last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+        min_x DOUBLE,
+        min_y DOUBLE,
+        max_x DOUBLE,
+        max_y DOUBLE,
+        srs_id INTEGER REFERENCES gpkg_spatial_ref_sys(srs_id)
+    );
+    CREATE TABLE gpkg_geometry_columns (
+        table_name TEXT NOT NULL REFERENCES gpkg_contents(table_name),
+        column_name TEXT NOT NULL,
+        geometry_type_name TEXT NOT NULL,
+        srs_id INTEGER NOT NULL REFERENCES gpkg_spatial_ref_sys(srs_id),
+        z TINYINT NOT NULL,
+        m TINYINT NOT NULL,
+        CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name)
+    );
+    CREATE TABLE gpkg_extensions (
+        table_name TEXT,
+        column_name TEXT,
+        extension_name TEXT NOT NULL,
+        definition TEXT NOT NULL,
+        scope TEXT NOT NULL,
+        CONSTRAINT ge_tce UNIQUE (table_name, column_name, extension_name)
+    );
+    INSERT INTO gpkg_spatial_ref_sys VALUES
+        ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'),
+        ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system');
+";
+
+type Envelope = (f64, f64, f64, f64);
+
+fn envelope(geometry: &Geometry<f64>) -> Option<Envelope> {
+    geometry_coords(geometry).iter().fold(None, |env, c| {
+        Some(match env {
+            None => (c.lon, c.lon, c.lat, c.lat),
+            Some((min_x, max_x, min_y, max_y)) => (
+                min_x.min(c.lon),
+                max_x.max(c.lon),
+                min_y.min(c.lat),
+                max_y.max(c.lat),
+            ),
+        })
+    })
+}
+
+/// GeoPackage binary geometry: a header with the srs and the envelope, followed by the WKB.
+fn gpkg_geometry(geometry: &Geometry<f64>, envelope: Option<Envelope>) -> Vec<u8> {
+    let mut blob = vec![b'G', b'P', 0];
+    match envelope {
+        // little endian, envelope [minx, maxx, miny, maxy]
+        Some((min_x, max_x, min_y, max_y)) => {
+            blob.push(0b0000_0011);
+            blob.extend(&4326i32.to_le_bytes());
+            for v in &[min_x, max_x, min_y, max_y] {
+                blob.extend(&v.to_le_bytes());
+            }
+        }
+        // little endian, no envelope, empty geometry
+        None => {
+            blob.push(0b0001_0001);
+            blob.extend(&4326i32.to_le_bytes());
+        }
+    }
+    blob.extend(geometry_to_wkb(geometry));
+    blob
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn real(value: Option<f64>) -> Value {
+    value.map_or(Value::Null, Value::Real)
+}
+
+struct Layer<'a> {
+    table: &'a str,
+    /// columns with their SQL type, without the fid and the geometry
+    columns: Vec<(String, &'static str)>,
+}
+
+impl<'a> Layer<'a> {
+    fn new(table: &'a str, columns: &[(&str, &'static str)], osm_tags: &TagColumns) -> Self {
+        Layer {
+            table,
+            columns: columns
+                .iter()
+                .map(|(name, sql_type)| (name.to_string(), *sql_type))
+                .chain(osm_tags.header().into_iter().map(|name| (name, "TEXT")))
+                .collect(),
+        }
+    }
+
+    fn columns_definition(&self) -> String {
+        self.columns
+            .iter()
+            .map(|(name, sql_type)| format!("\"{}\" {}", name.replace('"', "\"\""), sql_type))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn create_attributes(&self, tx: &Transaction) {
+        tx.execute_batch(&format!(
+            "CREATE TABLE \"{}\" (fid INTEGER PRIMARY KEY AUTOINCREMENT, {});",
+            self.table,
+            self.columns_definition()
+        ))
+        .unwrap();
+        tx.execute(
+            "INSERT INTO gpkg_contents (table_name, data_type, identifier) VALUES (?1, 'attributes', ?1)",
+            params![self.table],
+        )
+        .unwrap();
+    }
+
+    fn insert_attributes(&self, tx: &Transaction, rows: impl Iterator<Item = Vec<Value>>) {
+        let placeholders = vec!["?"; self.columns.len()].join(", ");
+        let mut stmt = tx
+            .prepare(&format!(
+                "INSERT INTO \"{}\" VALUES (NULL, {})",
+                self.table, placeholders
+            ))
+            .unwrap();
+        for row in rows {
+            stmt.execute(rusqlite::params_from_iter(row)).unwrap();
+        }
+    }
+
+    /// Creates a feature table with a `geom` column and its rtree spatial index,
+    /// and fills them.
+    fn write_features(
+        &self,
+        tx: &Transaction,
+        geometry_type: &str,
+        features: impl Iterator<Item = (Geometry<f64>, Vec<Value>)>,
+    ) {
+        let rtree = format!("rtree_{}_geom", self.table);
+        tx.execute_batch(&format!(
+            "CREATE TABLE \"{table}\" (fid INTEGER PRIMARY KEY AUTOINCREMENT, geom {geometry_type}, {columns});
+             CREATE VIRTUAL TABLE \"{rtree}\" USING rtree(id, minx, maxx, miny, maxy);",
+            table = self.table,
+            geometry_type = geometry_type,
+            columns = self.columns_definition(),
+            rtree = rtree,
+        ))
+        .unwrap();
+        let placeholders = vec!["?"; self.columns.len() + 1].join(", ");
+        let mut stmt = tx
+            .prepare(&format!(
+                "INSERT INTO \"{}\" VALUES (NULL, {})",
+                self.table, placeholders
+            ))
+            .unwrap();
+        let mut rtree_stmt = tx
+            .prepare(&format!("INSERT INTO \"{}\" VALUES (?, ?, ?, ?, ?)", rtree))
+            .unwrap();
+        let mut extent: Option<Envelope> = None;
+        for (geometry, values) in features {
+            let env = envelope(&geometry);
+            let row = std::iter::once(Value::Blob(gpkg_geometry(&geometry, env))).chain(values);
+            stmt.execute(rusqlite::params_from_iter(row)).unwrap();
+            if let Some((min_x, max_x, min_y, max_y)) = env {
+                rtree_stmt
+                    .execute(params![tx.last_insert_rowid(), min_x, max_x, min_y, max_y])
+                    .unwrap();
+                extent = Some(match extent {
+                    None => (min_x, max_x, min_y, max_y),
+                    Some(e) => (
+                        e.0.min(min_x),
+                        e.1.max(max_x),
+                        e.2.min(min_y),
+                        e.3.max(max_y),
+                    ),
+                });
+            }
+        }
+        let (min_x, max_x, min_y, max_y) = match extent {
+            Some((min_x, max_x, min_y, max_y)) => {
+                (Some(min_x), Some(max_x), Some(min_y), Some(max_y))
+            }
+            None => (None, None, None, None),
+        };
+        tx.execute(
+            "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id)
+             VALUES (?1, 'features', ?1, ?2, ?3, ?4, ?5, 4326)",
+            params![self.table, min_x, min_y, max_x, max_y],
+        )
+        .unwrap();
+        tx.execute(
+            "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', ?2, 4326, 0, 0)",
+            params![self.table, geometry_type],
+        )
+        .unwrap();
+        tx.execute(
+            "INSERT INTO gpkg_extensions VALUES (?1, 'geom', 'gpkg_rtree_index', 'http://www.geopackage.org/spec120/#extension_rtree', 'write-only')",
+            params![self.table],
+        )
+        .unwrap();
+        // created once the index is filled, as the ST_* functions of the triggers
+        // are only provided by the GeoPackage readers
+        tx.execute_batch(&RTREE_TRIGGERS.replace("TABLE", self.table))
+            .unwrap();
+    }
+}
+
+/// The triggers keeping the rtree index of the `geom` column of `TABLE` up to date,
+/// as required by the rtree extension.
+const RTREE_TRIGGERS: &str = "
+    CREATE TRIGGER \"rtree_TABLE_geom_insert\" AFTER INSERT ON \"TABLE\"
+    WHEN (new.geom NOT NULL AND NOT ST_IsEmpty(NEW.geom))
+    BEGIN
+        INSERT OR REPLACE INTO \"rtree_TABLE_geom\" VALUES (
+            NEW.fid, ST_MinX(NEW.geom), ST_MaxX(NEW.geom), ST_MinY(NEW.geom), ST_MaxY(NEW.geom)
+        );
+    END;
+    CREATE TRIGGER \"rtree_TABLE_geom_update1\" AFTER UPDATE OF geom ON \"TABLE\"
+    WHEN OLD.fid = NEW.fid AND (NEW.geom NOTNULL AND NOT ST_IsEmpty(NEW.geom))
+    BEGIN
+        INSERT OR REPLACE INTO \"rtree_TABLE_geom\" VALUES (
+            NEW.fid, ST_MinX(NEW.geom), ST_MaxX(NEW.geom), ST_MinY(NEW.geom), ST_MaxY(NEW.geom)
+        );
+    END;
+    CREATE TRIGGER \"rtree_TABLE_geom_update2\" AFTER UPDATE OF geom ON \"TABLE\"
+    WHEN OLD.fid = NEW.fid AND (NEW.geom ISNULL OR ST_IsEmpty(NEW.geom))
+    BEGIN
+        DELETE FROM \"rtree_TABLE_geom\" WHERE id = OLD.fid;
+    END;
+    CREATE TRIGGER \"rtree_TABLE_geom_update3\" AFTER UPDATE ON \"TABLE\"
+    WHEN OLD.fid != NEW.fid AND (NEW.geom NOTNULL AND NOT ST_IsEmpty(NEW.geom))
+    BEGIN
+        DELETE FROM \"rtree_TABLE_geom\" WHERE id = OLD.fid;
+        INSERT OR REPLACE INTO \"rtree_TABLE_geom\" VALUES (
+            NEW.fid, ST_MinX(NEW.geom), ST_MaxX(NEW.geom), ST_MinY(NEW.geom), ST_MaxY(NEW.geom)
+        );
+    END;
+    CREATE TRIGGER \"rtree_TABLE_geom_update4\" AFTER UPDATE ON \"TABLE\"
+    WHEN OLD.fid != NEW.fid AND (NEW.geom ISNULL OR ST_IsEmpty(NEW.geom))
+    BEGIN
+        DELETE FROM \"rtree_TABLE_geom\" WHERE id IN (OLD.fid, NEW.fid);
+    END;
+    CREATE TRIGGER \"rtree_TABLE_geom_delete\" AFTER DELETE ON \"TABLE\"
+    WHEN old.geom NOT NULL
+    BEGIN
+        DELETE FROM \"rtree_TABLE_geom\" WHERE id = OLD.fid;
+    END;
+";
+
+/// Max number of tag columns of a table, SQLite being limited to 2000 columns.
+pub const MAX_TAG_COLUMNS: usize = 1900;
+
+/// The tag columns of a table, or a single `osm_tags` JSON column when there are more
+/// than `MAX_TAG_COLUMNS` of them.
+fn tag_columns<'t, I: Iterator<Item = &'t osmpbfreader::objects::Tags>>(
+    table: &str,
+    tags_mode: &TagsMode,
+    tags: I,
+) -> TagColumns {
+    let columns = TagColumns::new(tags_mode, tags);
+    let count = columns.header().len();
+    if count > MAX_TAG_COLUMNS {
+        warn!(
+            "{} tag columns in table {}, tags written as JSON in an osm_tags column",
+            count, table
+        );
+        TagColumns::new(&TagsMode::Json, std::iter::empty())
+    } else {
+        columns
+    }
+}
+
+fn osm_tag_values(tags: &osmpbfreader::objects::Tags, osm_tags: &TagColumns) -> Vec<Value> {
+    osm_tags
+        .optional_values(tags)
+        .into_iter()
+        .map(|v| v.map_or(Value::Null, Value::Text))
+        .collect()
+}
+
+const METRICS_COLUMNS: [(&str, &str); 9] = [
+    ("length", "DOUBLE"),
+    ("stop_count", "INTEGER"),
+    ("mean_stop_distance", "DOUBLE"),
+    ("max_stop_distance", "DOUBLE"),
+    ("bbox_min_lat", "DOUBLE"),
+    ("bbox_min_lon", "DOUBLE"),
+    ("bbox_max_lat", "DOUBLE"),
+    ("bbox_max_lon", "DOUBLE"),
+    ("origin_destination_distance", "DOUBLE"),
+];
+
+fn metrics_values(metrics: &Metrics) -> Vec<Value> {
+    let bbox = metrics.bbox.as_ref();
+    vec![
+        Value::Real(metrics.length),
+        Value::Integer(metrics.stop_count as i64),
+        real(metrics.mean_stop_distance),
+        real(metrics.max_stop_distance),
+        real(bbox.map(|b| b.min_lat)),
+        real(bbox.map(|b| b.min_lon)),
+        real(bbox.map(|b| b.max_lat)),
+        real(bbox.map(|b| b.max_lon)),
+        real(metrics.origin_destination_distance),
+    ]
+}
+
+fn prefixed(prefix: &str, id: &str) -> Value {
+    if id.is_empty() {
+        Value::Null
+    } else {
+        Value::Text(format!("{}:{}", prefix, id))
+    }
+}
+
+/// Writes all the objects in a GeoPackage (`osm-transit-extractor.gpkg`), with a layer
+/// per object type, spatially indexed, and attribute tables for their relations.
+pub fn write_geopackage<P: AsRef<Path>>(
+    response: &OsmTcResponse,
+    output_dir: P,
+    tags_mode: &TagsMode,
+) {
+    let file = output_dir.as_ref().join("osm-transit-extractor.gpkg");
+    if file.exists() {
+        std::fs::remove_file(&file).unwrap();
+    }
+    let mut conn = rusqlite::Connection::open(&file).unwrap();
+    let tx = conn.transaction().unwrap();
+    tx.execute_batch(METADATA_TABLES).unwrap();
+    tx.execute(
+        "INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84 geodetic', 4326, 'EPSG', 4326, ?1, 'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid')",
+        params![WGS84_DEFINITION],
+    )
+    .unwrap();
+
+    let osm_tags = tag_columns(
+        "stop_points",
+        tags_mode,
+        response.stop_points.iter().map(|sp| &sp.all_osm_tags),
+    );
+    Layer::new(
+        "stop_points",
+        &[
+            ("stop_point_id", "TEXT"),
+            ("lat", "DOUBLE"),
+            ("lon", "DOUBLE"),
+            ("name", "TEXT"),
+            ("stop_point_type", "TEXT"),
+            ("paired_stop_point_id", "TEXT"),
+        ],
+        &osm_tags,
+    )
+    .write_features(
+        &tx,
+        "GEOMETRY",
+        response.stop_points.iter().map(|sp| {
+            let geometry = sp
+                .geometry
+                .clone()
+                .unwrap_or_else(|| Point::new(sp.coord.lon, sp.coord.lat).into());
+            let values = vec![
+                text(&format!("StopPoint:{}", sp.id)),
+                Value::Real(sp.coord.lat),
+                Value::Real(sp.coord.lon),
+                text(&sp.name),
+                text(&format!("{:?}", sp.stop_point_type)),
+                prefixed("StopPoint", &sp.paired_stop_point_id),
+            ];
+            (
+                geometry,
+                values
+                    .into_iter()
+                    .chain(osm_tag_values(&sp.all_osm_tags, &osm_tags))
+                    .collect(),
+            )
+        }),
+    );
+
+    let osm_tags = tag_columns(
+        "stop_areas",
+        tags_mode,
+        response.stop_areas.iter().map(|sa| &sa.all_osm_tags),
+    );
+    let stop_area_columns = [
+        ("stop_area_id", "TEXT"),
+        ("lat", "DOUBLE"),
+        ("lon", "DOUBLE"),
+        ("name", "TEXT"),
+    ];
+    let stop_area_values = |sa: &StopArea| -> Vec<Value> {
+        vec![
+            text(&format!("StopArea:{}", sa.id)),
+            Value::Real(sa.coord.lat),
+            Value::Real(sa.coord.lon),
+            text(&sa.name),
+        ]
+        .into_iter()
+        .chain(osm_tag_values(&sa.all_osm_tags, &osm_tags))
+        .collect()
+    };
+    Layer::new("stop_areas", &stop_area_columns, &osm_tags).write_features(
+        &tx,
+        "POINT",
+        response.stop_areas.iter().map(|sa| {
+            (
+                Point::new(sa.coord.lon, sa.coord.lat).into(),
+                stop_area_values(sa),
+            )
+        }),
+    );
+    Layer::new("stop_area_hulls", &stop_area_columns, &osm_tags).write_features(
+        &tx,
+        "GEOMETRY",
+        response
+            .stop_areas
+            .iter()
+            .filter(|sa| !sa.hull.is_empty())
+            .map(|sa| (hull_to_geometry(&sa.hull), stop_area_values(sa))),
+    );
+    let members = Layer::new(
+        "stop_area_stop_points",
+        &[("stop_area_id", "TEXT"), ("stop_point_id", "TEXT")],
+        &TagColumns::new(&TagsMode::None, std::iter::empty()),
+    );
+    members.create_attributes(&tx);
+    members.insert_attributes(
+        &tx,
+        response.stop_areas.iter().flat_map(|sa| {
+            sa.stop_point_ids.iter().map(move |sp_id| {
+                vec![
+                    text(&format!("StopArea:{}", sa.id)),
+                    text(&format!("StopPoint:{}", sp_id)),
+                ]
+            })
+        }),
+    );
+
+    if let Some(routes) = &response.routes {
+        let osm_tags = tag_columns("routes", tags_mode, routes.iter().map(|r| &r.all_osm_tags));
+        let columns: Vec<(&str, &str)> = [
+            "route_id",
+            "name",
+            "code",
+            "destination",
+            "origin",
+            "colour",
+            "text_colour",
+            "operator",
+            "network",
+            "mode",
+            "sub_mode",
+            "physical_mode",
+            "commercial_mode",
+            "netex_mode",
+            "netex_sub_mode",
+        ]
+        .iter()
+        .map(|c| (*c, "TEXT"))
+        .chain(vec![("gtfs_route_type", "INTEGER")])
+        .chain(
+            [
+                "frequency",
+                "opening_hours",
+                "frequency_exceptions",
+                "travel_time",
+                "line_id",
+            ]
+            .iter()
+            .map(|c| (*c, "TEXT")),
+        )
+        .chain(vec![
+            ("direction_id", "INTEGER"),
+            ("variant", "TEXT"),
+            ("roundtrip", "BOOLEAN"),
+        ])
+        .chain(METRICS_COLUMNS.iter().cloned())
+        .collect();
+        Layer::new("routes", &columns, &osm_tags).write_features(
+            &tx,
+            "MULTILINESTRING",
+            routes.iter().map(|r| {
+                let values = vec![
+                    text(&format!("Route:{}", r.id)),
+                    text(&r.name),
+                    text(&r.code),
+                    text(&r.destination),
+                    text(&r.origin),
+                    text(&r.colour),
+                    text(&r.text_colour),
+                    text(&r.operator),
+                    text(&r.network),
+                    text(&r.mode),
+                    r.transport_mode
+                        .sub_mode
+                        .map_or(Value::Null, |s| text(&s.to_string())),
+                    text(r.transport_mode.physical_mode()),
+                    text(r.transport_mode.commercial_mode()),
+                    text(r.transport_mode.netex_mode()),
+                    r.transport_mode.netex_sub_mode().map_or(Value::Null, text),
+                    Value::Integer(r.transport_mode.gtfs_route_type().into()),
+                    text(&r.frequency),
+                    text(&r.opening_hours),
+                    text(&r.frequency_exceptions),
+                    text(&r.travel_time),
+                    prefixed("Line", &r.line_id),
+                    r.direction_id
+                        .map_or(Value::Null, |d| Value::Integer(d.into())),
+                    r.variant.map_or(Value::Null, |v| text(&v.to_string())),
+                    Value::Integer(r.roundtrip.into()),
+                ]
+                .into_iter()
+                .chain(metrics_values(&r.metrics))
+                .chain(osm_tag_values(&r.all_osm_tags, &osm_tags))
+                .collect();
+                (ways_to_multi_line_string(&r.shape).into(), values)
+            }),
+        );
+        let route_points = Layer::new(
+            "route_points",
+            &[
+                ("route_id", "TEXT"),
+                ("role", "TEXT"),
+                ("stop_id", "TEXT"),
+                ("shape_dist_traveled", "DOUBLE"),
+                ("distance_to_shape", "DOUBLE"),
+                ("distance_from_previous", "DOUBLE"),
+            ],
+            &TagColumns::new(&TagsMode::None, std::iter::empty()),
+        );
+        route_points.create_attributes(&tx);
+        route_points.insert_attributes(
+            &tx,
+            routes.iter().flat_map(|r| {
+                r.ordered_route_points
+                    .iter()
+                    .zip(r.inter_stop_distances())
+                    .map(move |(rp, distance_from_previous)| {
+                        vec![
+                            text(&format!("Route:{}", r.id)),
+                            text(&rp.role),
+                            text(&format!("StopPoint:{}", rp.stop_point_id)),
+                            real(rp.shape_dist_traveled),
+                            real(rp.distance_to_shape),
+                            real(distance_from_previous),
+                        ]
+                    })
+            }),
+        );
+    }
+
+    if let Some(lines) = &response.lines {
+        let osm_tags = tag_columns("lines", tags_mode, lines.iter().map(|l| &l.all_osm_tags));
+        let columns: Vec<(&str, &str)> = [
+            "line_id",
+            "name",
+            "code",
+            "colour",
+            "text_colour",
+            "operator",
+            "network",
+            "mode",
+            "sub_mode",
+            "physical_mode",
+            "commercial_mode",
+            "netex_mode",
+            "netex_sub_mode",
+        ]
+        .iter()
+        .map(|c| (*c, "TEXT"))
+        .chain(vec![("gtfs_route_type", "INTEGER")])
+        .chain(
+            ["frequency", "opening_hours", "frequency_exceptions"]
+                .iter()
+                .map(|c| (*c, "TEXT")),
+        )
+        .chain(METRICS_COLUMNS.iter().cloned())
+        .collect();
+        Layer::new("lines", &columns, &osm_tags).write_features(
+            &tx,
+            "MULTILINESTRING",
+            lines.iter().map(|l| {
+                let values = vec![
+                    text(&format!("Line:{}", l.id)),
+                    text(&l.name),
+                    text(&l.code),
+                    text(&l.colour),
+                    text(&l.text_colour),
+                    text(&l.operator),
+                    text(&l.network),
+                    text(&l.mode),
+                    l.transport_mode
+                        .sub_mode
+                        .map_or(Value::Null, |s| text(&s.to_string())),
+                    text(l.transport_mode.physical_mode()),
+                    text(l.transport_mode.commercial_mode()),
+                    text(l.transport_mode.netex_mode()),
+                    l.transport_mode.netex_sub_mode().map_or(Value::Null, text),
+                    Value::Integer(l.transport_mode.gtfs_route_type().into()),
+                    text(&l.frequency),
+                    text(&l.opening_hours),
+                    text(&l.frequency_exceptions),
+                ]
+                .into_iter()
+                .chain(metrics_values(&l.metrics))
+                .chain(osm_tag_values(&l.all_osm_tags, &osm_tags))
+                .collect();
+                (ways_to_multi_line_string(&l.shape).into(), values)
+            }),
+        );
+        let line_routes = Layer::new(
+            "line_routes",
+            &[("line_id", "TEXT"), ("route_id", "TEXT")],
+            &TagColumns::new(&TagsMode::None, std::iter::empty()),
+        );
+        line_routes.create_attributes(&tx);
+        line_routes.insert_attributes(
+            &tx,
+            lines.iter().flat_map(|l| {
+                l.routes_id.iter().map(move |r| {
+                    vec![
+                        text(&format!("Line:{}", l.id)),
+                        text(&format!("Route:{}", r)),
+                    ]
+                })
+            }),
+        );
+    }
+    tx.commit().unwrap();
+}
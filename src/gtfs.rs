@@ -0,0 +1,389 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::{distance, route_linestring};
+use crate::{Line, OsmTcResponse, Route, StopPoint};
+use log::warn;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const SERVICE_ID: &str = "default";
+/// average speed (in m/s) used when a route has no valid `duration` tag
+const DEFAULT_SPEED: f64 = 20. / 3.6;
+
+pub struct GtfsOptions {
+    pub timezone: String,
+    /// url of the agencies whose lines have no `website` tag
+    pub agency_url: String,
+    /// departure time of every trip from its first stop, in seconds after midnight
+    pub departure_time: u32,
+    pub start_date: String,
+    pub end_date: String,
+    /// write the extended route types (see `TransportMode::gtfs_extended_route_type`)
+    pub extended_route_types: bool,
+}
+
+impl Default for GtfsOptions {
+    fn default() -> Self {
+        GtfsOptions {
+            timezone: "Europe/Paris".to_string(),
+            agency_url: "https://www.openstreetmap.org".to_string(),
+            departure_time: 8 * 3600,
+            start_date: "20000101".to_string(),
+            end_date: "20991231".to_string(),
+            extended_route_types: false,
+        }
+    }
+}
+
+fn agency_id(line: &Line) -> &str {
+    if !line.network.is_empty() {
+        &line.network
+    } else if !line.operator.is_empty() {
+        &line.operator
+    } else {
+        "default"
+    }
+}
+
+fn website(line: &Line) -> Option<&str> {
+    ["website", "contact:website", "url"]
+        .iter()
+        .filter_map(|key| line.all_osm_tags.get(*key))
+        .map(|url| url.as_str())
+        .find(|url| !url.is_empty())
+}
+
+fn write_agencies(lines: &[Line], output_dir: &Path, options: &GtfsOptions) {
+    let mut wtr = csv::Writer::from_path(output_dir.join("agency.txt")).unwrap();
+    wtr.serialize(("agency_id", "agency_name", "agency_url", "agency_timezone"))
+        .unwrap();
+    let mut agencies: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+    for line in lines {
+        let url = agencies.entry(agency_id(line)).or_insert(None);
+        if url.is_none() {
+            *url = website(line);
+        }
+    }
+    for (agency, url) in agencies {
+        wtr.serialize((
+            agency,
+            agency,
+            url.unwrap_or(&options.agency_url),
+            &options.timezone,
+        ))
+        .unwrap();
+    }
+}
+
+/// Parses an OSM `duration` tag (`mm`, `hh:mm` or `hh:mm:ss`) in seconds
+pub fn parse_duration(duration: &str) -> Option<u32> {
+    let parts = duration
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let seconds = match parts[..] {
+        [minutes] => minutes.checked_mul(60)?,
+        [hours, minutes] if minutes < 60 => hours.checked_mul(3600)?.checked_add(minutes * 60)?,
+        [hours, minutes, seconds] if minutes < 60 && seconds < 60 => hours
+            .checked_mul(3600)?
+            .checked_add(minutes * 60 + seconds)?,
+        _ => return None,
+    };
+    Some(seconds)
+}
+
+fn format_gtfs_time(seconds: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Travel time of a route from its first to its last stop : its `duration` tag,
+/// or its length at `DEFAULT_SPEED`
+fn travel_time(route: &Route) -> u32 {
+    parse_duration(&route.travel_time).unwrap_or_else(|| {
+        let length = route
+            .ordered_route_points
+            .last()
+            .and_then(|rp| rp.shape_dist_traveled)
+            .or(route.metrics.origin_destination_distance)
+            .unwrap_or(route.metrics.length);
+        (length / DEFAULT_SPEED).round() as u32
+    })
+}
+
+fn write_stops(response: &OsmTcResponse, output_dir: &Path) {
+    let mut wtr = csv::Writer::from_path(output_dir.join("stops.txt")).unwrap();
+    wtr.serialize((
+        "stop_id",
+        "stop_name",
+        "stop_lat",
+        "stop_lon",
+        "location_type",
+        "parent_station",
+    ))
+    .unwrap();
+    let mut parent_stations = BTreeMap::new();
+    for sa in &response.stop_areas {
+        wtr.serialize((
+            format!("StopArea:{}", sa.id),
+            &sa.name,
+            sa.coord.lat,
+            sa.coord.lon,
+            1,
+            "",
+        ))
+        .unwrap();
+        for sp_id in &sa.stop_point_ids {
+            parent_stations
+                .entry(sp_id.as_str())
+                .or_insert_with(|| format!("StopArea:{}", sa.id));
+        }
+    }
+    for sp in &response.stop_points {
+        wtr.serialize((
+            format!("StopPoint:{}", sp.id),
+            &sp.name,
+            sp.coord.lat,
+            sp.coord.lon,
+            0,
+            parent_stations
+                .get(sp.id.as_str())
+                .map_or("", |s| s.as_str()),
+        ))
+        .unwrap();
+    }
+}
+
+fn write_routes(lines: &[Line], output_dir: &Path, options: &GtfsOptions) {
+    let mut wtr = csv::Writer::from_path(output_dir.join("routes.txt")).unwrap();
+    wtr.serialize((
+        "route_id",
+        "agency_id",
+        "route_short_name",
+        "route_long_name",
+        "route_type",
+        "route_color",
+        "route_text_color",
+    ))
+    .unwrap();
+    for l in lines {
+        wtr.serialize((
+            format!("Line:{}", l.id),
+            agency_id(l),
+            &l.code,
+            &l.name,
+            if options.extended_route_types {
+                l.transport_mode.gtfs_extended_route_type()
+            } else {
+                l.transport_mode.gtfs_route_type()
+            },
+            l.colour.trim_start_matches('#'),
+            l.text_colour.trim_start_matches('#'),
+        ))
+        .unwrap();
+    }
+}
+
+fn write_calendar(output_dir: &Path, options: &GtfsOptions) {
+    let mut wtr = csv::Writer::from_path(output_dir.join("calendar.txt")).unwrap();
+    wtr.write_record([
+        "service_id",
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+        "start_date",
+        "end_date",
+    ])
+    .unwrap();
+    wtr.write_record([
+        SERVICE_ID,
+        "1",
+        "1",
+        "1",
+        "1",
+        "1",
+        "1",
+        "1",
+        &options.start_date,
+        &options.end_date,
+    ])
+    .unwrap();
+}
+
+fn write_trips(response: &OsmTcResponse, lines: &[Line], output_dir: &Path, options: &GtfsOptions) {
+    let mut trips_wtr = csv::Writer::from_path(output_dir.join("trips.txt")).unwrap();
+    trips_wtr
+        .serialize((
+            "route_id",
+            "service_id",
+            "trip_id",
+            "trip_headsign",
+            "direction_id",
+            "shape_id",
+        ))
+        .unwrap();
+    let mut stop_times_wtr = csv::Writer::from_path(output_dir.join("stop_times.txt")).unwrap();
+    stop_times_wtr
+        .serialize((
+            "trip_id",
+            "arrival_time",
+            "departure_time",
+            "stop_id",
+            "stop_sequence",
+            "shape_dist_traveled",
+        ))
+        .unwrap();
+    let mut shapes_wtr = csv::Writer::from_path(output_dir.join("shapes.txt")).unwrap();
+    shapes_wtr
+        .serialize((
+            "shape_id",
+            "shape_pt_lat",
+            "shape_pt_lon",
+            "shape_pt_sequence",
+            "shape_dist_traveled",
+        ))
+        .unwrap();
+
+    let line_of_route: BTreeMap<&str, &Line> = lines
+        .iter()
+        .flat_map(|l| l.routes_id.iter().map(move |r| (r.as_str(), l)))
+        .collect();
+    let stop_points: BTreeMap<&str, &StopPoint> = response
+        .stop_points
+        .iter()
+        .map(|sp| (sp.id.as_str(), sp))
+        .collect();
+
+    for r in response.routes.as_deref().unwrap_or(&[]) {
+        let line = match line_of_route.get(r.id.as_str()) {
+            Some(line) => line,
+            None => {
+                warn!("route {} is not part of any line : ignored in GTFS", r.id);
+                continue;
+            }
+        };
+        let arrival = match options.departure_time.checked_add(travel_time(r)) {
+            Some(arrival) => arrival,
+            None => {
+                warn!(
+                    "route {} has an overflowing travel time {} : ignored in GTFS",
+                    r.id, r.travel_time
+                );
+                continue;
+            }
+        };
+        let trip_id = format!("Route:{}", r.id);
+        let shape_id = if r.shape.is_empty() { "" } else { &trip_id };
+        trips_wtr
+            .serialize((
+                format!("Line:{}", line.id),
+                SERVICE_ID,
+                &trip_id,
+                &r.destination,
+                r.direction_id.map_or(String::new(), |d| d.to_string()),
+                shape_id,
+            ))
+            .unwrap();
+
+        let stop_ids: Vec<_> = r
+            .ordered_route_points
+            .iter()
+            .filter(|rp| stop_points.contains_key(rp.stop_point_id.as_str()))
+            .collect();
+        // only the first and the last stops are timed, the others are interpolated
+        let departure_time = format_gtfs_time(options.departure_time);
+        let arrival_time = format_gtfs_time(arrival);
+        // shape_dist_traveled must not decrease along a trip
+        let mut previous_distance = 0.;
+        for (sequence, rp) in stop_ids.iter().enumerate() {
+            let time = if sequence == 0 {
+                departure_time.as_str()
+            } else if sequence == stop_ids.len() - 1 {
+                arrival_time.as_str()
+            } else {
+                ""
+            };
+            let shape_dist_traveled = rp
+                .shape_dist_traveled
+                .filter(|d| *d >= previous_distance)
+                .map_or(String::new(), |d| {
+                    previous_distance = d;
+                    format!("{:.1}", d)
+                });
+            stop_times_wtr
+                .serialize((
+                    &trip_id,
+                    time,
+                    time,
+                    format!("StopPoint:{}", rp.stop_point_id),
+                    sequence,
+                    shape_dist_traveled,
+                ))
+                .unwrap();
+        }
+
+        let shape = route_linestring(r, &stop_points);
+        let mut shape_dist_traveled = 0.;
+        for (sequence, coord) in shape.iter().enumerate() {
+            if sequence > 0 {
+                shape_dist_traveled += distance(&shape[sequence - 1], coord);
+            }
+            shapes_wtr
+                .serialize((
+                    &trip_id,
+                    coord.lat,
+                    coord.lon,
+                    sequence,
+                    format!("{:.1}", shape_dist_traveled),
+                ))
+                .unwrap();
+        }
+    }
+}
+
+pub fn write_gtfs<P: AsRef<Path>>(response: &OsmTcResponse, output_dir: P, options: &GtfsOptions) {
+    let output_dir = output_dir.as_ref();
+    let lines = response.lines.as_deref().unwrap_or(&[]);
+    write_agencies(lines, output_dir, options);
+    write_stops(response, output_dir);
+    write_routes(lines, output_dir, options);
+    write_calendar(output_dir, options);
+    write_trips(response, lines, output_dir, options);
+}
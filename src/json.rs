@@ -0,0 +1,120 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::OsmTcResponse;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// The single JSON document is named after the prefix, without its trailing `_`
+/// (`osm-transit-extractor.json` with `sink::DEFAULT_PREFIX`).
+fn json_file(prefix: &str) -> String {
+    format!("{}.json", prefix.trim_end_matches('_'))
+}
+
+fn json_lines_file(prefix: &str, name: &str) -> String {
+    format!("{}{}.jsonl", prefix, name)
+}
+
+/// Writes all the objects as a single JSON document (`osm-transit-extractor.json`).
+pub fn write_json<P: AsRef<Path>>(response: &OsmTcResponse, output_dir: P, prefix: &str) {
+    let file = output_dir.as_ref().join(json_file(prefix));
+    let wtr = BufWriter::new(File::create(file).unwrap());
+    serde_json::to_writer(wtr, response).unwrap();
+}
+
+/// Reads the objects written by `write_json`.
+/// The errors give the file, and the line of the invalid JSON.
+pub fn read_json<P: AsRef<Path>>(input_dir: P, prefix: &str) -> Result<OsmTcResponse, String> {
+    let file = input_dir.as_ref().join(json_file(prefix));
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", file.display(), e);
+    let rdr = BufReader::new(File::open(&file).map_err(|e| error(&e))?);
+    serde_json::from_reader(rdr).map_err(|e| error(&e))
+}
+
+fn write_lines<T: Serialize>(objects: &[T], file: &Path) {
+    let mut wtr = BufWriter::new(File::create(file).unwrap());
+    for object in objects {
+        serde_json::to_writer(&mut wtr, object).unwrap();
+        writeln!(wtr).unwrap();
+    }
+}
+
+fn read_lines<T: DeserializeOwned>(file: &Path) -> Result<Vec<T>, String> {
+    let rdr = File::open(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+    let mut objects = vec![];
+    for (idx, line) in BufReader::new(rdr).lines().enumerate() {
+        let error = |e: &dyn std::fmt::Display| format!("{}:{}: {}", file.display(), idx + 1, e);
+        let line = line.map_err(|e| error(&e))?;
+        if !line.trim().is_empty() {
+            objects.push(serde_json::from_str(&line).map_err(|e| error(&e))?);
+        }
+    }
+    Ok(objects)
+}
+
+/// Writes the objects as JSON lines, one file per object type and one object per line.
+/// The routes and lines files are not written when only the stops have been extracted.
+pub fn write_json_lines<P: AsRef<Path>>(response: &OsmTcResponse, output_dir: P, prefix: &str) {
+    let file = |name: &str| output_dir.as_ref().join(json_lines_file(prefix, name));
+    write_lines(&response.stop_points, &file("stop_points"));
+    write_lines(&response.stop_areas, &file("stop_areas"));
+    if let Some(routes) = &response.routes {
+        write_lines(routes, &file("routes"));
+    }
+    if let Some(lines) = &response.lines {
+        write_lines(lines, &file("lines"));
+    }
+}
+
+/// Reads the objects written by `write_json_lines`.
+/// The errors give the file and the line of the invalid object.
+pub fn read_json_lines<P: AsRef<Path>>(
+    input_dir: P,
+    prefix: &str,
+) -> Result<OsmTcResponse, String> {
+    let file = |name: &str| input_dir.as_ref().join(json_lines_file(prefix, name));
+    let optional = |name: &str| {
+        let file = file(name);
+        if file.exists() {
+            Some(file)
+        } else {
+            None
+        }
+    };
+    Ok(OsmTcResponse {
+        stop_points: read_lines(&file("stop_points"))?,
+        stop_areas: read_lines(&file("stop_areas"))?,
+        routes: optional("routes").map(|f| read_lines(&f)).transpose()?,
+        lines: optional("lines").map(|f| read_lines(&f)).transpose()?,
+    })
+}
@@ -28,13 +28,40 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
-use geo_types::{LineString, MultiLineString};
+use geo_types::{Geometry, MultiLineString};
 use log::warn;
 use osmpbfreader::OsmObj::*;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::btree_set::BTreeSet;
 use std::collections::BTreeMap;
 use std::path::Path;
 
+pub mod archive;
+pub mod colour;
+pub mod consolidation;
+pub mod csv_reader;
+pub mod diff;
+pub mod direction;
+pub mod encoding;
+pub mod frequency;
+pub mod geojson;
+pub mod geometry;
+pub mod geopackage;
+pub mod gtfs;
+pub mod json;
+pub mod metrics;
+pub mod mode;
+pub mod opening_hours;
+pub mod pairing;
+pub mod postgis;
+pub mod roundtrip;
+pub mod simplification;
+pub mod sink;
+pub mod stats;
+pub mod tags;
+pub mod validation;
+pub mod wkt_format;
+
 pub type OsmPbfReader = osmpbfreader::OsmPbfReader<std::fs::File>;
 
 pub trait Id<T> {
@@ -49,19 +76,10 @@ pub fn shape_to_multi_line_string<T>(container: &T) -> MultiLineString<f64>
 where
     T: Shape,
 {
-    container
-        .get_shape()
-        .iter()
-        .map(|way_coords| {
-            way_coords
-                .iter()
-                .map(|coord| (coord.lon, coord.lat))
-                .collect::<LineString<f64>>()
-        })
-        .collect::<MultiLineString<f64>>()
+    wkt_format::ways_to_multi_line_string(container.get_shape())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Coord {
     pub lat: f64,
     pub lon: f64,
@@ -75,55 +93,91 @@ impl Coord {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StopPointType {
     StopPosition,
     Platform,
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StopPoint {
     pub id: String,
     pub stop_point_type: StopPointType,
     pub coord: Coord,
     pub name: String,
     pub all_osm_tags: osmpbfreader::objects::Tags,
+    /// polygon or linestring of the platforms mapped as ways or multipolygons
+    #[serde(with = "wkt_format::optional_geometry")]
+    pub geometry: Option<Geometry<f64>>,
+    /// id of the stop position of a platform (or of the platform of a stop position)
+    pub paired_stop_point_id: String,
+}
+
+impl Id<StopPoint> for StopPoint {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StopArea {
     pub id: String,
     pub coord: Coord,
     pub name: String,
     pub all_osm_tags: osmpbfreader::objects::Tags,
     pub stop_point_ids: Vec<String>,
+    pub stop_position_ids: Vec<String>,
+    /// convex hull of the stop points of the stop area (see `geometry::convex_hull`)
+    pub hull: Vec<Coord>,
 }
 
-#[derive(Debug, Clone)]
+impl Id<StopArea> for StopArea {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RoutePoint {
     pub role: String,
     pub stop_point_id: String,
+    pub shape_dist_traveled: Option<f64>,
+    pub distance_to_shape: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Route {
     pub id: String,
     pub name: String,
     pub code: String,
     pub destination: String,
     pub origin: String,
+    /// `#RRGGBB` colour (see `colour::normalize_colour`)
     pub colour: String,
+    pub text_colour: String,
     pub operator: String,
     pub network: String,
     pub mode: String,
+    pub transport_mode: mode::TransportMode,
     pub frequency: String,
     pub opening_hours: String,
+    /// `opening_hours` parsed, `None` when empty or invalid
+    pub calendar: Option<opening_hours::OpeningHours>,
     pub frequency_exceptions: String,
+    /// `frequency` and `frequency_exceptions` parsed, empty when invalid
+    pub headways: Vec<frequency::HeadwayWindow>,
     pub travel_time: String,
     pub all_osm_tags: osmpbfreader::objects::Tags,
     pub ordered_route_points: Vec<RoutePoint>,
     pub shape: Vec<Vec<Coord>>,
+    pub line_id: String,
+    pub metrics: metrics::Metrics,
+    /// direction within its line, 0 or 1 (see `direction::compute_directions`)
+    pub direction_id: Option<u8>,
+    pub variant: Option<direction::RouteVariant>,
+    /// circular route (see `roundtrip::detect_roundtrips`)
+    pub roundtrip: bool,
 }
 
 impl Route {
@@ -158,21 +212,39 @@ impl Shape for Route {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Line {
     pub id: String,
     pub name: String,
     pub code: String,
+    /// `#RRGGBB` colour (see `colour::normalize_colour`)
     pub colour: String,
+    pub text_colour: String,
     pub operator: String,
     pub network: String,
     pub mode: String,
+    pub transport_mode: mode::TransportMode,
     pub frequency: String,
     pub opening_hours: String,
+    /// `opening_hours` parsed, `None` when empty or invalid
+    pub calendar: Option<opening_hours::OpeningHours>,
     pub frequency_exceptions: String,
+    /// `frequency` and `frequency_exceptions` parsed, empty when invalid
+    pub headways: Vec<frequency::HeadwayWindow>,
     pub all_osm_tags: osmpbfreader::objects::Tags,
     pub shape: Vec<Vec<Coord>>,
     pub routes_id: Vec<String>,
+    pub dangling_routes_id: Vec<String>,
+    pub stop_area_ids: Vec<String>,
+    pub metrics: metrics::Metrics,
+}
+
+impl Line {
+    /// Tells if the line has been generated from orphan routes
+    /// (see `consolidation::add_synthetic_lines`) rather than read from a route_master.
+    pub fn is_synthetic(&self) -> bool {
+        self.id.starts_with(consolidation::SYNTHETIC_LINE_PREFIX)
+    }
 }
 
 impl Id<Line> for Line {
@@ -187,49 +259,7 @@ impl Shape for Line {
     }
 }
 
-#[allow(dead_code)]
-/* to_multilinestring is to be used when issue #8 is resolved*/
-impl Route {
-    fn to_multilinestring(&self) -> wkt::types::MultiLineString<f64> {
-        let wkt_linestrings = self
-            .shape
-            .iter()
-            .map(|vec_coord| {
-                vec_coord
-                    .iter()
-                    .map(|coord| wkt::types::Coord {
-                        x: coord.lon,
-                        y: coord.lat,
-                        z: None,
-                        m: None,
-                    })
-                    .collect()
-            })
-            .map(wkt::types::LineString)
-            .collect();
-        wkt::types::MultiLineString(wkt_linestrings)
-    }
-}
-
-fn shape_to_wkt(shape: &[Vec<Coord>]) -> String {
-    if shape.is_empty() {
-        "".to_string()
-    } else {
-        let linestring: String = shape
-            .iter()
-            .map(|vec_coord| {
-                vec_coord
-                    .iter()
-                    .map(|coord| format!("{} {}", coord.lon.to_string(), coord.lat.to_string()))
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            })
-            .collect::<Vec<String>>()
-            .join("), (");
-        format!("MULTILINESTRING(({}))", linestring)
-    }
-}
-
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OsmTcResponse {
     pub stop_points: Vec<StopPoint>,
     pub stop_areas: Vec<StopArea>,
@@ -237,13 +267,26 @@ pub struct OsmTcResponse {
     pub lines: Option<Vec<Line>>,
 }
 
+/// Opens a PBF file, or reads it from stdin when `path` is `-` (copied in a temporary
+/// file, as the file is read several times).
 pub fn parse_osm_pbf(path: &str) -> OsmPbfReader {
+    if path == "-" {
+        use std::io::{Seek, SeekFrom};
+        let mut file = tempfile::tempfile().unwrap();
+        std::io::copy(&mut std::io::stdin().lock(), &mut file).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        return osmpbfreader::OsmPbfReader::new(file);
+    }
     let path = std::path::Path::new(&path);
-    osmpbfreader::OsmPbfReader::new(std::fs::File::open(&path).unwrap())
+    osmpbfreader::OsmPbfReader::new(std::fs::File::open(path).unwrap())
+}
+
+fn is_multipolygon(obj: &osmpbfreader::OsmObj) -> bool {
+    obj.is_relation() && obj.tags().contains("type", "multipolygon")
 }
 
 fn is_stop_point(obj: &osmpbfreader::OsmObj) -> bool {
-    (obj.is_node() || obj.is_way())
+    (obj.is_node() || obj.is_way() || is_multipolygon(obj))
         && (obj.tags().contains("public_transport", "platform")
             || obj.tags().contains("public_transport", "stop_position")
             || obj.tags().contains("highway", "bus_stop")
@@ -405,7 +448,7 @@ fn osm_route_to_shape(
     osm_relation
         .refs
         .iter()
-        .filter(|refe| !is_stop(*refe))
+        .filter(|refe| !is_stop(refe))
         .filter_map(|refe| obj_map.get(&refe.member))
         .filter_map(|osm_obj| osmpbfreader::OsmObj::way(osm_obj))
         .filter_map(|osm_way| {
@@ -418,14 +461,64 @@ fn osm_route_to_shape(
         .collect()
 }
 
+fn is_route_relation(obj: &osmpbfreader::OsmObj) -> bool {
+    obj.is_relation() && obj.tags().contains("type", "route")
+}
+
+/// Returns the route relations holding the stops and ways of a route, in order.
+/// A route split into sub-relations (super-relation) is flattened into its parts.
+/// `path` holds the relations being flattened, to break membership cycles.
+fn osm_route_to_parts<'a>(
+    obj_map: &'a BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
+    osm_relation: &'a osmpbfreader::Relation,
+    path: &mut Vec<osmpbfreader::RelationId>,
+) -> Vec<&'a osmpbfreader::Relation> {
+    if path.contains(&osm_relation.id) {
+        warn!(
+            "relation {} is a member of itself : ignored",
+            osm_relation.id.0
+        );
+        return vec![];
+    }
+    path.push(osm_relation.id);
+    let mut parts = vec![osm_relation];
+    for refe in osm_relation.refs.iter().filter(|r| r.member.is_relation()) {
+        match obj_map.get(&refe.member) {
+            Some(obj) if is_route_relation(obj) => {
+                parts.extend(osm_route_to_parts(obj_map, obj.relation().unwrap(), path));
+            }
+            Some(_) => {}
+            None => warn!(
+                "member relation {} of relation {} is missing in the extract",
+                refe.member.inner_id(),
+                osm_relation.id.0
+            ),
+        }
+    }
+    path.pop();
+    parts
+}
+
 fn osm_line_to_shape(
     obj_map: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
     osm_relations_ref: &[osmpbfreader::Ref],
 ) -> Vec<Vec<Coord>> {
     osm_relations_ref
         .iter()
-        .filter_map(|refe| obj_map.get(&refe.member))
+        .filter(|refe| refe.member.is_relation())
+        .filter_map(|refe| {
+            let obj = obj_map.get(&refe.member);
+            if obj.is_none() {
+                warn!(
+                    "route relation {} is missing in the extract : ignored in line shape",
+                    refe.member.inner_id()
+                );
+            }
+            obj
+        })
+        .filter(|osm_obj| is_route_relation(osm_obj))
         .filter_map(|osm_obj| osmpbfreader::OsmObj::relation(osm_obj))
+        .flat_map(|relation| osm_route_to_parts(obj_map, relation, &mut vec![]))
         .flat_map(|relation| osm_route_to_shape(obj_map, relation))
         .collect()
 }
@@ -434,7 +527,7 @@ fn osm_route_to_route_points_list(osm_relation: &osmpbfreader::Relation) -> Vec<
     osm_relation
         .refs
         .iter()
-        .filter(|refe| is_stop(*refe))
+        .filter(|refe| is_stop(refe))
         .map(|refe| {
             let stop_point_id = match refe.member {
                 osmpbfreader::OsmId::Node(obj_id) => format!("node:{}", obj_id.0),
@@ -444,6 +537,8 @@ fn osm_route_to_route_points_list(osm_relation: &osmpbfreader::Relation) -> Vec<
             RoutePoint {
                 role: refe.role.to_string(),
                 stop_point_id,
+                shape_dist_traveled: None,
+                distance_to_shape: None,
             }
         })
         .collect()
@@ -465,27 +560,54 @@ fn osm_obj_to_route(
     obj: &osmpbfreader::OsmObj,
 ) -> Option<Route> {
     let osm_tags = obj.tags().clone();
-    obj.relation().map(|rel| Route {
-        id: format!("relation:{}", rel.id.0),
-        name: rel.tags.get("name").cloned().unwrap_or_default(),
-        code: rel.tags.get("ref").cloned().unwrap_or_default(),
-        destination: rel.tags.get("to").cloned().unwrap_or_default(),
-        origin: rel.tags.get("from").cloned().unwrap_or_default(),
-        mode: rel.tags.get("route").cloned().unwrap_or_default(),
-        colour: rel.tags.get("colour").cloned().unwrap_or_default(),
-        operator: rel.tags.get("operator").cloned().unwrap_or_default(),
-        network: rel.tags.get("network").cloned().unwrap_or_default(),
-        frequency: rel.tags.get("interval").cloned().unwrap_or_default(),
-        opening_hours: rel.tags.get("opening_hours").cloned().unwrap_or_default(),
-        frequency_exceptions: rel
-            .tags
-            .get("interval:conditional")
-            .cloned()
-            .unwrap_or_default(),
-        travel_time: rel.tags.get("duration").cloned().unwrap_or_default(),
-        all_osm_tags: osm_tags,
-        ordered_route_points: osm_route_to_route_points_list(rel),
-        shape: osm_route_to_shape(obj_map, rel),
+    let (colour, text_colour) = colour::colours_from_tags(&osm_tags);
+    obj.relation().map(|rel| {
+        let parts = osm_route_to_parts(obj_map, rel, &mut vec![]);
+        let mut ordered_route_points: Vec<RoutePoint> = vec![];
+        for part in &parts {
+            let mut part_route_points = osm_route_to_route_points_list(part);
+            // a stop shared by two consecutive parts ends the first one and starts the next one
+            if !part_route_points.is_empty()
+                && ordered_route_points.last() == part_route_points.first()
+            {
+                part_route_points.remove(0);
+            }
+            ordered_route_points.extend(part_route_points);
+        }
+        Route {
+            id: format!("relation:{}", rel.id.0),
+            name: rel.tags.get("name").cloned().unwrap_or_default(),
+            code: rel.tags.get("ref").cloned().unwrap_or_default(),
+            destination: rel.tags.get("to").cloned().unwrap_or_default(),
+            origin: rel.tags.get("from").cloned().unwrap_or_default(),
+            mode: rel.tags.get("route").cloned().unwrap_or_default(),
+            transport_mode: mode::TransportMode::from_tags(&rel.tags, "route"),
+            colour,
+            text_colour,
+            operator: rel.tags.get("operator").cloned().unwrap_or_default(),
+            network: rel.tags.get("network").cloned().unwrap_or_default(),
+            frequency: rel.tags.get("interval").cloned().unwrap_or_default(),
+            opening_hours: rel.tags.get("opening_hours").cloned().unwrap_or_default(),
+            calendar: rel.tags.get("opening_hours").and_then(|v| v.parse().ok()),
+            frequency_exceptions: rel
+                .tags
+                .get("interval:conditional")
+                .cloned()
+                .unwrap_or_default(),
+            headways: frequency::headways_from_tags(&rel.tags),
+            travel_time: rel.tags.get("duration").cloned().unwrap_or_default(),
+            all_osm_tags: osm_tags,
+            ordered_route_points,
+            shape: parts
+                .iter()
+                .flat_map(|part| osm_route_to_shape(obj_map, part))
+                .collect(),
+            line_id: String::new(),
+            metrics: Default::default(),
+            direction_id: None,
+            variant: None,
+            roundtrip: false,
+        }
     })
 }
 
@@ -494,27 +616,85 @@ fn osm_obj_to_line(
     obj: &osmpbfreader::OsmObj,
 ) -> Option<Line> {
     let osm_tags = obj.tags().clone();
+    let (colour, text_colour) = colour::colours_from_tags(&osm_tags);
     obj.relation().map(|rel| Line {
         id: format!("relation:{}", rel.id.0),
         name: rel.tags.get("name").cloned().unwrap_or_default(),
         code: rel.tags.get("ref").cloned().unwrap_or_default(),
-        colour: rel.tags.get("colour").cloned().unwrap_or_default(),
+        colour,
+        text_colour,
         mode: rel.tags.get("route_master").cloned().unwrap_or_default(),
+        transport_mode: mode::TransportMode::from_tags(&rel.tags, "route_master"),
         operator: rel.tags.get("operator").cloned().unwrap_or_default(),
         network: rel.tags.get("network").cloned().unwrap_or_default(),
         frequency: rel.tags.get("interval").cloned().unwrap_or_default(),
         opening_hours: rel.tags.get("opening_hours").cloned().unwrap_or_default(),
+        calendar: rel.tags.get("opening_hours").and_then(|v| v.parse().ok()),
         frequency_exceptions: rel
             .tags
             .get("interval:conditional")
             .cloned()
             .unwrap_or_default(),
+        headways: frequency::headways_from_tags(&rel.tags),
         all_osm_tags: osm_tags,
         shape: osm_line_to_shape(obj_map, &rel.refs),
         routes_id: osm_line_to_routes_list(rel),
+        dangling_routes_id: vec![],
+        stop_area_ids: vec![],
+        metrics: Default::default(),
     })
 }
 
+fn osm_way_to_geometry(
+    obj_map: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
+    way: &osmpbfreader::Way,
+) -> Option<Geometry<f64>> {
+    let coords = osm_way_to_vec(obj_map, way);
+    let linestring: geo_types::LineString<f64> = coords.iter().map(|c| (c.lon, c.lat)).collect();
+    match coords.len() {
+        0 | 1 => None,
+        n if n >= 4 && coords.first() == coords.last() => {
+            Some(geo_types::Polygon::new(linestring, vec![]).into())
+        }
+        _ => Some(linestring.into()),
+    }
+}
+
+fn osm_multipolygon_to_geometry(
+    obj_map: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
+    rel: &osmpbfreader::Relation,
+) -> Option<Geometry<f64>> {
+    let ways_of_role = |inner: bool| -> Vec<Vec<Coord>> {
+        rel.refs
+            .iter()
+            .filter(|refe| (refe.role.as_str() == "inner") == inner)
+            .filter_map(|refe| obj_map.get(&refe.member))
+            .filter_map(|obj| obj.way())
+            .map(|way| osm_way_to_vec(obj_map, way))
+            .collect()
+    };
+    let to_linestring = |ring: &Vec<Coord>| -> geo_types::LineString<f64> {
+        ring.iter().map(|c| (c.lon, c.lat)).collect()
+    };
+    let inners = geometry::assemble_rings(&ways_of_role(true));
+    let polygons: Vec<geo_types::Polygon<f64>> = geometry::assemble_rings(&ways_of_role(false))
+        .iter()
+        .map(|outer| {
+            let holes = inners
+                .iter()
+                .filter(|inner| geometry::is_in_ring(&inner[0], outer))
+                .map(to_linestring)
+                .collect();
+            geo_types::Polygon::new(to_linestring(outer), holes)
+        })
+        .collect();
+    match polygons.len() {
+        0 => None,
+        1 => polygons.into_iter().next().map(Geometry::Polygon),
+        _ => Some(geo_types::MultiPolygon(polygons).into()),
+    }
+}
+
 fn osm_obj_to_stop_point(
     obj_map: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
     obj: &osmpbfreader::OsmObj,
@@ -531,6 +711,11 @@ fn osm_obj_to_stop_point(
             },
         ),
     };
+    let geometry = match *obj {
+        Relation(ref rel) => osm_multipolygon_to_geometry(obj_map, rel),
+        Way(ref way) => osm_way_to_geometry(obj_map, way),
+        Node(_) => None,
+    };
     let name = obj.tags().get("name").cloned().unwrap_or_default();
     let id = format!("{}:{}", obj_type, obj_id);
     let osm_tags = obj.tags().clone();
@@ -540,6 +725,8 @@ fn osm_obj_to_stop_point(
         name,
         coord,
         all_osm_tags: osm_tags,
+        geometry,
+        paired_stop_point_id: String::new(),
     }
 }
 
@@ -547,8 +734,8 @@ fn osm_obj_to_stop_area(
     obj_map: &BTreeMap<osmpbfreader::OsmId, osmpbfreader::OsmObj>,
     obj: &osmpbfreader::OsmObj,
 ) -> StopArea {
-    let rel = &*obj.relation().unwrap();
-    let (obj_type, obj_id, coord) = ("relation", rel.id.0, get_one_coord_from_rel(obj_map, &rel));
+    let rel = obj.relation().unwrap();
+    let (obj_type, obj_id, coord) = ("relation", rel.id.0, get_one_coord_from_rel(obj_map, rel));
     let name = obj.tags().get("name").cloned().unwrap_or_default();
     let osm_tags = obj.tags().clone();
     StopArea {
@@ -556,15 +743,20 @@ fn osm_obj_to_stop_area(
         name,
         coord,
         all_osm_tags: osm_tags,
-        stop_point_ids: osm_stop_area_to_stop_point_list(rel),
+        stop_point_ids: osm_stop_area_to_stop_point_list(rel, "platform"),
+        stop_position_ids: osm_stop_area_to_stop_point_list(rel, "stop"),
+        hull: vec![],
     }
 }
 
-fn osm_stop_area_to_stop_point_list(osm_relation: &osmpbfreader::Relation) -> Vec<String> {
+fn osm_stop_area_to_stop_point_list(
+    osm_relation: &osmpbfreader::Relation,
+    role: &str,
+) -> Vec<String> {
     osm_relation
         .refs
         .iter()
-        .filter(|refe| refe.role.as_str() == "platform")
+        .filter(|refe| refe.role.as_str() == role)
         .map(|refe| match refe.member {
             osmpbfreader::OsmId::Node(obj_id) => format!("node:{}", obj_id.0),
             osmpbfreader::OsmId::Way(obj_id) => format!("way:{}", obj_id.0),
@@ -577,7 +769,7 @@ pub fn get_stop_points_from_osm(pbf: &mut OsmPbfReader) -> Vec<StopPoint> {
     let objects = pbf.get_objs_and_deps(is_stop_point).unwrap();
     objects
         .values()
-        .filter(|x| is_stop_point(*x))
+        .filter(|x| is_stop_point(x))
         .map(|obj| osm_obj_to_stop_point(&objects, obj))
         .collect()
 }
@@ -586,16 +778,24 @@ pub fn get_stop_areas_from_osm(pbf: &mut OsmPbfReader) -> Vec<StopArea> {
     let objects = pbf.get_objs_and_deps(is_stop_area).unwrap();
     objects
         .values()
-        .filter(|x| is_stop_area(*x))
+        .filter(|x| is_stop_area(x))
         .map(|obj| osm_obj_to_stop_area(&objects, obj))
         .collect()
 }
 
 pub fn get_routes_from_osm(pbf: &mut OsmPbfReader) -> Vec<Route> {
     let objects = pbf.get_objs_and_deps(is_route).unwrap();
+    // parts of a route split into sub-relations are not routes by themselves
+    let sub_route_ids: BTreeSet<osmpbfreader::OsmId> = objects
+        .values()
+        .filter(|x| is_route_relation(x))
+        .filter_map(|obj| obj.relation())
+        .flat_map(|rel| rel.refs.iter().map(|refe| refe.member))
+        .filter(|id| objects.get(id).is_some_and(is_route_relation))
+        .collect();
     objects
         .values()
-        .filter(|x| is_route(*x))
+        .filter(|x| is_route(x) && !sub_route_ids.contains(&x.id()))
         .filter_map(|obj| osm_obj_to_route(&objects, obj))
         .collect()
 }
@@ -604,7 +804,7 @@ pub fn get_lines_from_osm(pbf: &mut OsmPbfReader) -> Vec<Line> {
     let objects = pbf.get_objs_and_deps(is_line).unwrap();
     objects
         .values()
-        .filter(|x| is_line(*x))
+        .filter(|x| is_line(x))
         .filter_map(|obj| osm_obj_to_line(&objects, obj))
         .collect()
 }
@@ -659,15 +859,62 @@ pub fn categorize_stop_point(stop_point: &mut StopPoint, routes: Vec<&Route>) {
 }
 
 pub fn update_stop_points_type(stop_points: &mut [StopPoint], routes: &[Route]) {
-    stop_points.iter_mut().for_each(|mut sp| {
-        let route_from_stops = get_routes_from_stop(&routes, sp);
-        categorize_stop_point(&mut sp, route_from_stops);
+    stop_points.iter_mut().for_each(|sp| {
+        let route_from_stops = get_routes_from_stop(routes, sp);
+        categorize_stop_point(sp, route_from_stops);
     })
 }
 
+/// Keeps in each line only the routes that have been extracted (the other ones
+/// are moved to `dangling_routes_id`) and sets the `line_id` of each route.
+pub fn resolve_lines_routes(lines: &mut [Line], routes: &mut [Route]) {
+    let route_index: BTreeMap<String, usize> = routes
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| (r.id.clone(), idx))
+        .collect();
+    for line in lines.iter_mut() {
+        let (routes_id, dangling_routes_id): (Vec<String>, Vec<String>) = line
+            .routes_id
+            .drain(..)
+            .partition(|id| route_index.contains_key(id));
+        for route_id in &dangling_routes_id {
+            warn!(
+                "route {} of line {} is not an extracted route",
+                route_id, line.id
+            );
+        }
+        let routes_id = routes_id
+            .into_iter()
+            .filter(|route_id| {
+                let route = &mut routes[route_index[route_id]];
+                if !route.line_id.is_empty() {
+                    warn!(
+                        "route {} belongs to lines {} and {} : removed from line {}",
+                        route.id, route.line_id, line.id, line.id
+                    );
+                    return false;
+                }
+                route.line_id = line.id.clone();
+                true
+            })
+            .collect();
+        line.routes_id = routes_id;
+        line.dangling_routes_id.extend(dangling_routes_id);
+    }
+}
+
 pub fn get_osm_tcobjects(parsed_pbf: &mut OsmPbfReader, stops_only: bool) -> OsmTcResponse {
-    let mut stop_points = get_stop_points_from_osm(parsed_pbf);
-    let stop_areas = get_stop_areas_from_osm(parsed_pbf);
+    get_osm_tcobjects_with_timings(parsed_pbf, stops_only, &mut stats::PhaseTimings::default())
+}
+
+pub fn get_osm_tcobjects_with_timings(
+    parsed_pbf: &mut OsmPbfReader,
+    stops_only: bool,
+    timings: &mut stats::PhaseTimings,
+) -> OsmTcResponse {
+    let mut stop_points = timings.time("stop_points", || get_stop_points_from_osm(parsed_pbf));
+    let stop_areas = timings.time("stop_areas", || get_stop_areas_from_osm(parsed_pbf));
     if stops_only {
         OsmTcResponse {
             stop_points,
@@ -676,9 +923,36 @@ pub fn get_osm_tcobjects(parsed_pbf: &mut OsmPbfReader, stops_only: bool) -> Osm
             lines: None,
         }
     } else {
-        let routes = get_routes_from_osm(parsed_pbf);
-        let lines = get_lines_from_osm(parsed_pbf);
-        update_stop_points_type(&mut stop_points, &routes);
+        let mut routes = timings.time("routes", || get_routes_from_osm(parsed_pbf));
+        let mut lines = timings.time("lines", || get_lines_from_osm(parsed_pbf));
+        timings.time("lines_routes_resolution", || {
+            resolve_lines_routes(&mut lines, &mut routes)
+        });
+        timings.time("roundtrips", || {
+            roundtrip::detect_roundtrips(&mut routes, &stop_points, &stop_areas)
+        });
+        timings.time("route_points_location", || {
+            geometry::locate_routes_points(&mut routes, &stop_points)
+        });
+        timings.time("lines_consolidation", || {
+            consolidation::consolidate_lines(&mut lines, &routes, &stop_areas);
+            for route in routes.iter_mut() {
+                colour::set_contrasting_text_colour(&route.colour, &mut route.text_colour);
+            }
+        });
+        timings.time("metrics", || {
+            metrics::compute_routes_metrics(&mut routes);
+            metrics::compute_lines_metrics(&mut lines, &routes)
+        });
+        timings.time("stop_points_categorization", || {
+            update_stop_points_type(&mut stop_points, &routes)
+        });
+        timings.time("stop_points_pairing", || {
+            pairing::pair_stop_points(&mut stop_points, &stop_areas, &routes)
+        });
+        timings.time("route_directions", || {
+            direction::compute_directions(&mut routes, &lines, &stop_points, &stop_areas)
+        });
         OsmTcResponse {
             stop_points,
             stop_areas,
@@ -688,70 +962,115 @@ pub fn get_osm_tcobjects(parsed_pbf: &mut OsmPbfReader, stops_only: bool) -> Osm
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct RouteFilter {
+    pub networks: Vec<String>,
+    pub operators: Vec<String>,
+    pub modes: Vec<String>,
+}
+
+impl RouteFilter {
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty() && self.operators.is_empty() && self.modes.is_empty()
+    }
+
+    fn matches(&self, network: &str, operator: &str, mode: &str) -> bool {
+        let matches_one =
+            |values: &[String], value: &str| values.is_empty() || values.iter().any(|v| v == value);
+        matches_one(&self.networks, network)
+            && matches_one(&self.operators, operator)
+            && matches_one(&self.modes, mode)
+    }
+}
+
+pub fn filter_osm_tcobjects(response: &mut OsmTcResponse, filter: &RouteFilter) {
+    if filter.is_empty() {
+        return;
+    }
+    if let Some(routes) = response.routes.as_mut() {
+        routes.retain(|r| filter.matches(&r.network, &r.operator, &r.mode));
+    }
+    if let Some(lines) = response.lines.as_mut() {
+        lines.retain(|l| filter.matches(&l.network, &l.operator, &l.mode));
+        if let Some(routes) = response.routes.as_ref() {
+            let route_ids: BTreeSet<&str> = routes.iter().map(|r| r.id.as_str()).collect();
+            for line in lines.iter_mut() {
+                line.routes_id.retain(|id| route_ids.contains(id.as_str()));
+            }
+        }
+    }
+}
+
 pub fn write_stop_points_to_csv<P: AsRef<Path>>(
     stop_points: &[StopPoint],
     output_dir: P,
     all_tags: bool,
 ) {
-    let output_dir = output_dir.as_ref();
-    let csv_file = output_dir.join("osm-transit-extractor_stop_points.csv");
+    write_stop_points_to_csv_with_options(
+        stop_points,
+        output_dir,
+        &sink::CsvOptions::from_all_tags(all_tags),
+    )
+}
 
-    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
-    let default_header = ["stop_point_id", "lat", "lon", "name", "stop_point_type"];
-    let osm_tag_list: BTreeSet<String> = stop_points
-        .iter()
-        .flat_map(|s| s.all_osm_tags.keys().map(|s| s.to_string()))
-        .collect();
-    if all_tags {
-        let osm_header = osm_tag_list.iter().map(|s| format!("osm:{}", s));
-        let v: Vec<_> = default_header
-            .iter()
-            .map(|&s| s.to_string())
-            .chain(osm_header)
-            .collect();
-        wtr.serialize(v).unwrap();
-    } else {
-        wtr.serialize(default_header).unwrap();
+pub fn write_stop_points_to_csv_with_options<P: AsRef<Path>>(
+    stop_points: &[StopPoint],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let tag_columns = tags::TagColumns::new(
+        &options.tags_mode,
+        stop_points.iter().map(|s| &s.all_osm_tags),
+    );
+    let mut wtr = sink::StopPointsCsv::new(output_dir.as_ref(), &options.prefix, tag_columns);
+    for sp in stop_points {
+        wtr.write(sp);
     }
+}
 
+pub fn write_stop_point_pairs_to_csv<P: AsRef<Path>>(
+    stop_points: &[StopPoint],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let mut wtr = sink::StopPointPairsCsv::new(output_dir.as_ref(), &options.prefix);
     for sp in stop_points {
-        let mut csv_row = vec![
-            format!("StopPoint:{}", sp.id),
-            sp.coord.lat.to_string(),
-            sp.coord.lon.to_string(),
-            sp.name.to_string(),
-            format!("{:?}", sp.stop_point_type),
-        ];
-        if all_tags {
-            csv_row = csv_row
-                .into_iter()
-                .chain(osm_tag_list.iter().map(|k| {
-                    sp.all_osm_tags
-                        .get(k)
-                        .map_or("", |s| s.as_str())
-                        .to_string()
-                }))
-                .collect();
-        }
-        wtr.serialize(csv_row).unwrap();
+        wtr.write(sp);
+    }
+}
+
+/// Writes the headway windows of the routes and lines (`osm-transit-extractor_frequencies.csv`).
+pub fn write_frequencies_to_csv<P: AsRef<Path>>(
+    routes: &[Route],
+    lines: &[Line],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let mut wtr = sink::FrequenciesCsv::new(output_dir.as_ref(), &options.prefix);
+    for r in routes {
+        wtr.write(&format!("Route:{}", r.id), &r.headways);
+    }
+    for l in lines {
+        wtr.write(&format!("Line:{}", l.id), &l.headways);
     }
 }
 
 pub fn write_stop_areas_stop_point_to_csv<P: AsRef<Path>>(stop_areas: &[StopArea], output_dir: P) {
-    let output_dir = output_dir.as_ref();
-    let csv_file = output_dir.join("osm-transit-extractor_stop_areas_stop_point.csv");
+    write_stop_areas_stop_point_to_csv_with_options(
+        stop_areas,
+        output_dir,
+        &sink::CsvOptions::default(),
+    )
+}
 
-    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
-    let default_header = ["stop_area_id", "stop_point_id"];
-    wtr.serialize(default_header).unwrap();
+pub fn write_stop_areas_stop_point_to_csv_with_options<P: AsRef<Path>>(
+    stop_areas: &[StopArea],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let mut wtr = sink::StopAreaMembersCsv::new(output_dir.as_ref(), &options.prefix);
     for sa in stop_areas {
-        for sp_id in &sa.stop_point_ids {
-            let csv_row = vec![
-                format!("StopArea:{}", sa.id),
-                format!("StopPoint:{}", sp_id),
-            ];
-            wtr.serialize(csv_row).unwrap();
-        }
+        wtr.write(sa);
     }
 }
 
@@ -760,199 +1079,81 @@ pub fn write_stop_areas_to_csv<P: AsRef<Path>>(
     output_dir: P,
     all_tags: bool,
 ) {
-    let output_dir = output_dir.as_ref();
-    let csv_file = output_dir.join("osm-transit-extractor_stop_areas.csv");
-
-    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
-    let osm_tag_list: BTreeSet<String> = stop_areas
-        .iter()
-        .flat_map(|s| s.all_osm_tags.keys().map(|s| s.to_string()))
-        .collect();
-    let default_header = ["stop_area_id", "lat", "lon", "name"];
-    if all_tags {
-        let osm_header = osm_tag_list.iter().map(|s| format!("osm:{}", s));
-        let v: Vec<_> = default_header
-            .iter()
-            .map(|&s| s.to_string())
-            .chain(osm_header)
-            .collect();
-        wtr.serialize(v).unwrap();
-    } else {
-        wtr.serialize(default_header).unwrap();
-    }
+    write_stop_areas_to_csv_with_options(
+        stop_areas,
+        output_dir,
+        &sink::CsvOptions::from_all_tags(all_tags),
+    )
+}
 
+pub fn write_stop_areas_to_csv_with_options<P: AsRef<Path>>(
+    stop_areas: &[StopArea],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let tag_columns = tags::TagColumns::new(
+        &options.tags_mode,
+        stop_areas.iter().map(|s| &s.all_osm_tags),
+    );
+    let mut wtr = sink::StopAreasCsv::new(
+        output_dir.as_ref(),
+        &options.prefix,
+        tag_columns,
+        options.hulls,
+    );
     for sa in stop_areas {
-        let mut csv_row = vec![
-            format!("StopArea:{}", sa.id),
-            sa.coord.lat.to_string(),
-            sa.coord.lon.to_string(),
-            sa.name.to_string(),
-        ];
-        if all_tags {
-            csv_row = csv_row
-                .into_iter()
-                .chain(osm_tag_list.iter().map(|k| {
-                    sa.all_osm_tags
-                        .get(k)
-                        .map_or("", |s| s.as_str())
-                        .to_string()
-                }))
-                .collect();
-        }
-        wtr.serialize(csv_row).unwrap();
+        wtr.write(sa);
     }
 }
+
 pub fn write_routes_to_csv<P: AsRef<Path>>(routes: Vec<Route>, output_dir: P, all_tags: bool) {
-    let output_dir = output_dir.as_ref();
-    let csv_route_file = output_dir.join("osm-transit-extractor_routes.csv");
-    let csv_route_points_file = output_dir.join("osm-transit-extractor_route_points.csv");
-    let mut wtr_route = csv::Writer::from_path(csv_route_file).unwrap();
-    let mut wtr_route_points = csv::Writer::from_path(csv_route_points_file).unwrap();
-    let osm_tag_list: BTreeSet<String> = routes
-        .iter()
-        .flat_map(|r| r.all_osm_tags.keys().map(|s| s.to_string()))
-        .collect();
-    let osm_header = osm_tag_list.iter().map(|s| format!("osm:{}", s));
-    wtr_route_points
-        .serialize(("route_id", "role", "stop_id"))
-        .unwrap();
-    let default_header = [
-        "route_id",
-        "name",
-        "code",
-        "destination",
-        "origin",
-        "colour",
-        "operator",
-        "network",
-        "mode",
-        "frequency",
-        "opening_hours",
-        "frequency_exceptions",
-        "travel_time",
-        "shape",
-    ];
-    if all_tags {
-        let v: Vec<_> = default_header
-            .iter()
-            .map(|&s| s.to_string())
-            .chain(osm_header)
-            .collect();
-        wtr_route.serialize(v).unwrap();
-    } else {
-        wtr_route.serialize(default_header).unwrap();
-    }
-
-    for r in &routes {
-        // writing route csv
-        let mut csv_row = vec![
-            format!("Route:{}", r.id),
-            r.name.to_string(),
-            r.code.to_string(),
-            r.destination.to_string(),
-            r.origin.to_string(),
-            r.colour.to_string(),
-            r.operator.to_string(),
-            r.network.to_string(),
-            r.mode.to_string(),
-            r.frequency.to_string(),
-            r.opening_hours.to_string(),
-            r.frequency_exceptions.to_string(),
-            r.travel_time.to_string(),
-            shape_to_wkt(&r.shape),
-        ];
-        if all_tags {
-            csv_row = csv_row
-                .into_iter()
-                .chain(
-                    osm_tag_list
-                        .iter()
-                        .map(|k| r.all_osm_tags.get(k).map_or("", |s| s.as_str()).to_string()),
-                )
-                .collect();
-        }
-        wtr_route.serialize(csv_row).unwrap();
-
-        //writing the route_points csv
-        for rp in &r.ordered_route_points {
-            let row = vec![
-                format!("Route:{}", r.id),
-                rp.role.to_string(),
-                format!("StopPoint:{}", rp.stop_point_id),
-            ];
-            wtr_route_points.write_record(row.into_iter()).unwrap();
-        }
+    write_routes_to_csv_with_options(
+        &routes,
+        output_dir,
+        &sink::CsvOptions::from_all_tags(all_tags),
+    )
+}
+
+pub fn write_routes_to_csv_with_options<P: AsRef<Path>>(
+    routes: &[Route],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let tag_columns =
+        tags::TagColumns::new(&options.tags_mode, routes.iter().map(|r| &r.all_osm_tags));
+    let mut wtr = sink::RoutesCsv::new(
+        output_dir.as_ref(),
+        &options.prefix,
+        tag_columns,
+        options.geometry_encoding,
+    );
+    for r in routes {
+        wtr.write(r);
     }
 }
 
 pub fn write_lines_to_csv<P: AsRef<Path>>(lines: Vec<Line>, output_dir: P, all_tags: bool) {
-    let output_dir = output_dir.as_ref();
-    let lines_csv_file = output_dir.join("osm-transit-extractor_lines.csv");
-    let mut lines_wtr = csv::Writer::from_path(lines_csv_file).unwrap();
-    let osm_tag_list: BTreeSet<String> = lines
-        .iter()
-        .flat_map(|r| r.all_osm_tags.keys().map(|s| s.to_string()))
-        .collect();
-    let default_header = [
-        "line_id",
-        "name",
-        "code",
-        "colour",
-        "operator",
-        "network",
-        "mode",
-        "frequency",
-        "opening_hours",
-        "frequency_exceptions",
-        "shape",
-    ];
-    if all_tags {
-        let osm_header = osm_tag_list.iter().map(|s| format!("osm:{}", s));
-        let v: Vec<_> = default_header
-            .iter()
-            .map(|&s| s.to_string())
-            .chain(osm_header)
-            .collect();
-        lines_wtr.serialize(v).unwrap();
-    } else {
-        lines_wtr.serialize(default_header).unwrap();
-    }
-
-    let csv_file = output_dir.join("osm-transit-extractor_line_routes.csv");
-    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
-    wtr.serialize(("line_id", "route_id")).unwrap();
-
-    for l in &lines {
-        // writing lines csv
-        let mut csv_row = vec![
-            format!("Line:{}", l.id),
-            l.name.to_string(),
-            l.code.to_string(),
-            l.colour.to_string(),
-            l.operator.to_string(),
-            l.network.to_string(),
-            l.mode.to_string(),
-            l.frequency.to_string(),
-            l.opening_hours.to_string(),
-            l.frequency_exceptions.to_string(),
-            shape_to_wkt(&l.shape),
-        ];
-        if all_tags {
-            csv_row = csv_row
-                .into_iter()
-                .chain(
-                    osm_tag_list
-                        .iter()
-                        .map(|k| l.all_osm_tags.get(k).map_or("", |s| s.as_str()).to_string()),
-                )
-                .collect();
-        }
-        lines_wtr.serialize(csv_row).unwrap();
+    write_lines_to_csv_with_options(
+        &lines,
+        output_dir,
+        &sink::CsvOptions::from_all_tags(all_tags),
+    )
+}
 
-        //Writing line-route csv file
-        for r in &l.routes_id {
-            wtr.serialize((format!("Line:{}", &l.id), format!("Route:{}", &r)))
-                .unwrap();
-        }
+pub fn write_lines_to_csv_with_options<P: AsRef<Path>>(
+    lines: &[Line],
+    output_dir: P,
+    options: &sink::CsvOptions,
+) {
+    let tag_columns =
+        tags::TagColumns::new(&options.tags_mode, lines.iter().map(|l| &l.all_osm_tags));
+    let mut wtr = sink::LinesCsv::new(
+        output_dir.as_ref(),
+        &options.prefix,
+        tag_columns,
+        options.geometry_encoding,
+    );
+    for l in lines {
+        wtr.write(l);
     }
 }
@@ -28,7 +28,7 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
-use log::info;
+use log::{info, LevelFilter};
 use osm_transit_extractor::*;
 use simple_logger::SimpleLogger;
 use std::path::PathBuf;
@@ -36,7 +36,29 @@ use structopt::StructOpt;
 
 #[derive(StructOpt)]
 struct Args {
-    #[structopt(long = "input", short = "i", help = "OSM PBF file")]
+    #[structopt(
+        long = "verbose",
+        short = "v",
+        global = true,
+        parse(from_occurrences),
+        help = "Increase log verbosity (-v for debug, -vv for trace)"
+    )]
+    verbose: u8,
+
+    #[structopt(long = "quiet", short = "q", global = true, help = "Only log errors")]
+    quiet: bool,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+struct InputArgs {
+    #[structopt(
+        long = "input",
+        short = "i",
+        help = "OSM PBF file (- to read it from stdin)"
+    )]
     input: String,
 
     #[structopt(
@@ -46,6 +68,84 @@ struct Args {
     )]
     import_stops_only: bool,
 
+    #[structopt(
+        long = "network",
+        help = "Only keep routes and lines of this network (can be repeated)"
+    )]
+    networks: Vec<String>,
+
+    #[structopt(
+        long = "operator",
+        help = "Only keep routes and lines of this operator (can be repeated)"
+    )]
+    operators: Vec<String>,
+
+    #[structopt(
+        long = "mode",
+        help = "Only keep routes and lines of this mode (can be repeated)"
+    )]
+    modes: Vec<String>,
+
+    #[structopt(
+        long = "synthetic-lines",
+        help = "Group the routes that are not part of any line into generated lines (ids prefixed by synthetic:)"
+    )]
+    synthetic_lines: bool,
+
+    #[structopt(
+        long = "hulls",
+        help = "Compute the convex hull of the members of each stop area"
+    )]
+    hulls: bool,
+}
+
+fn parse_precision(precision: &str) -> Result<u32, String> {
+    match precision.parse::<u32>() {
+        Ok(precision) if precision <= simplification::MAX_PRECISION => Ok(precision),
+        _ => Err(format!(
+            "invalid precision {} (expected a number of decimals up to {})",
+            precision,
+            simplification::MAX_PRECISION
+        )),
+    }
+}
+
+#[derive(StructOpt)]
+struct GeometryArgs {
+    #[structopt(
+        long = "precision",
+        parse(try_from_str = parse_precision),
+        help = "Number of decimals of the written coordinates, up to 15 (default is full precision)"
+    )]
+    precision: Option<u32>,
+
+    #[structopt(
+        long = "simplify",
+        default_value = "0",
+        help = "Simplify the shapes with this tolerance in meters (default is no simplification)"
+    )]
+    simplify: f64,
+
+    #[structopt(
+        long = "simplify-algorithm",
+        default_value = "douglas-peucker",
+        help = "Shape simplification algorithm: douglas-peucker or visvalingam"
+    )]
+    simplify_algorithm: simplification::SimplificationAlgorithm,
+}
+
+#[derive(StructOpt)]
+struct PrefixArgs {
+    #[structopt(
+        long = "prefix",
+        default_value = sink::DEFAULT_PREFIX,
+        help = "Prefix of the names of the written files"
+    )]
+    prefix: String,
+}
+
+#[derive(StructOpt)]
+struct TagsArgs {
     #[structopt(
         long = "dump-all-tags",
         short = "t",
@@ -54,46 +154,488 @@ struct Args {
     dump_all_tags: bool,
 
     #[structopt(
-        long = "output",
-        short = "o",
-        default_value = ".",
-        parse(from_os_str),
-        help = "Output directory, can be relative (default is current dir)"
+        long = "tags-as-json",
+        conflicts_with_all = &["dump-all-tags", "tag-keys"],
+        help = "Write all tags from OSM objects as a JSON object in an osm_tags column"
     )]
-    output: PathBuf,
+    tags_as_json: bool,
+
+    #[structopt(
+        long = "tags",
+        use_delimiter = true,
+        conflicts_with = "dump-all-tags",
+        help = "Comma separated list of tags to write as columns (for example name,ref,wheelchair)"
+    )]
+    tag_keys: Vec<String>,
 }
 
-fn main() {
-    SimpleLogger::new().init().unwrap();
-    info!("Launching the process !");
+impl TagsArgs {
+    fn tags_mode(self) -> tags::TagsMode {
+        if self.tags_as_json {
+            tags::TagsMode::Json
+        } else if !self.tag_keys.is_empty() {
+            tags::TagsMode::Whitelist(self.tag_keys)
+        } else {
+            tags::TagsMode::from_all_tags(self.dump_all_tags)
+        }
+    }
+}
 
-    let args = Args::from_args();
+#[derive(StructOpt)]
+enum Command {
+    #[structopt(about = "Extract public transport objects to CSV files")]
+    Extract {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
+
+        #[structopt(flatten)]
+        tags: TagsArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+
+        #[structopt(
+            long = "geometry-encoding",
+            default_value = "wkt",
+            help = "Encoding of the shapes of routes and lines: wkt, polyline, geojson or wkb"
+        )]
+        geometry_encoding: encoding::GeometryEncoding,
+
+        #[structopt(flatten)]
+        prefix: PrefixArgs,
+
+        #[structopt(
+            long = "archive",
+            parse(try_from_str),
+            help = "Write the files in a zip or tar.gz archive (by extension: .zip, .tar.gz or .tgz) instead of the output directory, - for a tar.gz archive on stdout"
+        )]
+        archive: Option<archive::Archive>,
+
+        #[structopt(
+            long = "stats",
+            help = "Display a statistics report and write it as JSON in the output directory"
+        )]
+        stats: bool,
+    },
+    #[structopt(about = "Check the consistency of the public transport objects")]
+    Validate {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            parse(from_os_str),
+            help = "Output directory for the issues CSV file (default is no file)"
+        )]
+        output: Option<PathBuf>,
+
+        #[structopt(flatten)]
+        prefix: PrefixArgs,
+
+        #[structopt(
+            long = "fail-on-issue",
+            help = "Exit with a non zero code when an issue is found"
+        )]
+        fail_on_issue: bool,
+    },
+    #[structopt(about = "Display a statistics report of the extracted objects")]
+    Stats {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            parse(from_os_str),
+            help = "Output directory for the JSON statistics file (default is no file)"
+        )]
+        output: Option<PathBuf>,
+
+        #[structopt(flatten)]
+        prefix: PrefixArgs,
+    },
+    #[structopt(about = "Compare the public transport objects of two OSM files")]
+    Diff {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(long = "old", help = "OSM PBF file to compare the input with")]
+        old: String,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            parse(from_os_str),
+            help = "Output directory for the diff CSV file (default is no file)"
+        )]
+        output: Option<PathBuf>,
+
+        #[structopt(flatten)]
+        prefix: PrefixArgs,
+    },
+    #[structopt(
+        about = "Write public transport objects as a GTFS feed (with synthetic stop times)"
+    )]
+    Gtfs {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+
+        #[structopt(
+            long = "timezone",
+            default_value = "Europe/Paris",
+            help = "Timezone of the agencies"
+        )]
+        timezone: String,
+
+        #[structopt(
+            long = "agency-url",
+            default_value = "https://www.openstreetmap.org",
+            help = "Url of the agencies whose lines have no website tag"
+        )]
+        agency_url: String,
+
+        #[structopt(
+            long = "extended-route-types",
+            help = "Write the extended route types (with the sub-modes of trains and aerialways)"
+        )]
+        extended_route_types: bool,
+    },
+    #[structopt(about = "Write public transport objects as a GeoPackage file")]
+    Gpkg {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
+
+        #[structopt(flatten)]
+        tags: TagsArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+    },
+    #[structopt(about = "Write public transport objects as a SQL script for PostGIS")]
+    Postgis {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
 
-    let mut parsed_pbf = parse_osm_pbf(&args.input);
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+    },
+    #[structopt(about = "Write public transport objects as JSON, to be read back by the library")]
+    Json {
+        #[structopt(flatten)]
+        input: InputArgs,
 
-    let osmtc_response = get_osm_tcobjects(&mut parsed_pbf, args.import_stops_only);
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
 
-    write_stop_points_to_csv(
-        &osmtc_response.stop_points,
-        &args.output,
-        args.dump_all_tags,
-    );
-    write_stop_areas_to_csv(&osmtc_response.stop_areas, &args.output, args.dump_all_tags);
-    write_stop_areas_stop_point_to_csv(&osmtc_response.stop_areas, &args.output);
+        #[structopt(
+            long = "lines",
+            help = "Write JSON lines, one file per object type (default is a single JSON file)"
+        )]
+        json_lines: bool,
 
-    if osmtc_response.routes.is_some() {
-        write_routes_to_csv(
-            osmtc_response.routes.unwrap(),
-            &args.output,
-            args.dump_all_tags,
-        );
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+
+        #[structopt(flatten)]
+        prefix: PrefixArgs,
+    },
+    #[structopt(about = "Write public transport objects as GeoJSON files")]
+    Geojson {
+        #[structopt(flatten)]
+        input: InputArgs,
+
+        #[structopt(flatten)]
+        geometry: GeometryArgs,
+
+        #[structopt(flatten)]
+        tags: TagsArgs,
+
+        #[structopt(
+            long = "output",
+            short = "o",
+            default_value = ".",
+            parse(from_os_str),
+            help = "Output directory, can be relative (default is current dir)"
+        )]
+        output: PathBuf,
+    },
+}
+
+fn log_level(args: &Args) -> LevelFilter {
+    if args.quiet {
+        return LevelFilter::Error;
+    }
+    match args.verbose {
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+fn extract(input: &InputArgs) -> OsmTcResponse {
+    extract_from(&input.input, input, &mut stats::PhaseTimings::default())
+}
+
+fn extract_simplified(input: &InputArgs, geometry: &GeometryArgs) -> OsmTcResponse {
+    extract_simplified_with_timings(input, geometry, &mut stats::PhaseTimings::default())
+}
+
+/// Extracts the objects and simplifies their geometries for an output subcommand.
+fn extract_simplified_with_timings(
+    input: &InputArgs,
+    geometry: &GeometryArgs,
+    timings: &mut stats::PhaseTimings,
+) -> OsmTcResponse {
+    let mut osmtc_response = extract_from(&input.input, input, timings);
+    let geometry_options = simplification::GeometryOptions {
+        precision: geometry.precision,
+        algorithm: geometry.simplify_algorithm,
+        tolerance: geometry.simplify,
+    };
+    if !geometry_options.is_default() {
+        timings.time("geometry_simplification", || {
+            simplification::simplify_osm_tcobjects(&mut osmtc_response, &geometry_options)
+        });
+    }
+    osmtc_response
+}
+
+fn extract_from(path: &str, input: &InputArgs, timings: &mut stats::PhaseTimings) -> OsmTcResponse {
+    let mut parsed_pbf = parse_osm_pbf(path);
+    let mut osmtc_response =
+        get_osm_tcobjects_with_timings(&mut parsed_pbf, input.import_stops_only, timings);
+    if input.hulls {
+        timings.time("stop_areas_hull", || {
+            geometry::compute_stop_areas_hull(
+                &mut osmtc_response.stop_areas,
+                &osmtc_response.stop_points,
+            )
+        });
     }
-    if osmtc_response.lines.is_some() {
-        write_lines_to_csv(
-            osmtc_response.lines.unwrap(),
-            &args.output,
-            args.dump_all_tags,
-        );
+    if input.synthetic_lines {
+        timings.time("synthetic_lines", || {
+            consolidation::add_synthetic_lines(&mut osmtc_response)
+        });
+    }
+    let filter = RouteFilter {
+        networks: input.networks.clone(),
+        operators: input.operators.clone(),
+        modes: input.modes.clone(),
+    };
+    timings.time("filter", || {
+        filter_osm_tcobjects(&mut osmtc_response, &filter)
+    });
+    osmtc_response
+}
+
+fn write_geojson(osmtc_response: &OsmTcResponse, output: &PathBuf, tags_mode: tags::TagsMode) {
+    let mut sink = sink::GeoJsonSink::new(output, tags_mode);
+    sink::write_to_sink(osmtc_response, &mut sink);
+}
+
+fn main() {
+    let args = Args::from_args();
+    SimpleLogger::new()
+        .with_level(log_level(&args))
+        .init()
+        .unwrap();
+    info!("Launching the process !");
+
+    match args.command {
+        Command::Extract {
+            input,
+            geometry,
+            tags,
+            output,
+            geometry_encoding,
+            prefix,
+            archive,
+            stats,
+        } => {
+            let csv_options = sink::CsvOptions {
+                prefix: prefix.prefix.clone(),
+                tags_mode: tags.tags_mode(),
+                hulls: input.hulls,
+                geometry_encoding,
+            };
+            let mut timings = stats::PhaseTimings::default();
+            let osmtc_response = extract_simplified_with_timings(&input, &geometry, &mut timings);
+            let mut report = stats.then(|| stats::compute_stats(&osmtc_response, timings));
+            // the files are written in a temporary directory when they are archived
+            let archive_dir = archive.is_some().then(|| {
+                tempfile::Builder::new()
+                    .prefix("osm_transit_extractor")
+                    .tempdir()
+                    .unwrap()
+            });
+            let output_dir = archive_dir
+                .as_ref()
+                .map_or(output.clone(), |d| d.path().into());
+            let mut write_timings = stats::PhaseTimings::default();
+            write_timings.time("write_csv", || {
+                let mut sink = sink::CsvSink::new(&output_dir, csv_options);
+                sink::write_to_sink(&osmtc_response, &mut sink)
+            });
+            if let Some(report) = report.as_mut() {
+                report.timings.0.extend(write_timings.0);
+                if archive.as_ref().is_some_and(|a| a.is_stdout()) {
+                    eprint!("{}", report);
+                } else {
+                    print!("{}", report);
+                }
+                stats::write_stats_to_json(report, &output_dir, &prefix.prefix);
+            }
+            if let (Some(archive_dir), Some(archive)) = (archive_dir, &archive) {
+                archive::write_archive(&archive_dir, archive);
+            }
+        }
+        Command::Validate {
+            input,
+            output,
+            prefix,
+            fail_on_issue,
+        } => {
+            let issues = validation::validate(&extract(&input));
+            for issue in &issues {
+                info!("{} : {}", issue.object_id, issue.message);
+            }
+            info!("{} issues found", issues.len());
+            if let Some(output) = output {
+                validation::write_issues_to_csv(&issues, output, &prefix.prefix);
+            }
+            if fail_on_issue && !issues.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Command::Stats {
+            input,
+            output,
+            prefix,
+        } => {
+            let mut timings = stats::PhaseTimings::default();
+            let osmtc_response = extract_from(&input.input, &input, &mut timings);
+            let report = stats::compute_stats(&osmtc_response, timings);
+            print!("{}", report);
+            if let Some(output) = output {
+                stats::write_stats_to_json(&report, output, &prefix.prefix);
+            }
+        }
+        Command::Diff {
+            input,
+            old,
+            output,
+            prefix,
+        } => {
+            let old_response = extract_from(&old, &input, &mut stats::PhaseTimings::default());
+            let changes = diff::diff_osm_tcobjects(&old_response, &extract(&input));
+            for change in &changes {
+                println!("{:?} {}", change.kind, change.object_id);
+            }
+            if let Some(output) = output {
+                diff::write_changes_to_csv(&changes, output, &prefix.prefix);
+            }
+        }
+        Command::Gtfs {
+            input,
+            geometry,
+            output,
+            timezone,
+            agency_url,
+            extended_route_types,
+        } => {
+            let options = gtfs::GtfsOptions {
+                timezone,
+                agency_url,
+                extended_route_types,
+                ..Default::default()
+            };
+            gtfs::write_gtfs(&extract_simplified(&input, &geometry), &output, &options);
+        }
+        Command::Gpkg {
+            input,
+            geometry,
+            tags,
+            output,
+        } => geopackage::write_geopackage(
+            &extract_simplified(&input, &geometry),
+            &output,
+            &tags.tags_mode(),
+        ),
+        Command::Postgis {
+            input,
+            geometry,
+            output,
+        } => postgis::write_postgis_sql(&extract_simplified(&input, &geometry), &output),
+        Command::Json {
+            input,
+            geometry,
+            json_lines,
+            output,
+            prefix,
+        } => {
+            let osmtc_response = extract_simplified(&input, &geometry);
+            if json_lines {
+                json::write_json_lines(&osmtc_response, &output, &prefix.prefix)
+            } else {
+                json::write_json(&osmtc_response, &output, &prefix.prefix)
+            }
+        }
+        Command::Geojson {
+            input,
+            geometry,
+            tags,
+            output,
+        } => write_geojson(
+            &extract_simplified(&input, &geometry),
+            &output,
+            tags.tags_mode(),
+        ),
     }
     info!("end of osm-transit-extractor !")
 }
@@ -0,0 +1,229 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::{distance, interpolate, length, stitch_shape};
+use crate::pairing::{halt_stop, route_halts};
+use crate::{Coord, Line, Route};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn from_coords<'a, I: IntoIterator<Item = &'a Coord>>(coords: I) -> Option<BoundingBox> {
+        coords.into_iter().fold(None, |bbox, c| {
+            Some(match bbox {
+                None => BoundingBox {
+                    min_lat: c.lat,
+                    min_lon: c.lon,
+                    max_lat: c.lat,
+                    max_lon: c.lon,
+                },
+                Some(b) => BoundingBox {
+                    min_lat: b.min_lat.min(c.lat),
+                    min_lon: b.min_lon.min(c.lon),
+                    max_lat: b.max_lat.max(c.lat),
+                    max_lon: b.max_lon.max(c.lon),
+                },
+            })
+        })
+    }
+}
+
+/// Geometric figures of a Route or a Line. Distances are in meters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// geodesic length of the shape
+    pub length: f64,
+    pub stop_count: usize,
+    /// mean and max distances along the shape between consecutive located stops
+    pub mean_stop_distance: Option<f64>,
+    pub max_stop_distance: Option<f64>,
+    pub bbox: Option<BoundingBox>,
+    /// straight-line distance between the first and the last stop
+    pub origin_destination_distance: Option<f64>,
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn max(values: &[f64]) -> Option<f64> {
+    values
+        .iter()
+        .cloned()
+        .fold(None, |m, v| Some(m.map_or(v, |m: f64| m.max(v))))
+}
+
+/// Straight-line distance between the first and the last located stops of the route,
+/// or between the ends of the shape when no stop is located.
+fn origin_destination_distance(route: &Route, linestring: &[Coord]) -> Option<f64> {
+    let mut located = route
+        .ordered_route_points
+        .iter()
+        .filter_map(|rp| rp.shape_dist_traveled);
+    let (origin, destination) = match located.next() {
+        Some(first) => {
+            let last = located.next_back().unwrap_or(first);
+            (
+                interpolate(linestring, first)?,
+                interpolate(linestring, last)?,
+            )
+        }
+        None => (linestring.first()?.clone(), linestring.last()?.clone()),
+    };
+    Some(distance(&origin, &destination))
+}
+
+/// Distances along the shape between each located halt of the route and the previous one
+/// (see `pairing::route_halts`). A halt is located by its stop position when it is located.
+fn inter_halt_distances(route: &Route) -> Vec<f64> {
+    let mut previous: Option<f64> = None;
+    let mut distances = vec![];
+    for halt in route_halts(&route.ordered_route_points) {
+        let located = halt_stop(halt)
+            .shape_dist_traveled
+            .or_else(|| halt.iter().find_map(|rp| rp.shape_dist_traveled));
+        if let Some(d) = located {
+            if let Some(p) = previous {
+                distances.push(d - p);
+            }
+            previous = Some(d);
+        }
+    }
+    distances
+}
+
+pub fn route_metrics(route: &Route) -> Metrics {
+    let linestring = stitch_shape(&route.shape);
+    let stop_distances = inter_halt_distances(route);
+    Metrics {
+        length: length(&linestring),
+        stop_count: route_halts(&route.ordered_route_points).len(),
+        mean_stop_distance: mean(&stop_distances),
+        max_stop_distance: max(&stop_distances),
+        bbox: BoundingBox::from_coords(route.shape.iter().flatten()),
+        origin_destination_distance: origin_destination_distance(route, &linestring),
+    }
+}
+
+/// Metrics of a line, from the metrics of its routes: the length is the one of the
+/// ways of the line counted once whatever their direction, the stops are the distinct
+/// halts of the routes (identified by their stop position) and the origin-destination distance is the longest one of the routes.
+pub fn line_metrics(line: &Line, routes: &[&Route]) -> Metrics {
+    let mut ways: Vec<&Vec<Coord>> = vec![];
+    for way in &line.shape {
+        let is_known = ways
+            .iter()
+            .any(|w| *w == way || w.iter().rev().eq(way.iter()));
+        if !is_known {
+            ways.push(way);
+        }
+    }
+    let stops: BTreeSet<&str> = routes
+        .iter()
+        .flat_map(|r| route_halts(&r.ordered_route_points))
+        .map(|halt| halt_stop(halt).stop_point_id.as_str())
+        .collect();
+    let stop_distances: Vec<f64> = routes
+        .iter()
+        .flat_map(|r| inter_halt_distances(r))
+        .collect();
+    let origin_destination_distances: Vec<f64> = routes
+        .iter()
+        .filter_map(|r| r.metrics.origin_destination_distance)
+        .collect();
+    Metrics {
+        length: ways.iter().map(|w| length(w)).sum(),
+        stop_count: stops.len(),
+        mean_stop_distance: mean(&stop_distances),
+        max_stop_distance: max(&stop_distances),
+        bbox: BoundingBox::from_coords(line.shape.iter().flatten()),
+        origin_destination_distance: max(&origin_destination_distances),
+    }
+}
+
+pub fn compute_routes_metrics(routes: &mut [Route]) {
+    for route in routes.iter_mut() {
+        route.metrics = route_metrics(route);
+    }
+}
+
+/// Computes the metrics of the lines, the metrics of the routes having to be computed first.
+pub fn compute_lines_metrics(lines: &mut [Line], routes: &[Route]) {
+    let routes_by_id: BTreeMap<&str, &Route> = routes.iter().map(|r| (r.id.as_str(), r)).collect();
+    for line in lines.iter_mut() {
+        let line_routes: Vec<&Route> = line
+            .routes_id
+            .iter()
+            .filter_map(|id| routes_by_id.get(id.as_str()).copied())
+            .collect();
+        line.metrics = line_metrics(line, &line_routes);
+    }
+}
+
+/// Values of the metrics as CSV columns (see `CSV_HEADER`), distances rounded to the decimeter.
+pub(crate) fn to_csv_columns(metrics: &Metrics) -> Vec<String> {
+    let fmt_distance = |d: Option<f64>| d.map_or(String::new(), |d| format!("{:.1}", d));
+    let bbox = metrics.bbox.as_ref();
+    vec![
+        format!("{:.1}", metrics.length),
+        metrics.stop_count.to_string(),
+        fmt_distance(metrics.mean_stop_distance),
+        fmt_distance(metrics.max_stop_distance),
+        bbox.map_or(String::new(), |b| b.min_lat.to_string()),
+        bbox.map_or(String::new(), |b| b.min_lon.to_string()),
+        bbox.map_or(String::new(), |b| b.max_lat.to_string()),
+        bbox.map_or(String::new(), |b| b.max_lon.to_string()),
+        fmt_distance(metrics.origin_destination_distance),
+    ]
+}
+
+pub(crate) const CSV_HEADER: [&str; 9] = [
+    "length",
+    "stop_count",
+    "mean_stop_distance",
+    "max_stop_distance",
+    "bbox_min_lat",
+    "bbox_min_lon",
+    "bbox_max_lat",
+    "bbox_max_lon",
+    "origin_destination_distance",
+];
@@ -0,0 +1,291 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Mode of a Route or a Line, from its `route` (or `route_master`) tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    Bus,
+    Trolleybus,
+    Coach,
+    ShareTaxi,
+    Tram,
+    LightRail,
+    Subway,
+    Monorail,
+    /// `train`, `railway` and `rail`
+    Train,
+    Funicular,
+    Aerialway,
+    Ferry,
+    Unknown,
+}
+
+impl Mode {
+    pub fn from_osm(mode: &str) -> Mode {
+        match mode {
+            "bus" => Mode::Bus,
+            "trolleybus" => Mode::Trolleybus,
+            "coach" => Mode::Coach,
+            "share_taxi" => Mode::ShareTaxi,
+            "tram" => Mode::Tram,
+            "light_rail" => Mode::LightRail,
+            "subway" => Mode::Subway,
+            "monorail" => Mode::Monorail,
+            "train" | "railway" | "rail" => Mode::Train,
+            "funicular" => Mode::Funicular,
+            "aerialway" => Mode::Aerialway,
+            "ferry" => Mode::Ferry,
+            _ => Mode::Unknown,
+        }
+    }
+}
+
+/// Sub-mode of a train (`service` tag) or of an aerialway (`aerialway` tag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubMode {
+    HighSpeed,
+    LongDistance,
+    Regional,
+    Commuter,
+    Night,
+    CableCar,
+    Gondola,
+    MixedLift,
+    ChairLift,
+    /// `drag_lift`, `t-bar`, `j-bar`, `platter`, `rope_tow` and `magic_carpet`
+    DragLift,
+}
+
+const SUB_MODES: [(SubMode, &str); 10] = [
+    (SubMode::HighSpeed, "high_speed"),
+    (SubMode::LongDistance, "long_distance"),
+    (SubMode::Regional, "regional"),
+    (SubMode::Commuter, "commuter"),
+    (SubMode::Night, "night"),
+    (SubMode::CableCar, "cable_car"),
+    (SubMode::Gondola, "gondola"),
+    (SubMode::MixedLift, "mixed_lift"),
+    (SubMode::ChairLift, "chair_lift"),
+    (SubMode::DragLift, "drag_lift"),
+];
+
+impl fmt::Display for SubMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, value) = SUB_MODES.iter().find(|(m, _)| m == self).unwrap();
+        write!(f, "{}", value)
+    }
+}
+
+impl FromStr for SubMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "t-bar" | "j-bar" | "platter" | "rope_tow" | "magic_carpet" => Ok(SubMode::DragLift),
+            _ => SUB_MODES
+                .iter()
+                .find(|(_, value)| *value == s)
+                .map(|(m, _)| *m)
+                .ok_or_else(|| format!("unknown sub-mode {}", s)),
+        }
+    }
+}
+
+/// Mode and sub-mode of a Route or a Line, with their physical and commercial modes and
+/// their GTFS and NeTEx equivalents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportMode {
+    pub mode: Mode,
+    pub sub_mode: Option<SubMode>,
+}
+
+impl TransportMode {
+    /// The sub-mode is kept only if it is one of the mode.
+    pub fn new(mode: &str, sub_mode: Option<SubMode>) -> TransportMode {
+        let mode = Mode::from_osm(mode);
+        let sub_mode = sub_mode.filter(|s| match mode {
+            Mode::Train => matches!(
+                s,
+                SubMode::HighSpeed
+                    | SubMode::LongDistance
+                    | SubMode::Regional
+                    | SubMode::Commuter
+                    | SubMode::Night
+            ),
+            Mode::Aerialway => matches!(
+                s,
+                SubMode::CableCar
+                    | SubMode::Gondola
+                    | SubMode::MixedLift
+                    | SubMode::ChairLift
+                    | SubMode::DragLift
+            ),
+            _ => false,
+        });
+        TransportMode { mode, sub_mode }
+    }
+
+    /// From the tags of a Route (`mode_key` is `route`) or a Line (`route_master`).
+    pub fn from_tags(tags: &osmpbfreader::Tags, mode_key: &str) -> TransportMode {
+        let mode = tags.get(mode_key).map_or("", |v| v.as_str());
+        let sub_mode_key = match Mode::from_osm(mode) {
+            Mode::Aerialway => "aerialway",
+            _ => "service",
+        };
+        let sub_mode = tags.get(sub_mode_key).and_then(|v| v.parse().ok());
+        TransportMode::new(mode, sub_mode)
+    }
+
+    /// Physical mode, as the ids of the NTFS physical modes.
+    pub fn physical_mode(&self) -> &'static str {
+        match (self.mode, self.sub_mode) {
+            (Mode::Bus, _) | (Mode::Trolleybus, _) | (Mode::Unknown, _) => "Bus",
+            (Mode::Coach, _) => "Coach",
+            (Mode::ShareTaxi, _) => "Taxi",
+            (Mode::Tram, _) | (Mode::LightRail, _) => "Tramway",
+            (Mode::Subway, _) | (Mode::Monorail, _) => "Metro",
+            (Mode::Train, Some(SubMode::HighSpeed))
+            | (Mode::Train, Some(SubMode::LongDistance))
+            | (Mode::Train, Some(SubMode::Night)) => "LongDistanceTrain",
+            (Mode::Train, Some(SubMode::Regional)) => "LocalTrain",
+            (Mode::Train, Some(SubMode::Commuter)) => "RapidTransit",
+            (Mode::Train, _) => "Train",
+            (Mode::Funicular, _) => "Funicular",
+            (Mode::Aerialway, _) => "SuspendedCableCar",
+            (Mode::Ferry, _) => "Ferry",
+        }
+    }
+
+    /// Commercial mode, the name of the mode for the passengers.
+    pub fn commercial_mode(&self) -> &'static str {
+        match (self.mode, self.sub_mode) {
+            (Mode::Bus, _) | (Mode::Unknown, _) => "Bus",
+            (Mode::Trolleybus, _) => "Trolleybus",
+            (Mode::Coach, _) => "Coach",
+            (Mode::ShareTaxi, _) => "Shared taxi",
+            (Mode::Tram, _) => "Tramway",
+            (Mode::LightRail, _) => "Light rail",
+            (Mode::Subway, _) => "Metro",
+            (Mode::Monorail, _) => "Monorail",
+            (Mode::Train, Some(SubMode::HighSpeed)) => "High speed train",
+            (Mode::Train, Some(SubMode::LongDistance)) => "Long distance train",
+            (Mode::Train, Some(SubMode::Regional)) => "Regional train",
+            (Mode::Train, Some(SubMode::Commuter)) => "Commuter train",
+            (Mode::Train, Some(SubMode::Night)) => "Night train",
+            (Mode::Train, _) => "Train",
+            (Mode::Funicular, _) => "Funicular",
+            (Mode::Aerialway, Some(SubMode::CableCar)) => "Cable car",
+            (Mode::Aerialway, Some(SubMode::Gondola))
+            | (Mode::Aerialway, Some(SubMode::MixedLift)) => "Gondola",
+            (Mode::Aerialway, Some(SubMode::ChairLift)) => "Chair lift",
+            (Mode::Aerialway, Some(SubMode::DragLift)) => "Drag lift",
+            (Mode::Aerialway, _) => "Aerialway",
+            (Mode::Ferry, _) => "Ferry",
+        }
+    }
+
+    /// GTFS `route_type` (https://gtfs.org/schedule/reference/#routestxt).
+    pub fn gtfs_route_type(&self) -> u16 {
+        match self.mode {
+            Mode::Tram | Mode::LightRail => 0,
+            Mode::Subway => 1,
+            Mode::Train => 2,
+            Mode::Bus | Mode::Coach | Mode::ShareTaxi | Mode::Unknown => 3,
+            Mode::Ferry => 4,
+            Mode::Aerialway => 6,
+            Mode::Funicular => 7,
+            Mode::Trolleybus => 11,
+            Mode::Monorail => 12,
+        }
+    }
+
+    /// Extended GTFS `route_type`
+    /// (https://developers.google.com/transit/gtfs/reference/extended-route-types).
+    pub fn gtfs_extended_route_type(&self) -> u16 {
+        match (self.mode, self.sub_mode) {
+            (Mode::Train, Some(SubMode::HighSpeed)) => 101,
+            (Mode::Train, Some(SubMode::LongDistance)) => 102,
+            (Mode::Train, Some(SubMode::Night)) => 105,
+            (Mode::Train, Some(SubMode::Regional)) => 106,
+            (Mode::Train, Some(SubMode::Commuter)) => 109,
+            (Mode::Train, _) => 100,
+            (Mode::Coach, _) => 200,
+            (Mode::Subway, _) => 401,
+            (Mode::Monorail, _) => 405,
+            (Mode::Bus, _) | (Mode::Unknown, _) => 700,
+            (Mode::Trolleybus, _) => 800,
+            (Mode::Tram, _) | (Mode::LightRail, _) => 900,
+            (Mode::Ferry, _) => 1200,
+            (Mode::Aerialway, Some(SubMode::Gondola))
+            | (Mode::Aerialway, Some(SubMode::MixedLift)) => 1301,
+            (Mode::Aerialway, Some(SubMode::CableCar)) => 1302,
+            (Mode::Aerialway, Some(SubMode::ChairLift)) => 1304,
+            (Mode::Aerialway, Some(SubMode::DragLift)) => 1305,
+            (Mode::Aerialway, _) => 1300,
+            (Mode::Funicular, _) => 1400,
+            (Mode::ShareTaxi, _) => 1501,
+        }
+    }
+
+    /// NeTEx `VehicleModeEnumeration` value.
+    pub fn netex_mode(&self) -> &'static str {
+        match self.mode {
+            Mode::Bus | Mode::Unknown => "bus",
+            Mode::Trolleybus => "trolleyBus",
+            Mode::Coach => "coach",
+            Mode::ShareTaxi => "taxi",
+            Mode::Tram | Mode::LightRail => "tram",
+            Mode::Subway | Mode::Monorail => "metro",
+            Mode::Train => "rail",
+            Mode::Funicular => "funicular",
+            Mode::Aerialway => "cableway",
+            Mode::Ferry => "ferry",
+        }
+    }
+
+    /// NeTEx sub-mode (`RailSubmode` or `TelecabinSubmode` value).
+    pub fn netex_sub_mode(&self) -> Option<&'static str> {
+        self.sub_mode.map(|sub_mode| match sub_mode {
+            SubMode::HighSpeed => "highSpeedRail",
+            SubMode::LongDistance => "longDistance",
+            SubMode::Regional => "regionalRail",
+            SubMode::Commuter => "suburbanRailway",
+            SubMode::Night => "nightRail",
+            SubMode::CableCar => "cableCar",
+            SubMode::Gondola | SubMode::MixedLift => "telecabin",
+            SubMode::ChairLift => "chairLift",
+            SubMode::DragLift => "dragLift",
+        })
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Weekday {
+    Mo,
+    Tu,
+    We,
+    Th,
+    Fr,
+    Sa,
+    Su,
+}
+
+pub const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mo,
+    Weekday::Tu,
+    Weekday::We,
+    Weekday::Th,
+    Weekday::Fr,
+    Weekday::Sa,
+    Weekday::Su,
+];
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Weekday {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WEEKDAYS
+            .iter()
+            .find(|d| d.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown weekday {}", s))
+    }
+}
+
+/// Minutes from midnight. A range ending after midnight ends after `24:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+pub(crate) const DAY_MINUTES: u16 = 24 * 60;
+
+pub(crate) const WHOLE_DAY: TimeRange = TimeRange {
+    start: 0,
+    end: DAY_MINUTES,
+};
+
+fn format_time(minutes: u16) -> String {
+    let minutes = if minutes > DAY_MINUTES {
+        minutes - DAY_MINUTES
+    } else {
+        minutes
+    };
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+pub(crate) fn parse_time(time: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time {}", time);
+    let (hours, minutes) = time.split_once(':').ok_or_else(invalid)?;
+    if hours.is_empty() || hours.len() > 2 || minutes.len() != 2 {
+        return Err(invalid());
+    }
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if minutes >= 60 || hours * 60 + minutes > DAY_MINUTES {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", format_time(self.start), format_time(self.end))
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("invalid time range {}", s))?;
+        let start = parse_time(start)?;
+        let mut end = parse_time(end)?;
+        if start == DAY_MINUTES {
+            return Err(format!("invalid time range {}", s));
+        }
+        if end <= start {
+            end += DAY_MINUTES;
+        }
+        Ok(TimeRange { start, end })
+    }
+}
+
+impl TimeRange {
+    pub fn contains(&self, minute: u16) -> bool {
+        self.start <= minute && minute < self.end
+    }
+
+    /// The parts of the range outside of another one.
+    pub fn minus(&self, other: &TimeRange) -> Vec<TimeRange> {
+        if other.end <= self.start || self.end <= other.start {
+            return vec![*self];
+        }
+        let mut parts = vec![];
+        if self.start < other.start {
+            parts.push(TimeRange {
+                start: self.start,
+                end: other.start,
+            });
+        }
+        if other.end < self.end {
+            parts.push(TimeRange {
+                start: other.end,
+                end: self.end,
+            });
+        }
+        parts
+    }
+}
+
+/// The parts of time ranges outside of other ones.
+pub(crate) fn subtract_time_ranges(ranges: &[TimeRange], others: &[TimeRange]) -> Vec<TimeRange> {
+    others.iter().fold(ranges.to_vec(), |ranges, other| {
+        ranges.iter().flat_map(|r| r.minus(other)).collect()
+    })
+}
+
+/// A rule of an `opening_hours` value. A rule without days applies every day, and a
+/// rule without time ranges the whole day. An additional rule (after a `,`) adds its time
+/// ranges to the ones of the earlier rules, or with `off` removes them, instead of
+/// overriding them.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Rule {
+    pub weekdays: Vec<Weekday>,
+    pub public_holidays: bool,
+    pub school_holidays: bool,
+    pub time_ranges: Vec<TimeRange>,
+    pub off: bool,
+    pub additional: bool,
+}
+
+impl Rule {
+    fn applies(&self, weekday: Weekday, public_holiday: bool) -> bool {
+        if public_holiday && self.public_holidays {
+            true
+        } else {
+            self.weekdays.contains(&weekday)
+        }
+    }
+
+    fn add_days(&mut self, token: &str) -> Result<(), String> {
+        match token {
+            "PH" => self.public_holidays = true,
+            "SH" => self.school_holidays = true,
+            _ => match token.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (first.parse::<Weekday>()?, last.parse::<Weekday>()?);
+                    let mut day = first as usize;
+                    loop {
+                        self.weekdays.push(WEEKDAYS[day]);
+                        if day == last as usize {
+                            break;
+                        }
+                        day = (day + 1) % 7;
+                    }
+                }
+                None => self.weekdays.push(token.parse()?),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Days as in `opening_hours`, the consecutive days as ranges (`Mo-Fr,Su,PH`).
+pub fn format_days(weekdays: &[Weekday], public_holidays: bool, school_holidays: bool) -> String {
+    let mut days: Vec<String> = vec![];
+    let mut idx = 0;
+    while idx < 7 {
+        if !weekdays.contains(&WEEKDAYS[idx]) {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx + 1 < 7 && weekdays.contains(&WEEKDAYS[idx + 1]) {
+            idx += 1;
+        }
+        match idx - start {
+            0 => days.push(WEEKDAYS[start].to_string()),
+            1 => days.extend(vec![WEEKDAYS[start].to_string(), WEEKDAYS[idx].to_string()]),
+            _ => days.push(format!("{}-{}", WEEKDAYS[start], WEEKDAYS[idx])),
+        }
+        idx += 1;
+    }
+    if public_holidays {
+        days.push("PH".to_string());
+    }
+    if school_holidays {
+        days.push("SH".to_string());
+    }
+    days.join(",")
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        // every day is the default
+        if self.weekdays.len() < 7 || self.public_holidays || self.school_holidays {
+            parts.push(format_days(
+                &self.weekdays,
+                self.public_holidays,
+                self.school_holidays,
+            ));
+        }
+        if self.off {
+            parts.push("off".to_string());
+        } else if self.time_ranges != [WHOLE_DAY] {
+            let time_ranges: Vec<String> = self.time_ranges.iter().map(|t| t.to_string()).collect();
+            parts.push(time_ranges.join(","));
+        } else if parts.is_empty() {
+            parts.push("00:00-24:00".to_string());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Service calendar parsed from an `opening_hours` tag, with the subset of the syntax
+/// (https://wiki.openstreetmap.org/wiki/Key:opening_hours/specification) used on public
+/// transport : rules separated by `;`, made of weekdays (`Mo`, `Mo-Fr`, `Sa,Su`), public
+/// and school holidays (`PH`, `SH`), time ranges (`06:00-09:30,16:00-20:00`, possibly
+/// ending after midnight) and `off`, plus `24/7`. The later rules override the earlier
+/// ones on the days they apply to, except the additional rules separated by `,`
+/// (`Mo-Fr 08:00-12:00, Fr 14:00-18:00`) which are merged into them.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OpeningHours {
+    pub rules: Vec<Rule>,
+}
+
+fn parse_rule(rule: &str) -> Result<Vec<Rule>, String> {
+    if rule == "24/7" {
+        return Ok(vec![Rule {
+            weekdays: WEEKDAYS.to_vec(),
+            time_ranges: vec![WHOLE_DAY],
+            ..Default::default()
+        }]);
+    }
+    let tokens = rule
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty());
+    let mut rules = vec![];
+    let mut current = Rule::default();
+    let mut has_days = false;
+    for token in tokens {
+        let is_time = token.starts_with(|c: char| c.is_ascii_digit());
+        if token == "off" || token == "closed" {
+            current.off = true;
+        } else if is_time {
+            current.time_ranges.push(token.parse()?);
+        } else {
+            // days after times or off start an additional rule ("Mo-Fr 08:00-12:00, Sa 09:00-11:00")
+            if !current.time_ranges.is_empty() || current.off {
+                rules.push(std::mem::take(&mut current));
+                current.additional = true;
+            }
+            current
+                .add_days(token)
+                .map_err(|_| format!("unsupported selector {}", token))?;
+            has_days = true;
+        }
+    }
+    if !has_days && !current.off && current.time_ranges.is_empty() {
+        return Err(format!("empty rule {}", rule));
+    }
+    rules.push(current);
+    for rule in rules.iter_mut() {
+        if rule.weekdays.is_empty() && !rule.public_holidays && !rule.school_holidays {
+            rule.weekdays = WEEKDAYS.to_vec();
+        }
+        rule.weekdays.sort();
+        rule.weekdays.dedup();
+        if rule.time_ranges.is_empty() && !rule.off {
+            rule.time_ranges.push(WHOLE_DAY);
+        }
+    }
+    Ok(rules)
+}
+
+impl FromStr for OpeningHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = vec![];
+        for rule in s.split(';').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+            rules.extend(parse_rule(rule)?);
+        }
+        if rules.is_empty() {
+            return Err("empty opening_hours".to_string());
+        }
+        Ok(OpeningHours { rules })
+    }
+}
+
+impl fmt::Display for OpeningHours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, rule) in self.rules.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "{}", if rule.additional { ", " } else { "; " })?;
+            }
+            write!(f, "{}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl OpeningHours {
+    /// The time ranges with a service on a day: the ones of the last rule that applies
+    /// to it, merged with the ones of the additional rules that follow it.
+    pub fn time_ranges(&self, weekday: Weekday, public_holiday: bool) -> Vec<TimeRange> {
+        let mut time_ranges = vec![];
+        for rule in self
+            .rules
+            .iter()
+            .filter(|r| r.applies(weekday, public_holiday))
+        {
+            // an off rule without time ranges is off the whole day
+            let rule_ranges = if rule.time_ranges.is_empty() {
+                vec![WHOLE_DAY]
+            } else {
+                rule.time_ranges.clone()
+            };
+            time_ranges = match (rule.additional, rule.off) {
+                (false, false) => rule_ranges,
+                (false, true) => vec![],
+                (true, false) => [time_ranges, rule_ranges].concat(),
+                (true, true) => subtract_time_ranges(&time_ranges, &rule_ranges),
+            };
+        }
+        time_ranges
+    }
+
+    /// Tells if there is a service on a day at a given minute from midnight. The school
+    /// holidays, which depend on the region, are not taken into account.
+    pub fn is_open(&self, weekday: Weekday, public_holiday: bool, minute: u16) -> bool {
+        self.time_ranges(weekday, public_holiday)
+            .iter()
+            .any(|t| t.contains(minute))
+    }
+
+    /// The days of the week with a service (outside of the holidays).
+    pub fn service_days(&self) -> Vec<Weekday> {
+        WEEKDAYS
+            .iter()
+            .filter(|d| !self.time_ranges(**d, false).is_empty())
+            .cloned()
+            .collect()
+    }
+}
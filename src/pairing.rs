@@ -0,0 +1,279 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::distance;
+use crate::{Coord, Route, RoutePoint, StopArea, StopPoint, StopPointType};
+use log::info;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Distance (in meters) under which a platform and a stop position with the same name
+/// are considered as the same halt.
+pub const MAX_PAIRING_DISTANCE: f64 = 50.;
+
+/// Size (in degrees) of the cells of the grid used to find the stop positions close
+/// to a platform. A degree of latitude is at least 110.5km long, so two points less
+/// than `MAX_PAIRING_DISTANCE` apart are at most one row of cells apart.
+const GRID_CELL_SIZE: f64 = MAX_PAIRING_DISTANCE / 110_500.;
+
+fn grid_cell(coord: &Coord) -> (i64, i64) {
+    (
+        (coord.lat / GRID_CELL_SIZE).floor() as i64,
+        (coord.lon / GRID_CELL_SIZE).floor() as i64,
+    )
+}
+
+/// Cells around `coord` holding all the points less than `MAX_PAIRING_DISTANCE` away.
+/// A degree of longitude gets shorter away from the equator, so more columns are needed.
+fn neighbour_cells(coord: &Coord) -> impl Iterator<Item = (i64, i64)> {
+    let (row, column) = grid_cell(coord);
+    let cos_lat = (coord.lat.abs() + GRID_CELL_SIZE)
+        .min(90.)
+        .to_radians()
+        .cos()
+        .max(0.01);
+    let columns = (1. / cos_lat).ceil() as i64;
+    (row - 1..=row + 1)
+        .flat_map(move |r| (column - columns..=column + columns).map(move |c| (r, c)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RoleKind {
+    Stop,
+    Platform,
+}
+
+fn role_kind(role: &str) -> Option<RoleKind> {
+    if role.starts_with("stop") {
+        Some(RoleKind::Stop)
+    } else if role.starts_with("platform") {
+        Some(RoleKind::Platform)
+    } else {
+        None
+    }
+}
+
+/// Groups the route points of a route into halts: a stop position and a platform that
+/// are consecutive members (in any order) are the same halt, any other point is a halt.
+pub fn route_halts(route_points: &[RoutePoint]) -> Vec<&[RoutePoint]> {
+    let mut halts = vec![];
+    let mut idx = 0;
+    while idx < route_points.len() {
+        let size = match route_points.get(idx..idx + 2) {
+            Some([a, b]) => match (role_kind(&a.role), role_kind(&b.role)) {
+                (Some(RoleKind::Platform), Some(RoleKind::Stop))
+                | (Some(RoleKind::Stop), Some(RoleKind::Platform)) => 2,
+                _ => 1,
+            },
+            _ => 1,
+        };
+        halts.push(&route_points[idx..idx + size]);
+        idx += size;
+    }
+    halts
+}
+
+/// The member with a `stop` role of a halt (see `route_halts`), or its only member.
+pub fn halt_stop(halt: &[RoutePoint]) -> &RoutePoint {
+    halt.iter()
+        .find(|rp| role_kind(&rp.role) == Some(RoleKind::Stop))
+        .unwrap_or(&halt[0])
+}
+
+/// Counts, for each (platform, stop position) couple, the routes in which they are
+/// consecutive members (in any order).
+fn route_candidates(routes: &[Route]) -> BTreeMap<(&str, &str), usize> {
+    let mut candidates = BTreeMap::new();
+    for route in routes {
+        for halt in route_halts(&route.ordered_route_points) {
+            if let [a, b] = halt {
+                let couple = if role_kind(&a.role) == Some(RoleKind::Platform) {
+                    (a.stop_point_id.as_str(), b.stop_point_id.as_str())
+                } else {
+                    (b.stop_point_id.as_str(), a.stop_point_id.as_str())
+                };
+                *candidates.entry(couple).or_insert(0) += 1;
+            }
+        }
+    }
+    candidates
+}
+
+struct Pairing<'a> {
+    stop_points: BTreeMap<&'a str, &'a StopPoint>,
+    pairs: BTreeMap<&'a str, &'a str>,
+    paired: BTreeSet<&'a str>,
+}
+
+impl<'a> Pairing<'a> {
+    fn is_free(&self, id: &str, stop_point_type: StopPointType) -> bool {
+        !self.paired.contains(id)
+            && self
+                .stop_points
+                .get(id)
+                .is_some_and(|sp| sp.stop_point_type == stop_point_type)
+    }
+
+    fn pair(&mut self, platform_id: &'a str, stop_position_id: &'a str) -> bool {
+        if !self.is_free(platform_id, StopPointType::Platform)
+            || !self.is_free(stop_position_id, StopPointType::StopPosition)
+        {
+            return false;
+        }
+        self.paired.insert(platform_id);
+        self.paired.insert(stop_position_id);
+        self.pairs.insert(platform_id, stop_position_id);
+        true
+    }
+
+    /// Pairs the (distance, platform, stop position) couples, closest first.
+    fn pair_couples(&mut self, mut couples: Vec<(f64, &'a str, &'a str)>) -> usize {
+        couples.sort_by(|a, b| {
+            a.0.total_cmp(&b.0)
+                .then_with(|| (a.1, a.2).cmp(&(b.1, b.2)))
+        });
+        couples
+            .into_iter()
+            .filter(|(_, platform_id, stop_position_id)| self.pair(platform_id, stop_position_id))
+            .count()
+    }
+
+    /// Pairs each free platform among `candidates` with the closest free stop position
+    /// among `candidates`.
+    fn pair_closest(&mut self, candidates: &[&'a str]) -> usize {
+        let of_type = |stop_point_type| {
+            candidates
+                .iter()
+                .filter_map(|id| self.stop_points.get(id).copied())
+                .filter(move |sp| sp.stop_point_type == stop_point_type)
+        };
+        let mut couples = vec![];
+        for platform in of_type(StopPointType::Platform) {
+            for stop_position in of_type(StopPointType::StopPosition) {
+                let d = distance(&platform.coord, &stop_position.coord);
+                couples.push((d, platform.id.as_str(), stop_position.id.as_str()));
+            }
+        }
+        self.pair_couples(couples)
+    }
+
+    /// Pairs each free platform with the closest free stop position with the same name
+    /// within `MAX_PAIRING_DISTANCE`.
+    fn pair_by_proximity(&mut self) -> usize {
+        let mut grid: BTreeMap<(i64, i64), Vec<&'a StopPoint>> = BTreeMap::new();
+        for sp in self.stop_points.values() {
+            if sp.stop_point_type == StopPointType::StopPosition && !sp.name.is_empty() {
+                grid.entry(grid_cell(&sp.coord)).or_default().push(sp);
+            }
+        }
+        let mut couples = vec![];
+        for platform in self.stop_points.values() {
+            if platform.stop_point_type != StopPointType::Platform || platform.name.is_empty() {
+                continue;
+            }
+            for cell in neighbour_cells(&platform.coord) {
+                for stop_position in grid.get(&cell).into_iter().flatten() {
+                    if stop_position.name != platform.name {
+                        continue;
+                    }
+                    let d = distance(&platform.coord, &stop_position.coord);
+                    if d <= MAX_PAIRING_DISTANCE {
+                        couples.push((d, platform.id.as_str(), stop_position.id.as_str()));
+                    }
+                }
+            }
+        }
+        self.pair_couples(couples)
+    }
+}
+
+/// Pairs each platform with the stop position of the same halt, and sets their
+/// `paired_stop_point_id`. A platform is paired with at most one stop position
+/// (and conversely), looking in order for:
+/// * a stop position that is next to the platform in the members of routes
+///   (the one found in most routes is kept),
+/// * the closest stop position of the same stop area,
+/// * the closest stop position with the same name within `MAX_PAIRING_DISTANCE`.
+pub fn pair_stop_points(stop_points: &mut [StopPoint], stop_areas: &[StopArea], routes: &[Route]) {
+    let pairs: Vec<(String, String)> = {
+        let mut pairing = Pairing {
+            stop_points: stop_points.iter().map(|sp| (sp.id.as_str(), sp)).collect(),
+            pairs: BTreeMap::new(),
+            paired: BTreeSet::new(),
+        };
+
+        let mut route_couples: Vec<((&str, &str), usize)> =
+            route_candidates(routes).into_iter().collect();
+        route_couples.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let by_route = route_couples
+            .into_iter()
+            .filter(|((platform_id, stop_position_id), _)| {
+                pairing.pair(platform_id, stop_position_id)
+            })
+            .count();
+
+        let by_stop_area: usize = stop_areas
+            .iter()
+            .map(|sa| {
+                let members: Vec<&str> = sa
+                    .stop_point_ids
+                    .iter()
+                    .chain(&sa.stop_position_ids)
+                    .map(|id| id.as_str())
+                    .collect();
+                pairing.pair_closest(&members)
+            })
+            .sum();
+
+        let by_proximity = pairing.pair_by_proximity();
+
+        info!(
+            "{} platforms paired with a stop position ({} from routes, {} from stop areas, {} by proximity)",
+            pairing.pairs.len(),
+            by_route,
+            by_stop_area,
+            by_proximity
+        );
+        pairing
+            .pairs
+            .into_iter()
+            .map(|(p, s)| (p.to_string(), s.to_string()))
+            .collect()
+    };
+
+    let index: BTreeMap<String, usize> = stop_points
+        .iter()
+        .enumerate()
+        .map(|(idx, sp)| (sp.id.clone(), idx))
+        .collect();
+    for (platform_id, stop_position_id) in pairs {
+        stop_points[index[&platform_id]].paired_stop_point_id = stop_position_id.clone();
+        stop_points[index[&stop_position_id]].paired_stop_point_id = platform_id;
+    }
+}
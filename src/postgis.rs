@@ -0,0 +1,420 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::metrics::{self, Metrics};
+use crate::tags::tags_to_json;
+use crate::wkt_format::{geometry_to_wkt, hull_to_wkt, shape_to_wkt, stop_point_to_wkt};
+use crate::OsmTcResponse;
+use geo_types::Point;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The tables, whose foreign keys are added once the rows are loaded.
+fn schema() -> String {
+    format!(
+        "
+CREATE EXTENSION IF NOT EXISTS postgis;
+
+CREATE TABLE stop_points (
+    stop_point_id text PRIMARY KEY,
+    name text NOT NULL,
+    stop_point_type text NOT NULL,
+    paired_stop_point_id text,
+    tags jsonb NOT NULL,
+    geom geometry(Geometry, 4326) NOT NULL
+);
+
+CREATE TABLE stop_areas (
+    stop_area_id text PRIMARY KEY,
+    name text NOT NULL,
+    tags jsonb NOT NULL,
+    geom geometry(Point, 4326) NOT NULL,
+    hull geometry(Geometry, 4326) NOT NULL
+);
+
+CREATE TABLE stop_area_stop_points (
+    stop_area_id text NOT NULL,
+    stop_point_id text NOT NULL
+);
+
+CREATE TABLE lines (
+    line_id text PRIMARY KEY,
+    name text NOT NULL,
+    code text NOT NULL,
+    colour text NOT NULL,
+    text_colour text NOT NULL,
+    operator text NOT NULL,
+    network text NOT NULL,
+    mode text NOT NULL,
+    sub_mode text,
+    physical_mode text NOT NULL,
+    commercial_mode text NOT NULL,
+    netex_mode text NOT NULL,
+    netex_sub_mode text,
+    gtfs_route_type smallint NOT NULL,
+    frequency text NOT NULL,
+    opening_hours text NOT NULL,
+    frequency_exceptions text NOT NULL,
+    {metrics_columns},
+    tags jsonb NOT NULL,
+    geom geometry(MultiLineString, 4326) NOT NULL
+);
+
+CREATE TABLE routes (
+    route_id text PRIMARY KEY,
+    name text NOT NULL,
+    code text NOT NULL,
+    destination text NOT NULL,
+    origin text NOT NULL,
+    colour text NOT NULL,
+    text_colour text NOT NULL,
+    operator text NOT NULL,
+    network text NOT NULL,
+    mode text NOT NULL,
+    sub_mode text,
+    physical_mode text NOT NULL,
+    commercial_mode text NOT NULL,
+    netex_mode text NOT NULL,
+    netex_sub_mode text,
+    gtfs_route_type smallint NOT NULL,
+    frequency text NOT NULL,
+    opening_hours text NOT NULL,
+    frequency_exceptions text NOT NULL,
+    travel_time text NOT NULL,
+    line_id text,
+    direction_id smallint,
+    variant text,
+    roundtrip boolean NOT NULL,
+    {metrics_columns},
+    tags jsonb NOT NULL,
+    geom geometry(MultiLineString, 4326) NOT NULL
+);
+
+CREATE TABLE route_points (
+    route_id text NOT NULL,
+    position integer NOT NULL,
+    role text NOT NULL,
+    stop_point_id text NOT NULL,
+    shape_dist_traveled double precision,
+    distance_to_shape double precision,
+    distance_from_previous double precision,
+    PRIMARY KEY (route_id, position)
+);
+
+CREATE TABLE line_routes (
+    line_id text NOT NULL,
+    route_id text NOT NULL
+);
+
+CREATE TABLE line_stop_areas (
+    line_id text NOT NULL,
+    stop_area_id text NOT NULL
+);
+",
+        metrics_columns = metrics_columns()
+    )
+}
+
+/// The metrics columns, in the order of `metrics::CSV_HEADER`.
+fn metrics_columns() -> String {
+    metrics::CSV_HEADER
+        .iter()
+        .map(|&name| match name {
+            "length" => "length double precision NOT NULL".to_string(),
+            "stop_count" => "stop_count integer NOT NULL".to_string(),
+            name => format!("{} double precision", name),
+        })
+        .collect::<Vec<String>>()
+        .join(",\n    ")
+}
+
+/// The members of stop areas and routes, and the lines and routes referenced by each
+/// other, may not be extracted (partial extract, filters) : these foreign keys are not
+/// checked on the loaded rows.
+const UNCHECKED_FOREIGN_KEYS: &str = "
+ALTER TABLE stop_area_stop_points ADD FOREIGN KEY (stop_area_id) REFERENCES stop_areas NOT VALID;
+ALTER TABLE stop_area_stop_points ADD FOREIGN KEY (stop_point_id) REFERENCES stop_points NOT VALID;
+ALTER TABLE route_points ADD FOREIGN KEY (route_id) REFERENCES routes NOT VALID;
+ALTER TABLE route_points ADD FOREIGN KEY (stop_point_id) REFERENCES stop_points NOT VALID;
+ALTER TABLE routes ADD FOREIGN KEY (line_id) REFERENCES lines NOT VALID;
+ALTER TABLE line_routes ADD FOREIGN KEY (line_id) REFERENCES lines NOT VALID;
+ALTER TABLE line_routes ADD FOREIGN KEY (route_id) REFERENCES routes NOT VALID;
+ALTER TABLE line_stop_areas ADD FOREIGN KEY (line_id) REFERENCES lines NOT VALID;
+ALTER TABLE line_stop_areas ADD FOREIGN KEY (stop_area_id) REFERENCES stop_areas NOT VALID;
+
+CREATE INDEX ON stop_points USING gist (geom);
+CREATE INDEX ON stop_areas USING gist (geom);
+CREATE INDEX ON stop_areas USING gist (hull);
+CREATE INDEX ON routes USING gist (geom);
+CREATE INDEX ON lines USING gist (geom);
+CREATE INDEX ON route_points (stop_point_id);
+";
+
+/// Value of a COPY text field.
+fn field(value: Option<&str>) -> String {
+    match value {
+        None => "\\N".to_string(),
+        Some(value) => value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r"),
+    }
+}
+
+fn text(value: &str) -> String {
+    field(Some(value))
+}
+
+fn prefixed(prefix: &str, id: &str) -> String {
+    if id.is_empty() {
+        field(None)
+    } else {
+        text(&format!("{}:{}", prefix, id))
+    }
+}
+
+fn number(value: Option<f64>) -> String {
+    value.map_or_else(|| field(None), |v| v.to_string())
+}
+
+fn ewkt(wkt: &str) -> String {
+    text(&format!("SRID=4326;{}", wkt))
+}
+
+fn tags(tags: &osmpbfreader::objects::Tags) -> String {
+    text(&tags_to_json(tags))
+}
+
+fn metrics(metrics: &Metrics) -> Vec<String> {
+    let bbox = metrics.bbox.as_ref();
+    vec![
+        metrics.length.to_string(),
+        metrics.stop_count.to_string(),
+        number(metrics.mean_stop_distance),
+        number(metrics.max_stop_distance),
+        number(bbox.map(|b| b.min_lat)),
+        number(bbox.map(|b| b.min_lon)),
+        number(bbox.map(|b| b.max_lat)),
+        number(bbox.map(|b| b.max_lon)),
+        number(metrics.origin_destination_distance),
+    ]
+}
+
+fn copy<W: Write, I: IntoIterator<Item = Vec<String>>>(wtr: &mut W, table: &str, rows: I) {
+    writeln!(wtr, "COPY {} FROM stdin;", table).unwrap();
+    for row in rows {
+        writeln!(wtr, "{}", row.join("\t")).unwrap();
+    }
+    writeln!(wtr, "\\.\n").unwrap();
+}
+
+/// Writes all the objects as a SQL script for PostgreSQL/PostGIS
+/// (`osm-transit-extractor.sql`) : the tables, their data loaded with COPY,
+/// and the foreign keys and spatial indexes. The OSM tags are stored in a jsonb column.
+pub fn write_postgis_sql<P: AsRef<Path>>(response: &OsmTcResponse, output_dir: P) {
+    let file = output_dir.as_ref().join("osm-transit-extractor.sql");
+    let mut wtr = BufWriter::new(File::create(file).unwrap());
+    writeln!(wtr, "BEGIN;").unwrap();
+    write!(wtr, "{}", schema()).unwrap();
+    writeln!(wtr).unwrap();
+
+    copy(
+        &mut wtr,
+        "stop_points",
+        response.stop_points.iter().map(|sp| {
+            vec![
+                text(&format!("StopPoint:{}", sp.id)),
+                text(&sp.name),
+                text(&format!("{:?}", sp.stop_point_type)),
+                prefixed("StopPoint", &sp.paired_stop_point_id),
+                tags(&sp.all_osm_tags),
+                ewkt(&stop_point_to_wkt(sp)),
+            ]
+        }),
+    );
+    copy(
+        &mut wtr,
+        "stop_areas",
+        response.stop_areas.iter().map(|sa| {
+            vec![
+                text(&format!("StopArea:{}", sa.id)),
+                text(&sa.name),
+                tags(&sa.all_osm_tags),
+                ewkt(&geometry_to_wkt(
+                    &Point::new(sa.coord.lon, sa.coord.lat).into(),
+                )),
+                ewkt(&hull_to_wkt(&sa.hull)),
+            ]
+        }),
+    );
+    copy(
+        &mut wtr,
+        "stop_area_stop_points",
+        response.stop_areas.iter().flat_map(|sa| {
+            sa.stop_point_ids.iter().map(move |sp_id| {
+                vec![
+                    text(&format!("StopArea:{}", sa.id)),
+                    text(&format!("StopPoint:{}", sp_id)),
+                ]
+            })
+        }),
+    );
+
+    if let Some(lines) = &response.lines {
+        copy(
+            &mut wtr,
+            "lines",
+            lines.iter().map(|l| {
+                let mut row = vec![
+                    text(&format!("Line:{}", l.id)),
+                    text(&l.name),
+                    text(&l.code),
+                    text(&l.colour),
+                    text(&l.text_colour),
+                    text(&l.operator),
+                    text(&l.network),
+                    text(&l.mode),
+                    l.transport_mode
+                        .sub_mode
+                        .map_or_else(|| field(None), |s| s.to_string()),
+                    text(l.transport_mode.physical_mode()),
+                    text(l.transport_mode.commercial_mode()),
+                    text(l.transport_mode.netex_mode()),
+                    l.transport_mode
+                        .netex_sub_mode()
+                        .map_or_else(|| field(None), text),
+                    l.transport_mode.gtfs_route_type().to_string(),
+                    text(&l.frequency),
+                    text(&l.opening_hours),
+                    text(&l.frequency_exceptions),
+                ];
+                row.extend(metrics(&l.metrics));
+                row.push(tags(&l.all_osm_tags));
+                row.push(ewkt(&shape_to_wkt(&l.shape)));
+                row
+            }),
+        );
+    }
+    if let Some(routes) = &response.routes {
+        copy(
+            &mut wtr,
+            "routes",
+            routes.iter().map(|r| {
+                let mut row = vec![
+                    text(&format!("Route:{}", r.id)),
+                    text(&r.name),
+                    text(&r.code),
+                    text(&r.destination),
+                    text(&r.origin),
+                    text(&r.colour),
+                    text(&r.text_colour),
+                    text(&r.operator),
+                    text(&r.network),
+                    text(&r.mode),
+                    r.transport_mode
+                        .sub_mode
+                        .map_or_else(|| field(None), |s| s.to_string()),
+                    text(r.transport_mode.physical_mode()),
+                    text(r.transport_mode.commercial_mode()),
+                    text(r.transport_mode.netex_mode()),
+                    r.transport_mode
+                        .netex_sub_mode()
+                        .map_or_else(|| field(None), text),
+                    r.transport_mode.gtfs_route_type().to_string(),
+                    text(&r.frequency),
+                    text(&r.opening_hours),
+                    text(&r.frequency_exceptions),
+                    text(&r.travel_time),
+                    prefixed("Line", &r.line_id),
+                    r.direction_id
+                        .map_or_else(|| field(None), |d| d.to_string()),
+                    r.variant.map_or_else(|| field(None), |v| v.to_string()),
+                    r.roundtrip.to_string(),
+                ];
+                row.extend(metrics(&r.metrics));
+                row.push(tags(&r.all_osm_tags));
+                row.push(ewkt(&shape_to_wkt(&r.shape)));
+                row
+            }),
+        );
+        copy(
+            &mut wtr,
+            "route_points",
+            routes.iter().flat_map(|r| {
+                r.ordered_route_points
+                    .iter()
+                    .zip(r.inter_stop_distances())
+                    .enumerate()
+                    .map(move |(position, (rp, distance_from_previous))| {
+                        vec![
+                            text(&format!("Route:{}", r.id)),
+                            position.to_string(),
+                            text(&rp.role),
+                            text(&format!("StopPoint:{}", rp.stop_point_id)),
+                            number(rp.shape_dist_traveled),
+                            number(rp.distance_to_shape),
+                            number(distance_from_previous),
+                        ]
+                    })
+            }),
+        );
+    }
+    if let Some(lines) = &response.lines {
+        copy(
+            &mut wtr,
+            "line_routes",
+            lines.iter().flat_map(|l| {
+                l.routes_id.iter().map(move |r| {
+                    vec![
+                        text(&format!("Line:{}", l.id)),
+                        text(&format!("Route:{}", r)),
+                    ]
+                })
+            }),
+        );
+        copy(
+            &mut wtr,
+            "line_stop_areas",
+            lines.iter().flat_map(|l| {
+                l.stop_area_ids.iter().map(move |sa| {
+                    vec![
+                        text(&format!("Line:{}", l.id)),
+                        text(&format!("StopArea:{}", sa)),
+                    ]
+                })
+            }),
+        );
+    }
+
+    write!(wtr, "{}", UNCHECKED_FOREIGN_KEYS).unwrap();
+    writeln!(wtr, "\nCOMMIT;").unwrap();
+}
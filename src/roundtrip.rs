@@ -0,0 +1,139 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::distance;
+use crate::pairing::MAX_PAIRING_DISTANCE;
+use crate::{Route, StopArea, StopPoint};
+use log::info;
+use std::collections::BTreeMap;
+
+/// If two stops are the same halt: the same stop, members of the same StopArea, or
+/// stops with the same name close to each other.
+fn same_place(
+    a: &str,
+    b: &str,
+    stop_points: &BTreeMap<&str, &StopPoint>,
+    stop_area_of: &BTreeMap<&str, &StopArea>,
+) -> bool {
+    if a == b {
+        return true;
+    }
+    if let (Some(area_a), Some(area_b)) = (stop_area_of.get(a), stop_area_of.get(b)) {
+        if area_a.id == area_b.id {
+            return true;
+        }
+    }
+    match (stop_points.get(a), stop_points.get(b)) {
+        (Some(a), Some(b)) => {
+            !a.name.is_empty()
+                && a.name == b.name
+                && distance(&a.coord, &b.coord) < MAX_PAIRING_DISTANCE
+        }
+        _ => false,
+    }
+}
+
+/// Index of the first stop of a Route named like its origin (its `from` tag), or like the
+/// StopArea of the stop.
+fn origin_index(
+    route: &Route,
+    stop_points: &BTreeMap<&str, &StopPoint>,
+    stop_area_of: &BTreeMap<&str, &StopArea>,
+) -> Option<usize> {
+    if route.origin.is_empty() {
+        return None;
+    }
+    route.ordered_route_points.iter().position(|rp| {
+        let id = rp.stop_point_id.as_str();
+        stop_points
+            .get(id)
+            .is_some_and(|sp| sp.name == route.origin)
+            || stop_area_of
+                .get(id)
+                .is_some_and(|sa| sa.name == route.origin)
+    })
+}
+
+/// Flags the circular Routes: Routes tagged `roundtrip=yes`, or not tagged `roundtrip=no`
+/// and ending at their first stop. The stop sequence of a circular Route starts at its
+/// origin (its `from` tag) when one of its stops is named like it, else at the first stop
+/// of its relation. It is rotated around the loop, so a Route ending at its first stop
+/// still ends at its new first stop, and a Route ending elsewhere is not closed.
+pub fn detect_roundtrips(routes: &mut [Route], stop_points: &[StopPoint], stop_areas: &[StopArea]) {
+    let stop_points: BTreeMap<&str, &StopPoint> =
+        stop_points.iter().map(|sp| (sp.id.as_str(), sp)).collect();
+    let mut stop_area_of: BTreeMap<&str, &StopArea> = BTreeMap::new();
+    for sa in stop_areas {
+        for sp_id in sa.stop_point_ids.iter().chain(&sa.stop_position_ids) {
+            stop_area_of.insert(sp_id, sa);
+        }
+    }
+    let mut open_routes = 0;
+    let mut rotated_routes = 0;
+    for route in routes.iter_mut() {
+        let points = &route.ordered_route_points;
+        let is_closed = points.len() > 2
+            && same_place(
+                &points[0].stop_point_id,
+                &points[points.len() - 1].stop_point_id,
+                &stop_points,
+                &stop_area_of,
+            );
+        route.roundtrip = match route.all_osm_tags.get("roundtrip").map(|v| v.as_str()) {
+            Some("yes") => true,
+            Some("no") => false,
+            _ => is_closed,
+        };
+        if route.roundtrip && !is_closed {
+            open_routes += 1;
+        }
+        if !route.roundtrip {
+            continue;
+        }
+        // the closing stop is added again after the rotation
+        if is_closed {
+            route.ordered_route_points.pop();
+        }
+        if let Some(start) = origin_index(route, &stop_points, &stop_area_of).filter(|i| *i > 0) {
+            route.ordered_route_points.rotate_left(start);
+            rotated_routes += 1;
+        }
+        if is_closed {
+            let first = route.ordered_route_points[0].clone();
+            route.ordered_route_points.push(first);
+        }
+    }
+    info!(
+        "{} circular routes ({} not ending at their first stop, {} starting at their origin)",
+        routes.iter().filter(|r| r.roundtrip).count(),
+        open_routes,
+        rotated_routes
+    );
+}
@@ -0,0 +1,311 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::geometry::{locate_routes_points, EARTH_RADIUS};
+use crate::metrics::BoundingBox;
+use crate::{Coord, OsmTcResponse, Shape};
+use geo_types::{Geometry, LineString, MultiPolygon, Polygon};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimplificationAlgorithm {
+    DouglasPeucker,
+    Visvalingam,
+}
+
+impl FromStr for SimplificationAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "douglas-peucker" => Ok(SimplificationAlgorithm::DouglasPeucker),
+            "visvalingam" => Ok(SimplificationAlgorithm::Visvalingam),
+            _ => Err(format!(
+                "unknown simplification algorithm {} (expected douglas-peucker or visvalingam)",
+                s
+            )),
+        }
+    }
+}
+
+/// Number of decimals above which the coordinates are kept as is: a f64 holds about
+/// 15 significant digits.
+pub const MAX_PRECISION: u32 = 15;
+
+/// How the geometries are written: `precision` is the number of decimals kept in the
+/// coordinates (up to `MAX_PRECISION`), and the shapes are simplified with `algorithm` when `tolerance`
+/// (in meters) is positive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometryOptions {
+    pub precision: Option<u32>,
+    pub algorithm: SimplificationAlgorithm,
+    pub tolerance: f64,
+}
+
+impl Default for GeometryOptions {
+    fn default() -> Self {
+        GeometryOptions {
+            precision: None,
+            algorithm: SimplificationAlgorithm::DouglasPeucker,
+            tolerance: 0.,
+        }
+    }
+}
+
+impl GeometryOptions {
+    pub fn is_default(&self) -> bool {
+        self.precision.is_none() && self.tolerance <= 0.
+    }
+}
+
+pub fn round_coord(coord: &Coord, precision: u32) -> Coord {
+    if precision >= MAX_PRECISION {
+        return coord.clone();
+    }
+    let factor = 10f64.powi(precision as i32);
+    Coord {
+        lat: (coord.lat * factor).round() / factor,
+        lon: (coord.lon * factor).round() / factor,
+    }
+}
+
+fn round_bbox(bbox: &mut Option<BoundingBox>, precision: u32) {
+    if let Some(bbox) = bbox.as_mut() {
+        let min = round_coord(
+            &Coord {
+                lat: bbox.min_lat,
+                lon: bbox.min_lon,
+            },
+            precision,
+        );
+        let max = round_coord(
+            &Coord {
+                lat: bbox.max_lat,
+                lon: bbox.max_lon,
+            },
+            precision,
+        );
+        *bbox = BoundingBox {
+            min_lat: min.lat,
+            min_lon: min.lon,
+            max_lat: max.lat,
+            max_lon: max.lon,
+        };
+    }
+}
+
+fn round_linestring(linestring: &LineString<f64>, precision: u32) -> LineString<f64> {
+    linestring
+        .0
+        .iter()
+        .map(|c| {
+            let rounded = round_coord(&Coord { lat: c.y, lon: c.x }, precision);
+            (rounded.lon, rounded.lat)
+        })
+        .collect()
+}
+
+fn round_polygon(polygon: &Polygon<f64>, precision: u32) -> Polygon<f64> {
+    Polygon::new(
+        round_linestring(polygon.exterior(), precision),
+        polygon
+            .interiors()
+            .iter()
+            .map(|ring| round_linestring(ring, precision))
+            .collect(),
+    )
+}
+
+pub fn round_geometry(geometry: &Geometry<f64>, precision: u32) -> Geometry<f64> {
+    match geometry {
+        Geometry::LineString(linestring) => round_linestring(linestring, precision).into(),
+        Geometry::Polygon(polygon) => round_polygon(polygon, precision).into(),
+        Geometry::MultiPolygon(multi_polygon) => MultiPolygon(
+            multi_polygon
+                .0
+                .iter()
+                .map(|polygon| round_polygon(polygon, precision))
+                .collect(),
+        )
+        .into(),
+        _ => geometry.clone(),
+    }
+}
+
+/// Local planar coordinates in meters (equirectangular projection around `origin`).
+fn to_meters(origin: &Coord, coord: &Coord) -> (f64, f64) {
+    let scale = origin.lat.to_radians().cos();
+    (
+        (coord.lon - origin.lon).to_radians() * EARTH_RADIUS * scale,
+        (coord.lat - origin.lat).to_radians() * EARTH_RADIUS,
+    )
+}
+
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let norm = dx * dx + dy * dy;
+    let t = if norm == 0. {
+        0.
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / norm).clamp(0., 1.)
+    };
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+fn triangle_area(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.
+}
+
+/// Douglas-Peucker simplification: the points closer than `tolerance` meters to the
+/// simplified line are removed.
+pub fn douglas_peucker(way: &[Coord], tolerance: f64) -> Vec<Coord> {
+    if way.len() < 3 {
+        return way.to_vec();
+    }
+    let points: Vec<(f64, f64)> = way.iter().map(|c| to_meters(&way[0], c)).collect();
+    let mut kept = vec![false; way.len()];
+    kept[0] = true;
+    kept[way.len() - 1] = true;
+    let mut ranges = vec![(0, way.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(points[i], points[first], points[last]),
+                )
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        if let Some((idx, d)) = farthest {
+            if d > tolerance {
+                kept[idx] = true;
+                ranges.push((first, idx));
+                ranges.push((idx, last));
+            }
+        }
+    }
+    way.iter()
+        .zip(kept)
+        .filter(|(_, kept)| *kept)
+        .map(|(c, _)| c.clone())
+        .collect()
+}
+
+/// Visvalingam-Whyatt simplification: the points forming with their neighbours a
+/// triangle smaller than `tolerance`² square meters are removed, smallest first.
+pub fn visvalingam(way: &[Coord], tolerance: f64) -> Vec<Coord> {
+    let points: Vec<(f64, f64)> = way.iter().map(|c| to_meters(&way[0], c)).collect();
+    let mut remaining: Vec<usize> = (0..way.len()).collect();
+    let min_area = tolerance * tolerance;
+    while remaining.len() > 2 {
+        let smallest = remaining
+            .windows(3)
+            .enumerate()
+            .map(|(i, w)| {
+                (
+                    i + 1,
+                    triangle_area(points[w[0]], points[w[1]], points[w[2]]),
+                )
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        match smallest {
+            Some((i, area)) if area < min_area => {
+                remaining.remove(i);
+            }
+            _ => break,
+        }
+    }
+    remaining.into_iter().map(|i| way[i].clone()).collect()
+}
+
+/// Simplifies and rounds the ways of a shape according to the options.
+pub fn simplify_shape<T: Shape>(container: &T, options: &GeometryOptions) -> Vec<Vec<Coord>> {
+    container
+        .get_shape()
+        .iter()
+        .map(|way| {
+            let way = if options.tolerance > 0. {
+                match options.algorithm {
+                    SimplificationAlgorithm::DouglasPeucker => {
+                        douglas_peucker(way, options.tolerance)
+                    }
+                    SimplificationAlgorithm::Visvalingam => visvalingam(way, options.tolerance),
+                }
+            } else {
+                way.clone()
+            };
+            match options.precision {
+                Some(precision) => {
+                    let mut rounded: Vec<Coord> =
+                        way.iter().map(|c| round_coord(c, precision)).collect();
+                    rounded.dedup();
+                    rounded
+                }
+                None => way,
+            }
+        })
+        .collect()
+}
+
+/// Simplifies the shapes and rounds the coordinates of all the objects, to be done
+/// once the objects are extracted and before writing them. The stops of the routes are
+/// located again on the simplified shapes, so that their `shape_dist_traveled` match them.
+pub fn simplify_osm_tcobjects(response: &mut OsmTcResponse, options: &GeometryOptions) {
+    if options.is_default() {
+        return;
+    }
+    if let Some(precision) = options.precision {
+        for sp in response.stop_points.iter_mut() {
+            sp.coord = round_coord(&sp.coord, precision);
+            sp.geometry = sp.geometry.as_ref().map(|g| round_geometry(g, precision));
+        }
+        for sa in response.stop_areas.iter_mut() {
+            sa.coord = round_coord(&sa.coord, precision);
+            for c in sa.hull.iter_mut() {
+                *c = round_coord(c, precision);
+            }
+        }
+    }
+    for route in response.routes.iter_mut().flatten() {
+        route.shape = simplify_shape(route, options);
+        if let Some(precision) = options.precision {
+            round_bbox(&mut route.metrics.bbox, precision);
+        }
+    }
+    if let Some(routes) = response.routes.as_mut() {
+        locate_routes_points(routes, &response.stop_points);
+    }
+    for line in response.lines.iter_mut().flatten() {
+        line.shape = simplify_shape(line, options);
+        if let Some(precision) = options.precision {
+            round_bbox(&mut line.metrics.bbox, precision);
+        }
+    }
+}
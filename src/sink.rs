@@ -0,0 +1,783 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::encoding::{self, GeometryEncoding};
+use crate::frequency::HeadwayWindow;
+use crate::tags::{TagColumns, TagsMode};
+use crate::{geojson, metrics, wkt_format};
+use crate::{Line, OsmTcResponse, Route, StopArea, StopPoint, StopPointType};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the names of the written files.
+pub const DEFAULT_PREFIX: &str = "osm-transit-extractor_";
+
+/// How the objects are written in the CSV files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// prefix of the file names (`DEFAULT_PREFIX` by default)
+    pub prefix: String,
+    pub tags_mode: TagsMode,
+    /// encoding of the shapes of the routes and lines
+    pub geometry_encoding: GeometryEncoding,
+    /// write the `hull` column of the stop areas (see `geometry::compute_stop_areas_hull`)
+    pub hulls: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            prefix: DEFAULT_PREFIX.to_string(),
+            tags_mode: TagsMode::default(),
+            geometry_encoding: GeometryEncoding::default(),
+            hulls: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// The options of the `all_tags` flag of the CSV writers.
+    pub fn from_all_tags(all_tags: bool) -> Self {
+        CsvOptions {
+            tags_mode: TagsMode::from_all_tags(all_tags),
+            ..Default::default()
+        }
+    }
+}
+
+/// An output receiving the objects one at a time, by reference.
+pub trait Sink {
+    fn stop_point(&mut self, stop_point: &StopPoint);
+    fn stop_area(&mut self, stop_area: &StopArea);
+    fn route(&mut self, route: &Route);
+    fn line(&mut self, line: &Line);
+    /// Called after the stops when the routes and lines are extracted, even when
+    /// there are none.
+    fn start_routes(&mut self) {}
+    /// Called once all the objects have been given.
+    fn finish(&mut self) {}
+}
+
+/// Gives all the objects to the sink, and finishes it.
+pub fn write_to_sink<S: Sink + ?Sized>(response: &OsmTcResponse, sink: &mut S) {
+    for sp in &response.stop_points {
+        sink.stop_point(sp);
+    }
+    for sa in &response.stop_areas {
+        sink.stop_area(sa);
+    }
+    if response.routes.is_some() || response.lines.is_some() {
+        sink.start_routes();
+    }
+    for r in response.routes.iter().flatten() {
+        sink.route(r);
+    }
+    for l in response.lines.iter().flatten() {
+        sink.line(l);
+    }
+    sink.finish();
+}
+
+/// Creates the `<prefix><name>.csv` file with its header.
+fn csv_writer<I>(output_dir: &Path, prefix: &str, name: &str, header: I) -> csv::Writer<File>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let file = output_dir.join(format!("{}{}.csv", prefix, name));
+    let mut wtr = csv::Writer::from_path(file).unwrap();
+    wtr.write_record(header).unwrap();
+    wtr
+}
+
+fn header(default_header: &[&str], tag_columns: &TagColumns) -> Vec<String> {
+    default_header
+        .iter()
+        .map(|&s| s.to_string())
+        .chain(tag_columns.header())
+        .collect()
+}
+
+pub(crate) struct StopPointsCsv {
+    wtr: csv::Writer<File>,
+    tag_columns: TagColumns,
+}
+
+impl StopPointsCsv {
+    pub(crate) fn new(output_dir: &Path, prefix: &str, tag_columns: TagColumns) -> Self {
+        let default_header = [
+            "stop_point_id",
+            "lat",
+            "lon",
+            "name",
+            "stop_point_type",
+            "geometry",
+        ];
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "stop_points",
+            header(&default_header, &tag_columns),
+        );
+        StopPointsCsv { wtr, tag_columns }
+    }
+
+    pub(crate) fn write(&mut self, sp: &StopPoint) {
+        let mut csv_row = vec![
+            format!("StopPoint:{}", sp.id),
+            sp.coord.lat.to_string(),
+            sp.coord.lon.to_string(),
+            sp.name.to_string(),
+            format!("{:?}", sp.stop_point_type),
+            wkt_format::stop_point_to_wkt(sp),
+        ];
+        csv_row.extend(self.tag_columns.values(&sp.all_osm_tags));
+        self.wtr.write_record(csv_row).unwrap();
+    }
+}
+
+pub(crate) struct StopPointPairsCsv {
+    wtr: csv::Writer<File>,
+}
+
+impl StopPointPairsCsv {
+    pub(crate) fn new(output_dir: &Path, prefix: &str) -> Self {
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "stop_point_pairs",
+            ["platform_id", "stop_position_id"],
+        );
+        StopPointPairsCsv { wtr }
+    }
+
+    pub(crate) fn write(&mut self, sp: &StopPoint) {
+        if sp.stop_point_type == StopPointType::Platform && !sp.paired_stop_point_id.is_empty() {
+            self.wtr
+                .write_record([
+                    format!("StopPoint:{}", sp.id),
+                    format!("StopPoint:{}", sp.paired_stop_point_id),
+                ])
+                .unwrap();
+        }
+    }
+}
+
+pub(crate) struct StopAreasCsv {
+    wtr: csv::Writer<File>,
+    tag_columns: TagColumns,
+    hulls: bool,
+}
+
+impl StopAreasCsv {
+    pub(crate) fn new(
+        output_dir: &Path,
+        prefix: &str,
+        tag_columns: TagColumns,
+        hulls: bool,
+    ) -> Self {
+        let default_header: &[&str] = if hulls {
+            &["stop_area_id", "lat", "lon", "name", "hull"]
+        } else {
+            &["stop_area_id", "lat", "lon", "name"]
+        };
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "stop_areas",
+            header(default_header, &tag_columns),
+        );
+        StopAreasCsv {
+            wtr,
+            tag_columns,
+            hulls,
+        }
+    }
+
+    pub(crate) fn write(&mut self, sa: &StopArea) {
+        let mut csv_row = vec![
+            format!("StopArea:{}", sa.id),
+            sa.coord.lat.to_string(),
+            sa.coord.lon.to_string(),
+            sa.name.to_string(),
+        ];
+        if self.hulls {
+            csv_row.push(wkt_format::hull_to_wkt(&sa.hull));
+        }
+        csv_row.extend(self.tag_columns.values(&sa.all_osm_tags));
+        self.wtr.write_record(csv_row).unwrap();
+    }
+}
+
+pub(crate) struct StopAreaMembersCsv {
+    wtr: csv::Writer<File>,
+}
+
+impl StopAreaMembersCsv {
+    pub(crate) fn new(output_dir: &Path, prefix: &str) -> Self {
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "stop_areas_stop_point",
+            ["stop_area_id", "stop_point_id"],
+        );
+        StopAreaMembersCsv { wtr }
+    }
+
+    pub(crate) fn write(&mut self, sa: &StopArea) {
+        for sp_id in &sa.stop_point_ids {
+            self.wtr
+                .write_record([
+                    format!("StopArea:{}", sa.id),
+                    format!("StopPoint:{}", sp_id),
+                ])
+                .unwrap();
+        }
+    }
+}
+
+/// The routes and their stops.
+pub(crate) struct RoutesCsv {
+    wtr: csv::Writer<File>,
+    route_points_wtr: csv::Writer<File>,
+    tag_columns: TagColumns,
+    geometry_encoding: GeometryEncoding,
+}
+
+impl RoutesCsv {
+    pub(crate) fn new(
+        output_dir: &Path,
+        prefix: &str,
+        tag_columns: TagColumns,
+        geometry_encoding: GeometryEncoding,
+    ) -> Self {
+        let route_points_wtr = csv_writer(
+            output_dir,
+            prefix,
+            "route_points",
+            [
+                "route_id",
+                "role",
+                "stop_id",
+                "shape_dist_traveled",
+                "distance_to_shape",
+                "distance_from_previous",
+            ],
+        );
+        let default_header: Vec<&str> = [
+            "route_id",
+            "name",
+            "code",
+            "destination",
+            "origin",
+            "colour",
+            "text_colour",
+            "operator",
+            "network",
+            "mode",
+            "sub_mode",
+            "physical_mode",
+            "commercial_mode",
+            "netex_mode",
+            "netex_sub_mode",
+            "gtfs_route_type",
+            "frequency",
+            "opening_hours",
+            "frequency_exceptions",
+            "travel_time",
+            "line_id",
+            "direction_id",
+            "variant",
+            "roundtrip",
+            "shape",
+        ]
+        .iter()
+        .chain(metrics::CSV_HEADER.iter())
+        .cloned()
+        .collect();
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "routes",
+            header(&default_header, &tag_columns),
+        );
+        RoutesCsv {
+            wtr,
+            route_points_wtr,
+            tag_columns,
+            geometry_encoding,
+        }
+    }
+
+    pub(crate) fn write(&mut self, r: &Route) {
+        let mut csv_row = vec![
+            format!("Route:{}", r.id),
+            r.name.to_string(),
+            r.code.to_string(),
+            r.destination.to_string(),
+            r.origin.to_string(),
+            r.colour.to_string(),
+            r.text_colour.to_string(),
+            r.operator.to_string(),
+            r.network.to_string(),
+            r.mode.to_string(),
+            r.transport_mode
+                .sub_mode
+                .map_or(String::new(), |s| s.to_string()),
+            r.transport_mode.physical_mode().to_string(),
+            r.transport_mode.commercial_mode().to_string(),
+            r.transport_mode.netex_mode().to_string(),
+            r.transport_mode.netex_sub_mode().unwrap_or("").to_string(),
+            r.transport_mode.gtfs_route_type().to_string(),
+            r.frequency.to_string(),
+            r.opening_hours.to_string(),
+            r.frequency_exceptions.to_string(),
+            r.travel_time.to_string(),
+            if r.line_id.is_empty() {
+                String::new()
+            } else {
+                format!("Line:{}", r.line_id)
+            },
+            r.direction_id.map_or(String::new(), |d| d.to_string()),
+            r.variant.map_or(String::new(), |v| v.to_string()),
+            r.roundtrip.to_string(),
+        ];
+        csv_row.push(encoding::encode_shape(&r.shape, self.geometry_encoding));
+        csv_row.extend(metrics::to_csv_columns(&r.metrics));
+        csv_row.extend(self.tag_columns.values(&r.all_osm_tags));
+        self.wtr.write_record(csv_row).unwrap();
+
+        for (rp, distance_from_previous) in
+            r.ordered_route_points.iter().zip(r.inter_stop_distances())
+        {
+            let row = vec![
+                format!("Route:{}", r.id),
+                rp.role.to_string(),
+                format!("StopPoint:{}", rp.stop_point_id),
+                rp.shape_dist_traveled
+                    .map_or(String::new(), |d| format!("{:.1}", d)),
+                rp.distance_to_shape
+                    .map_or(String::new(), |d| format!("{:.1}", d)),
+                distance_from_previous.map_or(String::new(), |d| format!("{:.1}", d)),
+            ];
+            self.route_points_wtr.write_record(row).unwrap();
+        }
+    }
+}
+
+/// The lines, their routes and their stop areas.
+pub(crate) struct LinesCsv {
+    wtr: csv::Writer<File>,
+    line_routes_wtr: csv::Writer<File>,
+    line_stop_areas_wtr: csv::Writer<File>,
+    tag_columns: TagColumns,
+    geometry_encoding: GeometryEncoding,
+}
+
+impl LinesCsv {
+    pub(crate) fn new(
+        output_dir: &Path,
+        prefix: &str,
+        tag_columns: TagColumns,
+        geometry_encoding: GeometryEncoding,
+    ) -> Self {
+        let default_header: Vec<&str> = [
+            "line_id",
+            "name",
+            "code",
+            "colour",
+            "text_colour",
+            "operator",
+            "network",
+            "mode",
+            "sub_mode",
+            "physical_mode",
+            "commercial_mode",
+            "netex_mode",
+            "netex_sub_mode",
+            "gtfs_route_type",
+            "frequency",
+            "opening_hours",
+            "frequency_exceptions",
+            "shape",
+        ]
+        .iter()
+        .chain(metrics::CSV_HEADER.iter())
+        .cloned()
+        .collect();
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "lines",
+            header(&default_header, &tag_columns),
+        );
+        let line_routes_wtr =
+            csv_writer(output_dir, prefix, "line_routes", ["line_id", "route_id"]);
+        let line_stop_areas_wtr = csv_writer(
+            output_dir,
+            prefix,
+            "line_stop_areas",
+            ["line_id", "stop_area_id"],
+        );
+        LinesCsv {
+            wtr,
+            line_routes_wtr,
+            line_stop_areas_wtr,
+            tag_columns,
+            geometry_encoding,
+        }
+    }
+
+    pub(crate) fn write(&mut self, l: &Line) {
+        let mut csv_row = vec![
+            format!("Line:{}", l.id),
+            l.name.to_string(),
+            l.code.to_string(),
+            l.colour.to_string(),
+            l.text_colour.to_string(),
+            l.operator.to_string(),
+            l.network.to_string(),
+            l.mode.to_string(),
+            l.transport_mode
+                .sub_mode
+                .map_or(String::new(), |s| s.to_string()),
+            l.transport_mode.physical_mode().to_string(),
+            l.transport_mode.commercial_mode().to_string(),
+            l.transport_mode.netex_mode().to_string(),
+            l.transport_mode.netex_sub_mode().unwrap_or("").to_string(),
+            l.transport_mode.gtfs_route_type().to_string(),
+            l.frequency.to_string(),
+            l.opening_hours.to_string(),
+            l.frequency_exceptions.to_string(),
+        ];
+        csv_row.push(encoding::encode_shape(&l.shape, self.geometry_encoding));
+        csv_row.extend(metrics::to_csv_columns(&l.metrics));
+        csv_row.extend(self.tag_columns.values(&l.all_osm_tags));
+        self.wtr.write_record(csv_row).unwrap();
+
+        for r in &l.routes_id {
+            self.line_routes_wtr
+                .write_record([format!("Line:{}", l.id), format!("Route:{}", r)])
+                .unwrap();
+        }
+        for sa in &l.stop_area_ids {
+            self.line_stop_areas_wtr
+                .write_record([format!("Line:{}", l.id), format!("StopArea:{}", sa)])
+                .unwrap();
+        }
+    }
+}
+
+pub(crate) struct FrequenciesCsv {
+    wtr: csv::Writer<File>,
+}
+
+/// `HH:MM:SS`, after `24:00:00` for a time after midnight.
+fn format_time(minutes: u16) -> String {
+    format!("{:02}:{:02}:00", minutes / 60, minutes % 60)
+}
+
+impl FrequenciesCsv {
+    pub(crate) fn new(output_dir: &Path, prefix: &str) -> Self {
+        let wtr = csv_writer(
+            output_dir,
+            prefix,
+            "frequencies",
+            [
+                "object_id",
+                "days",
+                "start_time",
+                "end_time",
+                "headway_secs",
+                "conditional",
+            ],
+        );
+        FrequenciesCsv { wtr }
+    }
+
+    pub(crate) fn write(&mut self, object_id: &str, headways: &[HeadwayWindow]) {
+        for h in headways {
+            self.wtr
+                .write_record([
+                    object_id.to_string(),
+                    h.days(),
+                    format_time(h.time_range.start),
+                    format_time(h.time_range.end),
+                    h.headway_secs.to_string(),
+                    h.conditional.to_string(),
+                ])
+                .unwrap();
+        }
+    }
+}
+
+/// Writes the CSV files of the `extract` subcommand. The files of the stops are created
+/// with the sink, the ones of the routes and lines by `start_routes` or with their
+/// first object.
+///
+/// With `TagsMode::Columns`, the columns depend on the tags of all the objects: the
+/// objects are kept until `finish` and written then.
+pub struct CsvSink {
+    output_dir: PathBuf,
+    options: CsvOptions,
+    stop_points: Option<StopPointsCsv>,
+    stop_point_pairs: StopPointPairsCsv,
+    stop_areas: Option<StopAreasCsv>,
+    stop_area_members: StopAreaMembersCsv,
+    routes: Option<RoutesCsv>,
+    lines: Option<LinesCsv>,
+    frequencies: Option<FrequenciesCsv>,
+    pending: OsmTcResponse,
+}
+
+impl CsvSink {
+    pub fn new<P: AsRef<Path>>(output_dir: P, options: CsvOptions) -> Self {
+        let output_dir = output_dir.as_ref();
+        let streamed = options.tags_mode != TagsMode::Columns;
+        let prefix = options.prefix.as_str();
+        let tag_columns = || TagColumns::new(&options.tags_mode, std::iter::empty());
+        CsvSink {
+            stop_points: streamed.then(|| StopPointsCsv::new(output_dir, prefix, tag_columns())),
+            stop_point_pairs: StopPointPairsCsv::new(output_dir, prefix),
+            stop_areas: streamed
+                .then(|| StopAreasCsv::new(output_dir, prefix, tag_columns(), options.hulls)),
+            stop_area_members: StopAreaMembersCsv::new(output_dir, prefix),
+            routes: None,
+            lines: None,
+            frequencies: None,
+            pending: OsmTcResponse {
+                stop_points: vec![],
+                stop_areas: vec![],
+                routes: None,
+                lines: None,
+            },
+            output_dir: output_dir.to_path_buf(),
+            options,
+        }
+    }
+
+    fn is_streamed(&self) -> bool {
+        self.options.tags_mode != TagsMode::Columns
+    }
+
+    fn tag_columns(&self) -> TagColumns {
+        TagColumns::new(&self.options.tags_mode, std::iter::empty())
+    }
+
+    fn frequencies(&mut self) -> &mut FrequenciesCsv {
+        let (output_dir, prefix) = (&self.output_dir, &self.options.prefix);
+        self.frequencies
+            .get_or_insert_with(|| FrequenciesCsv::new(output_dir, prefix))
+    }
+
+    fn routes(&mut self) -> &mut RoutesCsv {
+        let tag_columns = self.tag_columns();
+        let (output_dir, options) = (&self.output_dir, &self.options);
+        self.routes.get_or_insert_with(|| {
+            RoutesCsv::new(
+                output_dir,
+                &options.prefix,
+                tag_columns,
+                options.geometry_encoding,
+            )
+        })
+    }
+
+    fn lines(&mut self) -> &mut LinesCsv {
+        let tag_columns = self.tag_columns();
+        let (output_dir, options) = (&self.output_dir, &self.options);
+        self.lines.get_or_insert_with(|| {
+            LinesCsv::new(
+                output_dir,
+                &options.prefix,
+                tag_columns,
+                options.geometry_encoding,
+            )
+        })
+    }
+}
+
+impl Sink for CsvSink {
+    fn stop_point(&mut self, stop_point: &StopPoint) {
+        self.stop_point_pairs.write(stop_point);
+        match &mut self.stop_points {
+            Some(wtr) => wtr.write(stop_point),
+            None => self.pending.stop_points.push(stop_point.clone()),
+        }
+    }
+
+    fn stop_area(&mut self, stop_area: &StopArea) {
+        self.stop_area_members.write(stop_area);
+        match &mut self.stop_areas {
+            Some(wtr) => wtr.write(stop_area),
+            None => self.pending.stop_areas.push(stop_area.clone()),
+        }
+    }
+
+    fn route(&mut self, route: &Route) {
+        self.frequencies()
+            .write(&format!("Route:{}", route.id), &route.headways);
+        if self.is_streamed() {
+            self.routes().write(route);
+        } else {
+            self.pending
+                .routes
+                .get_or_insert_with(Vec::new)
+                .push(route.clone());
+        }
+    }
+
+    fn line(&mut self, line: &Line) {
+        self.frequencies()
+            .write(&format!("Line:{}", line.id), &line.headways);
+        if self.is_streamed() {
+            self.lines().write(line);
+        } else {
+            self.pending
+                .lines
+                .get_or_insert_with(Vec::new)
+                .push(line.clone());
+        }
+    }
+
+    /// Creates the files of the routes and lines, written with their header only
+    /// when there are none.
+    fn start_routes(&mut self) {
+        self.frequencies();
+        if self.is_streamed() {
+            self.routes();
+            self.lines();
+        } else {
+            self.pending.routes.get_or_insert_with(Vec::new);
+            self.pending.lines.get_or_insert_with(Vec::new);
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.is_streamed() {
+            return;
+        }
+        let output_dir = &self.output_dir;
+        let pending = &self.pending;
+        crate::write_stop_points_to_csv_with_options(
+            &pending.stop_points,
+            output_dir,
+            &self.options,
+        );
+        crate::write_stop_areas_to_csv_with_options(&pending.stop_areas, output_dir, &self.options);
+        if let Some(routes) = &pending.routes {
+            crate::write_routes_to_csv_with_options(routes, output_dir, &self.options);
+        }
+        if let Some(lines) = &pending.lines {
+            crate::write_lines_to_csv_with_options(lines, output_dir, &self.options);
+        }
+    }
+}
+
+/// Writes the GeoJSON files of the `geojson` subcommand, one feature at a time. The files
+/// of the stops are created with the sink, the ones of the routes and lines by
+/// `start_routes` or with their first object. The feature collections are closed by `finish`.
+pub struct GeoJsonSink {
+    output_dir: PathBuf,
+    tags_mode: TagsMode,
+    stop_points: geojson::FeatureCollectionWriter,
+    stop_areas: geojson::FeatureCollectionWriter,
+    routes: Option<geojson::FeatureCollectionWriter>,
+    lines: Option<geojson::FeatureCollectionWriter>,
+}
+
+impl GeoJsonSink {
+    pub fn new<P: AsRef<Path>>(output_dir: P, tags_mode: TagsMode) -> Self {
+        let output_dir = output_dir.as_ref();
+        GeoJsonSink {
+            stop_points: geojson::FeatureCollectionWriter::new(
+                &output_dir.join(geojson::STOP_POINTS_FILE),
+            ),
+            stop_areas: geojson::FeatureCollectionWriter::new(
+                &output_dir.join(geojson::STOP_AREAS_FILE),
+            ),
+            routes: None,
+            lines: None,
+            output_dir: output_dir.to_path_buf(),
+            tags_mode,
+        }
+    }
+}
+
+impl GeoJsonSink {
+    fn routes(&mut self) -> &mut geojson::FeatureCollectionWriter {
+        let output_dir = &self.output_dir;
+        self.routes.get_or_insert_with(|| {
+            geojson::FeatureCollectionWriter::new(&output_dir.join(geojson::ROUTES_FILE))
+        })
+    }
+
+    fn lines(&mut self) -> &mut geojson::FeatureCollectionWriter {
+        let output_dir = &self.output_dir;
+        self.lines.get_or_insert_with(|| {
+            geojson::FeatureCollectionWriter::new(&output_dir.join(geojson::LINES_FILE))
+        })
+    }
+}
+
+impl Sink for GeoJsonSink {
+    fn stop_point(&mut self, stop_point: &StopPoint) {
+        self.stop_points
+            .write(&geojson::stop_point_feature(stop_point, &self.tags_mode));
+    }
+
+    fn stop_area(&mut self, stop_area: &StopArea) {
+        self.stop_areas
+            .write(&geojson::stop_area_feature(stop_area, &self.tags_mode));
+    }
+
+    fn route(&mut self, route: &Route) {
+        let feature = geojson::route_feature(route, &self.tags_mode);
+        self.routes().write(&feature);
+    }
+
+    fn line(&mut self, line: &Line) {
+        let feature = geojson::line_feature(line, &self.tags_mode);
+        self.lines().write(&feature);
+    }
+
+    fn start_routes(&mut self) {
+        self.routes();
+        self.lines();
+    }
+
+    fn finish(&mut self) {
+        self.stop_points.finish();
+        self.stop_areas.finish();
+        for wtr in self.routes.iter_mut().chain(self.lines.iter_mut()) {
+            wtr.finish();
+        }
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::validation::is_null_island;
+use crate::OsmTcResponse;
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PhaseTimings(pub Vec<PhaseTiming>);
+
+impl PhaseTimings {
+    pub fn time<T, F: FnOnce() -> T>(&mut self, phase: &str, f: F) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.0.push(PhaseTiming {
+            phase: phase.to_string(),
+            seconds: start.elapsed().as_secs_f64(),
+        });
+        result
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    pub stop_points: usize,
+    pub stop_areas: usize,
+    pub routes: usize,
+    pub lines: usize,
+    pub stop_points_by_type: BTreeMap<String, usize>,
+    pub routes_by_mode: BTreeMap<String, usize>,
+    pub routes_by_network: BTreeMap<String, usize>,
+    pub routes_by_operator: BTreeMap<String, usize>,
+    pub routes_without_shape: usize,
+    pub routes_without_stops: usize,
+    pub unresolved_route_points: usize,
+    pub unresolved_line_routes: usize,
+    pub stops_at_null_island: usize,
+    pub timings: PhaseTimings,
+}
+
+fn count_by<'a, I: Iterator<Item = &'a str>>(values: I) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for value in values {
+        let key = if value.is_empty() { "unknown" } else { value };
+        *counts.entry(key.to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn compute_stats(response: &OsmTcResponse, timings: PhaseTimings) -> Stats {
+    let routes = response.routes.as_deref().unwrap_or(&[]);
+    let lines = response.lines.as_deref().unwrap_or(&[]);
+    let stop_point_ids: BTreeSet<&str> = response
+        .stop_points
+        .iter()
+        .map(|sp| sp.id.as_str())
+        .collect();
+    let route_ids: BTreeSet<&str> = routes.iter().map(|r| r.id.as_str()).collect();
+
+    let mut stop_points_by_type = BTreeMap::new();
+    for sp in &response.stop_points {
+        *stop_points_by_type
+            .entry(format!("{:?}", sp.stop_point_type))
+            .or_insert(0) += 1;
+    }
+
+    Stats {
+        stop_points: response.stop_points.len(),
+        stop_areas: response.stop_areas.len(),
+        routes: routes.len(),
+        lines: lines.len(),
+        stop_points_by_type,
+        routes_by_mode: count_by(routes.iter().map(|r| r.mode.as_str())),
+        routes_by_network: count_by(routes.iter().map(|r| r.network.as_str())),
+        routes_by_operator: count_by(routes.iter().map(|r| r.operator.as_str())),
+        routes_without_shape: routes.iter().filter(|r| r.shape.is_empty()).count(),
+        routes_without_stops: routes
+            .iter()
+            .filter(|r| r.ordered_route_points.is_empty())
+            .count(),
+        unresolved_route_points: routes
+            .iter()
+            .flat_map(|r| &r.ordered_route_points)
+            .filter(|rp| !stop_point_ids.contains(rp.stop_point_id.as_str()))
+            .count(),
+        unresolved_line_routes: lines
+            .iter()
+            .flat_map(|l| {
+                l.routes_id
+                    .iter()
+                    .filter(|r| !route_ids.contains(r.as_str()))
+                    .chain(&l.dangling_routes_id)
+            })
+            .count(),
+        stops_at_null_island: response
+            .stop_points
+            .iter()
+            .filter(|sp| is_null_island(&sp.coord))
+            .count(),
+        timings,
+    }
+}
+
+fn fmt_counts(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    counts: &BTreeMap<String, usize>,
+) -> fmt::Result {
+    writeln!(f, "{}", title)?;
+    for (key, count) in counts {
+        writeln!(f, "  {:<38}{:>10}", key, count)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let totals = [
+            ("stop points", self.stop_points),
+            ("stop areas", self.stop_areas),
+            ("routes", self.routes),
+            ("lines", self.lines),
+            ("routes without shape", self.routes_without_shape),
+            ("routes without stops", self.routes_without_stops),
+            ("unresolved route points", self.unresolved_route_points),
+            ("unresolved line routes", self.unresolved_line_routes),
+            ("stop points at 0,0", self.stops_at_null_island),
+        ];
+        for (title, count) in totals.iter() {
+            writeln!(f, "{:<40}{:>10}", title, count)?;
+        }
+        fmt_counts(f, "stop points by type", &self.stop_points_by_type)?;
+        fmt_counts(f, "routes by mode", &self.routes_by_mode)?;
+        fmt_counts(f, "routes by network", &self.routes_by_network)?;
+        fmt_counts(f, "routes by operator", &self.routes_by_operator)?;
+        writeln!(f, "runtime by phase (s)")?;
+        for timing in &self.timings.0 {
+            writeln!(f, "  {:<38}{:>10.3}", timing.phase, timing.seconds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the report in `<prefix>stats.json`.
+pub fn write_stats_to_json<P: AsRef<Path>>(stats: &Stats, output_dir: P, prefix: &str) {
+    let output_dir = output_dir.as_ref();
+    let json_file = output_dir.join(format!("{}stats.json", prefix));
+    let wtr = std::io::BufWriter::new(std::fs::File::create(json_file).unwrap());
+    serde_json::to_writer_pretty(wtr, stats).unwrap();
+}
@@ -0,0 +1,120 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use osmpbfreader::objects::Tags;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// How the OSM tags of the objects are written in the CSV files.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TagsMode {
+    /// no tags
+    #[default]
+    None,
+    /// one `osm:<key>` column per key found on the objects of the file
+    Columns,
+    /// all the tags as a JSON object in a single `osm_tags` column
+    Json,
+    /// one `osm:<key>` column per given key
+    Whitelist(Vec<String>),
+}
+
+impl TagsMode {
+    /// The mode of the `all_tags` flag of the CSV writers.
+    pub fn from_all_tags(all_tags: bool) -> Self {
+        if all_tags {
+            TagsMode::Columns
+        } else {
+            TagsMode::None
+        }
+    }
+}
+
+/// The OSM tags columns of a CSV file.
+pub(crate) struct TagColumns {
+    json: bool,
+    keys: Vec<String>,
+}
+
+impl TagColumns {
+    /// The columns of the objects with the given tags (only used to list the keys
+    /// with `TagsMode::Columns`).
+    pub(crate) fn new<'t, I: Iterator<Item = &'t Tags>>(mode: &TagsMode, tags: I) -> Self {
+        let keys = match mode {
+            TagsMode::Columns => tags
+                .flat_map(|t| t.keys().map(|k| k.to_string()))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            TagsMode::Whitelist(keys) => keys.clone(),
+            TagsMode::None | TagsMode::Json => vec![],
+        };
+        TagColumns {
+            json: *mode == TagsMode::Json,
+            keys,
+        }
+    }
+
+    pub(crate) fn header(&self) -> Vec<String> {
+        if self.json {
+            vec!["osm_tags".to_string()]
+        } else {
+            self.keys.iter().map(|k| format!("osm:{}", k)).collect()
+        }
+    }
+
+    pub(crate) fn values(&self, tags: &Tags) -> Vec<String> {
+        self.optional_values(tags)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    /// The values of the columns, `None` for the missing keys.
+    pub(crate) fn optional_values(&self, tags: &Tags) -> Vec<Option<String>> {
+        if self.json {
+            vec![Some(tags_to_json(tags))]
+        } else {
+            self.keys
+                .iter()
+                .map(|k| tags.get(k.as_str()).map(|s| s.to_string()))
+                .collect()
+        }
+    }
+}
+
+/// The tags as a JSON object, keys sorted.
+pub fn tags_to_json(tags: &Tags) -> String {
+    let object: Map<String, Value> = tags
+        .iter()
+        .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+        .collect();
+    Value::Object(object).to_string()
+}
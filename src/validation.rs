@@ -0,0 +1,247 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::colour::invalid_colour_tags;
+use crate::consolidation::find_line_conflicts;
+use crate::frequency::parse_headway_windows;
+use crate::geometry::MAX_STOP_DISTANCE_TO_SHAPE;
+use crate::opening_hours::OpeningHours;
+use crate::{Coord, OsmTcResponse};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    StopAtNullIsland,
+    RouteWithoutStops,
+    RouteWithoutShape,
+    UnknownStopPoint,
+    UnknownRoute,
+    LineRouteConflict,
+    StopFarFromShape,
+    StopBeforePreviousStop,
+    InvalidColour,
+    InvalidOpeningHours,
+    InvalidFrequency,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub object_id: String,
+    pub message: String,
+}
+
+pub(crate) fn is_null_island(coord: &Coord) -> bool {
+    coord.lat == 0. && coord.lon == 0.
+}
+
+fn opening_hours_error(opening_hours: &str) -> Option<String> {
+    if opening_hours.is_empty() {
+        return None;
+    }
+    opening_hours.parse::<OpeningHours>().err().map(|e| {
+        format!(
+            "opening_hours {} is not a valid calendar : {}",
+            opening_hours, e
+        )
+    })
+}
+
+fn frequency_error(frequency: &str, frequency_exceptions: &str) -> Option<String> {
+    parse_headway_windows(frequency, frequency_exceptions)
+        .err()
+        .map(|e| format!("interval or interval:conditional is not valid : {}", e))
+}
+
+pub fn validate(response: &OsmTcResponse) -> Vec<Issue> {
+    let mut issues = vec![];
+    for sp in &response.stop_points {
+        if is_null_island(&sp.coord) {
+            issues.push(Issue {
+                kind: IssueKind::StopAtNullIsland,
+                object_id: format!("StopPoint:{}", sp.id),
+                message: "stop point has no valid coordinates".to_string(),
+            });
+        }
+    }
+    for sa in &response.stop_areas {
+        if is_null_island(&sa.coord) {
+            issues.push(Issue {
+                kind: IssueKind::StopAtNullIsland,
+                object_id: format!("StopArea:{}", sa.id),
+                message: "stop area has no valid coordinates".to_string(),
+            });
+        }
+    }
+
+    let stop_point_ids: BTreeSet<&str> = response
+        .stop_points
+        .iter()
+        .map(|sp| sp.id.as_str())
+        .collect();
+    let routes = response.routes.as_deref().unwrap_or(&[]);
+    for r in routes {
+        if r.ordered_route_points.is_empty() {
+            issues.push(Issue {
+                kind: IssueKind::RouteWithoutStops,
+                object_id: format!("Route:{}", r.id),
+                message: "route has no stop".to_string(),
+            });
+        }
+        for (key, value) in invalid_colour_tags(&r.all_osm_tags) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidColour,
+                object_id: format!("Route:{}", r.id),
+                message: format!("{} {} is not a valid colour", key, value),
+            });
+        }
+        if let Some(message) = opening_hours_error(&r.opening_hours) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidOpeningHours,
+                object_id: format!("Route:{}", r.id),
+                message,
+            });
+        }
+        if let Some(message) = frequency_error(&r.frequency, &r.frequency_exceptions) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidFrequency,
+                object_id: format!("Route:{}", r.id),
+                message,
+            });
+        }
+        if r.shape.is_empty() {
+            issues.push(Issue {
+                kind: IssueKind::RouteWithoutShape,
+                object_id: format!("Route:{}", r.id),
+                message: "route has no shape".to_string(),
+            });
+        }
+        let mut previous_distance = 0.;
+        for rp in &r.ordered_route_points {
+            if let Some(distance_to_shape) = rp.distance_to_shape {
+                if distance_to_shape > MAX_STOP_DISTANCE_TO_SHAPE {
+                    issues.push(Issue {
+                        kind: IssueKind::StopFarFromShape,
+                        object_id: format!("Route:{}", r.id),
+                        message: format!(
+                            "stop {} is {:.0}m away from the route shape",
+                            rp.stop_point_id, distance_to_shape
+                        ),
+                    });
+                }
+            }
+            if let Some(shape_dist_traveled) = rp.shape_dist_traveled {
+                if shape_dist_traveled < previous_distance {
+                    issues.push(Issue {
+                        kind: IssueKind::StopBeforePreviousStop,
+                        object_id: format!("Route:{}", r.id),
+                        message: format!(
+                            "stop {} is located before the previous stop on the route shape",
+                            rp.stop_point_id
+                        ),
+                    });
+                }
+                previous_distance = shape_dist_traveled;
+            }
+            if !stop_point_ids.contains(rp.stop_point_id.as_str()) {
+                issues.push(Issue {
+                    kind: IssueKind::UnknownStopPoint,
+                    object_id: format!("Route:{}", r.id),
+                    message: format!("member {} is not an extracted stop", rp.stop_point_id),
+                });
+            }
+        }
+    }
+
+    let route_ids: BTreeSet<&str> = routes.iter().map(|r| r.id.as_str()).collect();
+    for l in response.lines.as_deref().unwrap_or(&[]) {
+        for (key, value) in invalid_colour_tags(&l.all_osm_tags) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidColour,
+                object_id: format!("Line:{}", l.id),
+                message: format!("{} {} is not a valid colour", key, value),
+            });
+        }
+        if let Some(message) = opening_hours_error(&l.opening_hours) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidOpeningHours,
+                object_id: format!("Line:{}", l.id),
+                message,
+            });
+        }
+        if let Some(message) = frequency_error(&l.frequency, &l.frequency_exceptions) {
+            issues.push(Issue {
+                kind: IssueKind::InvalidFrequency,
+                object_id: format!("Line:{}", l.id),
+                message,
+            });
+        }
+        let dangling_routes_id = l
+            .routes_id
+            .iter()
+            .filter(|id| !route_ids.contains(id.as_str()))
+            .chain(&l.dangling_routes_id);
+        for route_id in dangling_routes_id {
+            issues.push(Issue {
+                kind: IssueKind::UnknownRoute,
+                object_id: format!("Line:{}", l.id),
+                message: format!("member {} is not an extracted route", route_id),
+            });
+        }
+    }
+    for conflict in find_line_conflicts(response.lines.as_deref().unwrap_or(&[]), routes) {
+        issues.push(Issue {
+            kind: IssueKind::LineRouteConflict,
+            object_id: format!("Line:{}", conflict.line_id),
+            message: format!(
+                "{} is {} but {} on route {}",
+                conflict.field, conflict.line_value, conflict.route_value, conflict.route_id
+            ),
+        });
+    }
+    issues
+}
+
+pub fn write_issues_to_csv<P: AsRef<Path>>(issues: &[Issue], output_dir: P, prefix: &str) {
+    let output_dir = output_dir.as_ref();
+    let csv_file = output_dir.join(format!("{}issues.csv", prefix));
+
+    let mut wtr = csv::Writer::from_path(csv_file).unwrap();
+    wtr.serialize(("object_id", "kind", "message")).unwrap();
+    for issue in issues {
+        wtr.serialize((
+            &issue.object_id,
+            format!("{:?}", issue.kind),
+            &issue.message,
+        ))
+        .unwrap();
+    }
+}
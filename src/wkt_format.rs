@@ -0,0 +1,134 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::{Coord, StopPoint};
+use geo_types::{Geometry, LineString, MultiLineString, Point, Polygon};
+use wkt::{ToWkt, Wkt};
+
+/// Ways of a shape as a MultiLineString, without the degenerate ways (less than 2 points).
+pub fn ways_to_multi_line_string(shape: &[Vec<Coord>]) -> MultiLineString<f64> {
+    shape
+        .iter()
+        .filter(|way| way.len() >= 2)
+        .map(|way| {
+            way.iter()
+                .map(|coord| (coord.lon, coord.lat))
+                .collect::<LineString<f64>>()
+        })
+        .collect()
+}
+
+/// Geometry of a hull (see `geometry::convex_hull`): a Polygon when it is a closed ring,
+/// a Point or a LineString when all its points are the same or aligned.
+pub fn hull_to_geometry(hull: &[Coord]) -> Geometry<f64> {
+    let coords: Vec<(f64, f64)> = hull.iter().map(|c| (c.lon, c.lat)).collect();
+    match coords.len() {
+        0 => Polygon::new(LineString(vec![]), vec![]).into(),
+        1 => Point::from(coords[0]).into(),
+        2 | 3 => LineString::from(coords[..2].to_vec()).into(),
+        _ => Polygon::new(LineString::from(coords), vec![]).into(),
+    }
+}
+
+pub fn geometry_to_wkt(geometry: &Geometry<f64>) -> String {
+    match geometry {
+        // geo-types has no empty polygon, its exterior ring is just empty
+        Geometry::Polygon(polygon) if polygon.exterior().0.is_empty() => {
+            wkt::types::Polygon::<f64>(vec![]).to_string()
+        }
+        _ => geometry.to_wkt().items[0].to_string(),
+    }
+}
+
+/// WKT of the geometry of a stop point, a POINT when it has no geometry.
+pub fn stop_point_to_wkt(stop_point: &StopPoint) -> String {
+    match &stop_point.geometry {
+        Some(geometry) => geometry_to_wkt(geometry),
+        None => geometry_to_wkt(&Point::new(stop_point.coord.lon, stop_point.coord.lat).into()),
+    }
+}
+
+/// WKT of a shape, `MULTILINESTRING EMPTY` when it has no (valid) way.
+pub fn shape_to_wkt(shape: &[Vec<Coord>]) -> String {
+    geometry_to_wkt(&ways_to_multi_line_string(shape).into())
+}
+
+pub fn hull_to_wkt(hull: &[Coord]) -> String {
+    geometry_to_wkt(&hull_to_geometry(hull))
+}
+
+pub fn wkt_to_geometry(wkt: &str) -> Result<Geometry<f64>, String> {
+    let wkt: Wkt<f64> = Wkt::from_str(wkt).map_err(|e| e.to_string())?;
+    match wkt.items.first() {
+        Some(item) => wkt::conversion::try_into_geometry(item).map_err(|e| e.to_string()),
+        None => Err("no geometry found".to_string()),
+    }
+}
+
+/// Reads a shape written by `shape_to_wkt` (a LINESTRING is read as a single way).
+pub fn wkt_to_shape(wkt: &str) -> Result<Vec<Vec<Coord>>, String> {
+    let to_way = |linestring: &LineString<f64>| -> Vec<Coord> {
+        linestring
+            .0
+            .iter()
+            .map(|c| Coord { lat: c.y, lon: c.x })
+            .collect()
+    };
+    match wkt_to_geometry(wkt)? {
+        Geometry::MultiLineString(multi_line_string) => {
+            Ok(multi_line_string.0.iter().map(to_way).collect())
+        }
+        Geometry::LineString(linestring) => Ok(vec![to_way(&linestring)]),
+        _ => Err(format!("{} is not a MULTILINESTRING", wkt)),
+    }
+}
+
+/// Serialization of an optional geometry as WKT (`#[serde(with = "wkt_format::optional_geometry")]`).
+pub mod optional_geometry {
+    use super::{geometry_to_wkt, wkt_to_geometry};
+    use geo_types::Geometry;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        geometry: &Option<Geometry<f64>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        geometry.as_ref().map(geometry_to_wkt).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Geometry<f64>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|wkt| wkt_to_geometry(&wkt).map_err(D::Error::custom))
+            .transpose()
+    }
+}
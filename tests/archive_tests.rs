@@ -0,0 +1,163 @@
+mod common;
+
+use osm_transit_extractor::archive::{self, Archive, ArchiveFormat};
+use osm_transit_extractor::sink::{self, CsvOptions};
+use osm_transit_extractor::{csv_reader, json, validation};
+use std::io::Read;
+use tempfile::Builder;
+
+fn write_csv_files(prefix: &str) -> tempfile::TempDir {
+    let response = common::extract("osm_fixture.osm.pbf", true);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let options = CsvOptions {
+        prefix: prefix.to_string(),
+        ..Default::default()
+    };
+    osm_transit_extractor::write_stop_points_to_csv_with_options(
+        &response.stop_points,
+        &tmp_dir,
+        &options,
+    );
+    osm_transit_extractor::write_stop_areas_to_csv_with_options(
+        &response.stop_areas,
+        &tmp_dir,
+        &options,
+    );
+    tmp_dir
+}
+
+#[test]
+pub fn archive_formats() {
+    assert_eq!(
+        ArchiveFormat::from_file_name("out.zip"),
+        Ok(ArchiveFormat::Zip)
+    );
+    assert_eq!(
+        ArchiveFormat::from_file_name("out.tar.gz"),
+        Ok(ArchiveFormat::TarGz)
+    );
+    assert_eq!(
+        ArchiveFormat::from_file_name("out.tgz"),
+        Ok(ArchiveFormat::TarGz)
+    );
+    assert_eq!(ArchiveFormat::from_file_name("-"), Ok(ArchiveFormat::TarGz));
+    assert!(ArchiveFormat::from_file_name("out.rar").is_err());
+    let archive: Archive = "-".parse().unwrap();
+    assert!(archive.is_stdout());
+    assert!("out.rar".parse::<Archive>().is_err());
+}
+
+#[test]
+pub fn zip_and_tar_gz_archives() {
+    let csv_dir = write_csv_files("paris_");
+    let stop_points =
+        std::fs::read_to_string(csv_dir.path().join("paris_stop_points.csv")).unwrap();
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+
+    let zip_file = tmp_dir.path().join("out.zip");
+    archive::write_archive(&csv_dir, &zip_file.to_str().unwrap().parse().unwrap());
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&zip_file).unwrap()).unwrap();
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort_unstable();
+    assert_eq!(names, vec!["paris_stop_areas.csv", "paris_stop_points.csv"]);
+    let mut content = String::new();
+    zip.by_name("paris_stop_points.csv")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, stop_points);
+    csv_dir.close().expect("delete temp dir");
+
+    let csv_dir = write_csv_files(sink::DEFAULT_PREFIX);
+    let tar_file = tmp_dir.path().join("out.tar.gz");
+    archive::write_archive(&csv_dir, &tar_file.to_str().unwrap().parse().unwrap());
+    let gz = flate2::read::GzDecoder::new(std::fs::File::open(&tar_file).unwrap());
+    let mut tar = tar::Archive::new(gz);
+    let mut names = vec![];
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().to_string();
+        if name == "osm-transit-extractor_stop_points.csv" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            assert_eq!(content, stop_points);
+        }
+        names.push(name);
+    }
+    assert_eq!(
+        names,
+        vec![
+            "osm-transit-extractor_stop_areas.csv",
+            "osm-transit-extractor_stop_points.csv"
+        ]
+    );
+
+    tmp_dir.close().expect("delete temp dir");
+    csv_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn csv_sink_with_prefix() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let options = CsvOptions {
+        prefix: "paris_".to_string(),
+        ..Default::default()
+    };
+    let mut sink = sink::CsvSink::new(&tmp_dir, options);
+    sink::write_to_sink(&response, &mut sink);
+    drop(sink);
+    let mut names: Vec<String> = std::fs::read_dir(&tmp_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 10);
+    assert!(names.iter().all(|name| name.starts_with("paris_")));
+    assert!(names.contains(&"paris_frequencies.csv".to_string()));
+
+    // read back with the same prefix
+    let read = csv_reader::read_osm_tcobjects_from_csv(&tmp_dir, "paris_").unwrap();
+    assert_eq!(read.stop_points.len(), response.stop_points.len());
+    assert_eq!(
+        read.routes.unwrap().len(),
+        response.routes.as_ref().unwrap().len()
+    );
+    assert!(csv_reader::read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).is_err());
+
+    // the other writers
+    let options = CsvOptions {
+        prefix: "paris_".to_string(),
+        ..Default::default()
+    };
+    osm_transit_extractor::write_stop_areas_stop_point_to_csv_with_options(
+        &response.stop_areas,
+        &tmp_dir,
+        &options,
+    );
+    let issues = validation::validate(&response);
+    validation::write_issues_to_csv(&issues, &tmp_dir, "paris_");
+    json::write_json(&response, &tmp_dir, "paris_");
+    json::write_json_lines(&response, &tmp_dir, "paris_");
+    for name in &[
+        "paris_stop_areas_stop_point.csv",
+        "paris_issues.csv",
+        "paris.json",
+        "paris_stop_points.jsonl",
+    ] {
+        assert!(tmp_dir.path().join(name).exists(), "{}", name);
+    }
+    assert!(json::read_json(&tmp_dir, "paris_").is_ok());
+    assert!(json::read_json_lines(&tmp_dir, "paris_").is_ok());
+
+    tmp_dir.close().expect("delete temp dir");
+}
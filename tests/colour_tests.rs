@@ -0,0 +1,52 @@
+mod common;
+
+use osm_transit_extractor::colour::{contrasting_text_colour, normalize_colour};
+use osm_transit_extractor::validation::{validate, IssueKind};
+
+#[test]
+pub fn normalized_colours() {
+    for colour in &["red", "Red", "#F00", "f00", "ff0000", "#FF0000 ", "#ff0000"] {
+        assert_eq!(normalize_colour(colour), Some("#FF0000".to_string()));
+    }
+    assert_eq!(
+        normalize_colour("rebeccapurple"),
+        Some("#663399".to_string())
+    );
+    for colour in &["", "rouge", "#FF00", "#GG0000", "#FF0000FF", "##F00"] {
+        assert_eq!(normalize_colour(colour), None);
+    }
+}
+
+#[test]
+pub fn contrasting_text_colours() {
+    assert_eq!(contrasting_text_colour("#FF0000"), "#000000");
+    assert_eq!(contrasting_text_colour("#FFFF00"), "#000000");
+    assert_eq!(contrasting_text_colour("#0000FF"), "#FFFFFF");
+    assert_eq!(contrasting_text_colour("#9C983A"), "#000000");
+    assert_eq!(contrasting_text_colour("#663399"), "#FFFFFF");
+}
+
+#[test]
+pub fn osm_fixture_colours() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    let lines = response.lines.as_mut().unwrap();
+    assert_eq!(lines[0].colour, "#9C983A");
+    assert_eq!(lines[0].text_colour, "#000000");
+    assert!(validate(&response)
+        .iter()
+        .all(|i| i.kind != IssueKind::InvalidColour));
+
+    let lines = response.lines.as_mut().unwrap();
+    lines[0]
+        .all_osm_tags
+        .insert("text_colour".into(), "garbage".into());
+    let issues: Vec<String> = validate(&response)
+        .into_iter()
+        .filter(|i| i.kind == IssueKind::InvalidColour)
+        .map(|i| format!("{} {}", i.object_id, i.message))
+        .collect();
+    assert_eq!(
+        issues,
+        vec!["Line:relation:1257187 text_colour garbage is not a valid colour".to_string()]
+    );
+}
@@ -0,0 +1,11 @@
+use osm_transit_extractor::OsmTcResponse;
+
+/// Extracts the public transport objects of a file of `tests/fixtures`.
+pub fn extract(fixture: &str, stops_only: bool) -> OsmTcResponse {
+    let osm_path = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures")
+        .join(fixture);
+    let mut parsed_pbf = osmpbfreader::OsmPbfReader::new(std::fs::File::open(&osm_path).unwrap());
+    osm_transit_extractor::get_osm_tcobjects(&mut parsed_pbf, stops_only)
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use osm_transit_extractor::csv_reader::*;
+use osm_transit_extractor::metrics::Metrics;
+use osm_transit_extractor::tags::TagsMode;
+use osm_transit_extractor::*;
+use std::path::Path;
+use tempfile::Builder;
+
+fn write_csv(response: &OsmTcResponse, output_dir: &Path, tags_mode: &TagsMode, hulls: bool) {
+    let options = sink::CsvOptions {
+        tags_mode: tags_mode.clone(),
+        hulls,
+        ..Default::default()
+    };
+    write_stop_points_to_csv_with_options(&response.stop_points, output_dir, &options);
+    write_stop_areas_to_csv_with_options(&response.stop_areas, output_dir, &options);
+    write_stop_areas_stop_point_to_csv(&response.stop_areas, output_dir);
+    write_stop_point_pairs_to_csv(&response.stop_points, output_dir, &options);
+    if let Some(routes) = &response.routes {
+        write_routes_to_csv_with_options(routes, output_dir, &options);
+    }
+    if let Some(lines) = &response.lines {
+        write_lines_to_csv_with_options(lines, output_dir, &options);
+    }
+}
+
+fn round(distance: Option<f64>) -> Option<f64> {
+    distance.map(|d| format!("{:.1}", d).parse().unwrap())
+}
+
+fn round_metrics(metrics: &mut Metrics) {
+    metrics.length = round(Some(metrics.length)).unwrap();
+    metrics.mean_stop_distance = round(metrics.mean_stop_distance);
+    metrics.max_stop_distance = round(metrics.max_stop_distance);
+    metrics.origin_destination_distance = round(metrics.origin_destination_distance);
+}
+
+/// The objects as they are written in the CSV files.
+fn as_written(mut response: OsmTcResponse) -> OsmTcResponse {
+    for sa in &mut response.stop_areas {
+        sa.stop_position_ids.clear();
+    }
+    for r in response.routes.iter_mut().flatten() {
+        for rp in &mut r.ordered_route_points {
+            rp.shape_dist_traveled = round(rp.shape_dist_traveled);
+            rp.distance_to_shape = round(rp.distance_to_shape);
+        }
+        r.shape.retain(|way| way.len() >= 2);
+        round_metrics(&mut r.metrics);
+    }
+    for l in response.lines.iter_mut().flatten() {
+        l.dangling_routes_id.clear();
+        l.shape.retain(|way| way.len() >= 2);
+        round_metrics(&mut l.metrics);
+    }
+    response
+}
+
+#[test]
+pub fn csv_round_trip() {
+    for fixture in &[
+        "osm_fixture.osm.pbf",
+        "platform_polygons.osm.pbf",
+        "platform_pairing.osm.pbf",
+        "super_route.osm.pbf",
+    ] {
+        for tags_mode in &[TagsMode::Columns, TagsMode::Json] {
+            let tmp_dir = Builder::new()
+                .prefix("osm_transit_extractor")
+                .tempdir()
+                .expect("create temp dir");
+            let mut response = common::extract(fixture, false);
+            geometry::compute_stop_areas_hull(&mut response.stop_areas, &response.stop_points);
+            write_csv(&response, tmp_dir.path(), tags_mode, true);
+            let read = read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).unwrap();
+            assert_eq!(read, as_written(response), "{}", fixture);
+
+            // writing again the objects read gives the same files (except the distances
+            // from the previous stops, computed again from the rounded distances)
+            let other_dir = Builder::new()
+                .prefix("osm_transit_extractor")
+                .tempdir()
+                .expect("create temp dir");
+            write_csv(&read, other_dir.path(), tags_mode, true);
+            for entry in std::fs::read_dir(&tmp_dir).unwrap() {
+                let path = entry.unwrap().path();
+                let other = other_dir.path().join(path.file_name().unwrap());
+                let content = |path: &Path| -> Vec<String> {
+                    let route_points = path.ends_with("osm-transit-extractor_route_points.csv");
+                    std::fs::read_to_string(path)
+                        .unwrap()
+                        .lines()
+                        .map(|l| {
+                            if route_points {
+                                l.rsplit_once(',').unwrap().0.to_string()
+                            } else {
+                                l.to_string()
+                            }
+                        })
+                        .collect()
+                };
+                assert_eq!(content(&path), content(&other), "{:?}", path);
+            }
+
+            tmp_dir.close().expect("delete temp dir");
+            other_dir.close().expect("delete temp dir");
+        }
+    }
+}
+
+#[test]
+pub fn csv_stops_only() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let response = common::extract("osm_fixture.osm.pbf", true);
+    write_csv(&response, tmp_dir.path(), &TagsMode::None, false);
+    let stop_areas_header =
+        std::fs::read_to_string(tmp_dir.path().join("osm-transit-extractor_stop_areas.csv"))
+            .unwrap();
+    assert!(stop_areas_header.starts_with("stop_area_id,lat,lon,name\n"));
+    let read = read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).unwrap();
+    assert!(read.routes.is_none());
+    assert!(read.lines.is_none());
+    assert_eq!(read.stop_points.len(), response.stop_points.len());
+    // without the tags columns
+    assert!(read.stop_points.iter().all(|sp| sp.all_osm_tags.is_empty()));
+    assert_eq!(read.stop_points[0].id, response.stop_points[0].id);
+    assert_eq!(read.stop_points[0].coord, response.stop_points[0].coord);
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn csv_invalid_files() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let response = common::extract("osm_fixture.osm.pbf", true);
+    write_csv(&response, tmp_dir.path(), &TagsMode::None, false);
+    let file = tmp_dir.path().join("osm-transit-extractor_stop_points.csv");
+    let content = std::fs::read_to_string(&file).unwrap();
+
+    // a missing column
+    let without_name: String = content
+        .lines()
+        .map(|l| l.splitn(5, ',').take(4).collect::<Vec<_>>().join(",") + "\n")
+        .collect();
+    std::fs::write(&file, without_name).unwrap();
+    let error = read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).unwrap_err();
+    assert!(error.contains("osm-transit-extractor_stop_points.csv:2: no column"));
+
+    // an invalid coordinate
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let mut fields: Vec<&str> = lines[2].split(',').collect();
+    let lat = fields.iter().position(|f| f.contains('.')).unwrap();
+    fields[lat] = "north";
+    lines[2] = fields.join(",");
+    std::fs::write(&file, lines.join("\n")).unwrap();
+    let error = read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).unwrap_err();
+    assert!(
+        error.ends_with(":3: invalid number north in column lat"),
+        "{}",
+        error
+    );
+
+    // a missing file
+    std::fs::remove_file(&file).unwrap();
+    assert!(read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).is_err());
+
+    tmp_dir.close().expect("delete temp dir");
+}
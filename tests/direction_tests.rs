@@ -0,0 +1,78 @@
+mod common;
+
+use osm_transit_extractor::direction::{compute_directions, RouteVariant};
+
+#[test]
+pub fn osm_fixture_directions() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    let direction = |id: &str| {
+        let route = routes.iter().find(|r| r.id == id).unwrap();
+        (route.direction_id, route.variant)
+    };
+    assert_eq!(
+        direction("relation:1257168"),
+        (Some(0), Some(RouteVariant::Main))
+    );
+    // the stops of this route are mapped in the same order as the other route
+    assert_eq!(
+        direction("relation:1257174"),
+        (Some(0), Some(RouteVariant::Branch))
+    );
+    // not part of a line
+    assert_eq!(direction("relation:123"), (None, None));
+}
+
+#[test]
+pub fn directions_and_variants() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let mut main = response
+        .routes
+        .unwrap()
+        .into_iter()
+        .find(|r| r.id == "relation:1257168")
+        .unwrap();
+    main.ordered_route_points
+        .dedup_by(|a, b| a.stop_point_id == b.stop_point_id);
+    let mut line = response.lines.unwrap().into_iter().next().unwrap();
+
+    let mut opposite = main.clone();
+    opposite.id = "opposite".to_string();
+    opposite.ordered_route_points.reverse();
+    let mut short_turn = main.clone();
+    short_turn.id = "short_turn".to_string();
+    short_turn.ordered_route_points.truncate(10);
+    let mut branch = main.clone();
+    branch.id = "branch".to_string();
+    branch.ordered_route_points.truncate(20);
+    branch.ordered_route_points[19].stop_point_id = "node:unknown".to_string();
+    let mut reverse_short_turn = opposite.clone();
+    reverse_short_turn.id = "reverse_short_turn".to_string();
+    reverse_short_turn.ordered_route_points.drain(..5);
+
+    let mut routes = vec![main, opposite, short_turn, branch, reverse_short_turn];
+    line.routes_id = routes.iter().map(|r| r.id.clone()).collect();
+    for r in routes.iter_mut() {
+        r.line_id = line.id.clone();
+    }
+    compute_directions(
+        &mut routes,
+        &[line],
+        &response.stop_points,
+        &response.stop_areas,
+    );
+    let directions: Vec<(&str, Option<u8>, Option<RouteVariant>)> = routes
+        .iter()
+        .map(|r| (r.id.as_str(), r.direction_id, r.variant))
+        .collect();
+    assert_eq!(
+        directions,
+        vec![
+            ("relation:1257168", Some(0), Some(RouteVariant::Main)),
+            ("opposite", Some(1), Some(RouteVariant::Main)),
+            ("short_turn", Some(0), Some(RouteVariant::ShortTurn)),
+            ("branch", Some(0), Some(RouteVariant::Branch)),
+            ("reverse_short_turn", Some(1), Some(RouteVariant::ShortTurn)),
+        ]
+    );
+}
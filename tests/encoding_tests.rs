@@ -0,0 +1,99 @@
+use osm_transit_extractor::encoding::*;
+use osm_transit_extractor::Coord;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+fn shape() -> Vec<Vec<Coord>> {
+    vec![vec![
+        Coord {
+            lat: 38.5,
+            lon: -120.2,
+        },
+        Coord {
+            lat: 40.7,
+            lon: -120.95,
+        },
+        Coord {
+            lat: 43.252,
+            lon: -126.453,
+        },
+    ]]
+}
+
+#[test]
+pub fn shape_encodings() {
+    let shape = shape();
+    assert_eq!(
+        encode_shape(&shape, GeometryEncoding::Polyline),
+        "_p~iF~ps|U_ulLnnqC_mqNvxq`@"
+    );
+    assert_eq!(
+        encode_shape(&shape, GeometryEncoding::GeoJson),
+        r#"{"coordinates":[[[-120.2,38.5],[-120.95,40.7],[-126.453,43.252]]],"type":"MultiLineString"}"#
+    );
+    let wkb = encode_shape(&shape, GeometryEncoding::WkbHex);
+    // little endian MultiLineString of 1 LineString of 3 points
+    assert!(wkb.starts_with("010500000001000000010200000003000000CDCCCCCCCC0C5EC0"));
+    assert_eq!(wkb.len(), 2 * (9 + 9 + 3 * 16));
+    assert_eq!(
+        encode_shape(&[], GeometryEncoding::WkbHex),
+        "010500000000000000"
+    );
+    assert_eq!(
+        encode_shape(&[], GeometryEncoding::Wkt),
+        "MULTILINESTRING EMPTY"
+    );
+}
+
+#[test]
+pub fn osm_fixture_routes_csv_with_polylines() {
+    let osm_path = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/osm_fixture.osm.pbf");
+    let mut parsed_pbf = osmpbfreader::OsmPbfReader::new(std::fs::File::open(&osm_path).unwrap());
+    let routes = osm_transit_extractor::get_routes_from_osm(&mut parsed_pbf);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::write_routes_to_csv_with_options(
+        &routes,
+        &tmp_dir,
+        &osm_transit_extractor::sink::CsvOptions {
+            geometry_encoding: GeometryEncoding::Polyline,
+            ..Default::default()
+        },
+    );
+    let file_path = tmp_dir.path().join("osm-transit-extractor_routes.csv");
+    let reader = BufReader::new(File::open(file_path).unwrap());
+    let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+    assert!(lines[0].contains(",shape,"));
+    assert!(lines.iter().all(|l| !l.contains("MULTILINESTRING")));
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn wkb_of_all_geometries() {
+    use geo_types::{Geometry, GeometryCollection, Line, MultiPoint, Point, Rect};
+    let point = Point::new(1., 2.);
+    assert_eq!(geometry_to_wkb(&point.into()).len(), 21);
+    // MultiPoint of 2 Points
+    let multi_point: Geometry<f64> = MultiPoint(vec![point, Point::new(3., 4.)]).into();
+    let wkb = geometry_to_wkb(&multi_point);
+    assert_eq!(&wkb[..9], &[1, 4, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(wkb.len(), 9 + 2 * 21);
+    // GeometryCollection of a Point and a Line written as a LineString
+    let line = Line::new((0., 0.), (1., 1.));
+    let collection: Geometry<f64> =
+        Geometry::GeometryCollection(GeometryCollection(vec![point.into(), line.into()]));
+    let wkb = geometry_to_wkb(&collection);
+    assert_eq!(&wkb[..9], &[1, 7, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(&wkb[30..35], &[1, 2, 0, 0, 0]);
+    assert_eq!(wkb.len(), 9 + 21 + 9 + 2 * 16);
+    // Rect written as a Polygon of 1 ring of 5 points
+    let rect: Geometry<f64> = Rect::new((0., 0.), (1., 1.)).into();
+    let wkb = geometry_to_wkb(&rect);
+    assert_eq!(&wkb[..13], &[1, 3, 0, 0, 0, 1, 0, 0, 0, 5, 0, 0, 0]);
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use osm_transit_extractor::frequency::{parse_headway_windows, parse_interval};
+use osm_transit_extractor::opening_hours::{TimeRange, Weekday};
+use osm_transit_extractor::validation::{validate, IssueKind};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+#[test]
+pub fn parsed_intervals() {
+    assert_eq!(parse_interval("10"), Ok(600));
+    assert_eq!(parse_interval("00:10"), Ok(600));
+    assert_eq!(parse_interval(" 01:30 "), Ok(5400));
+    assert_eq!(parse_interval("00:07:30"), Ok(450));
+    for interval in &["", "0", "00:00", "10 min", "00:60", "1:2:3:4", "-5"] {
+        assert!(parse_interval(interval).is_err(), "{}", interval);
+    }
+    // overflowing u32 seconds
+    for interval in &["71582789", "1193047:00", "1193046:28:16"] {
+        assert_eq!(
+            parse_interval(interval),
+            Err(format!("invalid interval {}", interval))
+        );
+    }
+    assert_eq!(parse_interval("1193046:28:15"), Ok(u32::MAX));
+}
+
+#[test]
+pub fn parsed_headway_windows() {
+    let windows = parse_headway_windows(
+        "00:10",
+        "00:05 @ (Mo-Fr 07:00-09:00,17:00-19:00); 00:30 @ (Sa,PH)",
+    )
+    .unwrap();
+    let summary: Vec<(u32, String, TimeRange, bool)> = windows
+        .iter()
+        .map(|w| (w.headway_secs, w.days(), w.time_range, w.conditional))
+        .collect();
+    let range = |start, end| TimeRange { start, end };
+    assert_eq!(
+        summary,
+        vec![
+            (600, "Mo-Fr".to_string(), range(0, 420), false),
+            (600, "Mo-Fr".to_string(), range(540, 1020), false),
+            (600, "Mo-Fr".to_string(), range(1140, 1440), false),
+            (600, "Su".to_string(), range(0, 1440), false),
+            (300, "Mo-Fr".to_string(), range(420, 540), true),
+            (300, "Mo-Fr".to_string(), range(1020, 1140), true),
+            (1800, "Sa,PH".to_string(), range(0, 1440), true),
+        ]
+    );
+    assert_eq!(windows[6].weekdays, vec![Weekday::Sa]);
+    assert!(windows[6].public_holidays);
+
+    // a later condition overrides an earlier one
+    let windows =
+        parse_headway_windows("", "00:05 @ (Mo-Fr 07:00-09:00); 00:02 @ (Mo 08:00-10:00)").unwrap();
+    let summary: Vec<(u32, String, TimeRange)> = windows
+        .iter()
+        .map(|w| (w.headway_secs, w.days(), w.time_range))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (300, "Mo".to_string(), range(420, 480)),
+            (300, "Tu-Fr".to_string(), range(420, 540)),
+            (120, "Mo".to_string(), range(480, 600)),
+        ]
+    );
+
+    // without parentheses, without interval
+    let windows = parse_headway_windows("", "15 @ Su 22:00-01:00").unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].time_range, range(1320, 1500));
+    assert_eq!(parse_headway_windows("", ""), Ok(vec![]));
+
+    for (interval, conditional) in &[
+        ("10 minutes", ""),
+        ("10", "00:05"),
+        ("10", "00:05 @ (Jan)"),
+        ("10", "00:05 @ (Su off)"),
+        ("10", "five @ (Sa)"),
+    ] {
+        assert!(
+            parse_headway_windows(interval, conditional).is_err(),
+            "{} {}",
+            interval,
+            conditional
+        );
+    }
+}
+
+#[test]
+pub fn frequencies_csv() {
+    let mut response = common::extract("shape_with_platform.osm.pbf", false);
+    let routes = response.routes.as_mut().unwrap();
+    assert_eq!(routes[0].headways.len(), 1);
+    assert_eq!(routes[0].headways[0].headway_secs, 300);
+    routes[0].frequency_exceptions = "00:15 @ (Mo-Fr 21:00-00:30)".to_string();
+    routes[0].headways =
+        parse_headway_windows(&routes[0].frequency, &routes[0].frequency_exceptions).unwrap();
+
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::write_frequencies_to_csv(
+        response.routes.as_deref().unwrap(),
+        response.lines.as_deref().unwrap(),
+        &tmp_dir,
+        &osm_transit_extractor::sink::CsvOptions::default(),
+    );
+    let file_path = tmp_dir.path().join("osm-transit-extractor_frequencies.csv");
+    let reader = BufReader::new(File::open(file_path).unwrap());
+    let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+    let route_id = format!("Route:{}", response.routes.as_ref().unwrap()[0].id);
+    assert_eq!(
+        lines,
+        vec![
+            "object_id,days,start_time,end_time,headway_secs,conditional".to_string(),
+            format!("{},Mo,00:00:00,21:00:00,300,false", route_id),
+            format!("{},Tu-Fr,00:30:00,21:00:00,300,false", route_id),
+            format!("{},Sa,00:30:00,24:00:00,300,false", route_id),
+            format!("{},Su,00:00:00,24:00:00,300,false", route_id),
+            format!("{},Mo-Fr,21:00:00,24:30:00,900,true", route_id),
+        ]
+    );
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn invalid_frequencies() {
+    let mut response = common::extract("shape_with_platform.osm.pbf", false);
+    assert!(validate(&response)
+        .iter()
+        .all(|i| i.kind != IssueKind::InvalidFrequency));
+    let routes = response.routes.as_mut().unwrap();
+    routes[0].frequency_exceptions = "00:05 @ (sunrise-sunset)".to_string();
+    let issues: Vec<String> = validate(&response)
+        .into_iter()
+        .filter(|i| i.kind == IssueKind::InvalidFrequency)
+        .map(|i| i.message)
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            "interval or interval:conditional is not valid : unsupported selector sunrise-sunset"
+                .to_string()
+        ]
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use osm_transit_extractor::tags::TagsMode;
+use tempfile::Builder;
+
+#[test]
+pub fn osm_fixture_geopackage() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::geopackage::write_geopackage(&response, &tmp_dir, &TagsMode::Columns);
+    // writing twice replaces the file
+    osm_transit_extractor::geopackage::write_geopackage(&response, &tmp_dir, &TagsMode::None);
+
+    let conn =
+        rusqlite::Connection::open(tmp_dir.path().join("osm-transit-extractor.gpkg")).unwrap();
+    let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("PRAGMA application_id"), 0x4750_4B47);
+    assert_eq!(count("SELECT count(*) FROM stop_points"), 77);
+    assert_eq!(count("SELECT count(*) FROM routes"), 3);
+    assert_eq!(count("SELECT count(*) FROM lines"), 1);
+    assert_eq!(count("SELECT count(*) FROM route_points"), 66);
+    assert_eq!(count("SELECT count(*) FROM line_routes"), 2);
+    assert_eq!(
+        count("SELECT count(*) FROM gpkg_contents WHERE data_type = 'features'"),
+        5
+    );
+    assert_eq!(
+        count("SELECT count(*) FROM gpkg_extensions WHERE extension_name = 'gpkg_rtree_index'"),
+        5
+    );
+    // routes without shape are not in the spatial index
+    assert_eq!(count("SELECT count(*) FROM rtree_routes_geom"), 2);
+    assert_eq!(count("SELECT count(*) FROM rtree_stop_points_geom"), 77);
+    // the 6 triggers of the rtree extension on each feature table
+    assert_eq!(
+        count("SELECT count(*) FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'rtree_%'"),
+        5 * 6
+    );
+    // rows can still be deleted without the ST_* functions
+    assert_eq!(
+        conn.execute("DELETE FROM stop_points WHERE fid = 1", [])
+            .unwrap(),
+        1
+    );
+    assert_eq!(count("SELECT count(*) FROM rtree_stop_points_geom"), 76);
+    let header: Vec<u8> = conn
+        .query_row("SELECT geom FROM stop_points LIMIT 1", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(&header[..4], b"GP\x00\x03");
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn geopackage_tags_modes() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let file = tmp_dir.path().join("osm-transit-extractor.gpkg");
+
+    osm_transit_extractor::geopackage::write_geopackage(&response, &tmp_dir, &TagsMode::Json);
+    let conn = rusqlite::Connection::open(&file).unwrap();
+    let tags: String = conn
+        .query_row(
+            "SELECT osm_tags FROM routes WHERE route_id = 'Route:relation:1257168'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let tags: serde_json::Value = serde_json::from_str(&tags).unwrap();
+    assert_eq!(tags["network"], "RATP");
+    drop(conn);
+
+    osm_transit_extractor::geopackage::write_geopackage(
+        &response,
+        &tmp_dir,
+        &TagsMode::Whitelist(vec!["network".to_string(), "unknown".to_string()]),
+    );
+    let conn = rusqlite::Connection::open(&file).unwrap();
+    let (network, unknown): (String, Option<String>) = conn
+        .query_row(
+            "SELECT \"osm:network\", \"osm:unknown\" FROM routes WHERE route_id = 'Route:relation:1257168'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(network, "RATP");
+    assert_eq!(unknown, None);
+    drop(conn);
+
+    // too many tag columns for SQLite: the tags are written as JSON
+    let keys = (0..=osm_transit_extractor::geopackage::MAX_TAG_COLUMNS)
+        .map(|i| format!("key{}", i))
+        .chain(std::iter::once("network".to_string()))
+        .collect();
+    osm_transit_extractor::geopackage::write_geopackage(
+        &response,
+        &tmp_dir,
+        &TagsMode::Whitelist(keys),
+    );
+    let conn = rusqlite::Connection::open(&file).unwrap();
+    let tags: String = conn
+        .query_row(
+            "SELECT osm_tags FROM routes WHERE route_id = 'Route:relation:1257168'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let tags: serde_json::Value = serde_json::from_str(&tags).unwrap();
+    assert_eq!(tags["network"], "RATP");
+    drop(conn);
+
+    tmp_dir.close().expect("delete temp dir");
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use osm_transit_extractor::{json, sink};
+use tempfile::Builder;
+
+#[test]
+pub fn json_round_trip() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    for fixture in &["osm_fixture.osm.pbf", "platform_polygons.osm.pbf"] {
+        let response = common::extract(fixture, false);
+        json::write_json(&response, &tmp_dir, sink::DEFAULT_PREFIX);
+        assert_eq!(
+            json::read_json(&tmp_dir, sink::DEFAULT_PREFIX).unwrap(),
+            response
+        );
+    }
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn json_lines_round_trip() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let response = common::extract("platform_polygons.osm.pbf", false);
+    json::write_json_lines(&response, &tmp_dir, sink::DEFAULT_PREFIX);
+    let stop_points = std::fs::read_to_string(
+        tmp_dir
+            .path()
+            .join("osm-transit-extractor_stop_points.jsonl"),
+    )
+    .unwrap();
+    assert_eq!(stop_points.lines().count(), response.stop_points.len());
+    // platforms geometries are written as WKT
+    assert!(stop_points.contains(r#""geometry":"POLYGON(("#));
+    assert_eq!(
+        json::read_json_lines(&tmp_dir, sink::DEFAULT_PREFIX).unwrap(),
+        response
+    );
+
+    tmp_dir.close().expect("delete temp dir");
+
+    // without routes and lines
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let response = common::extract("osm_fixture.osm.pbf", true);
+    json::write_json_lines(&response, &tmp_dir, sink::DEFAULT_PREFIX);
+    assert!(!tmp_dir
+        .path()
+        .join("osm-transit-extractor_routes.jsonl")
+        .exists());
+    let read = json::read_json_lines(&tmp_dir, sink::DEFAULT_PREFIX).unwrap();
+    assert!(read.routes.is_none());
+    assert_eq!(read, response);
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn json_invalid_files() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let response = common::extract("osm_fixture.osm.pbf", true);
+
+    // a truncated JSON document
+    json::write_json(&response, &tmp_dir, sink::DEFAULT_PREFIX);
+    let file = tmp_dir.path().join("osm-transit-extractor.json");
+    let content = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, &content[..content.len() / 2]).unwrap();
+    let error = json::read_json(&tmp_dir, sink::DEFAULT_PREFIX).unwrap_err();
+    assert!(error.contains("osm-transit-extractor.json: "), "{}", error);
+    assert!(error.contains("line 1"), "{}", error);
+
+    // an invalid object
+    json::write_json_lines(&response, &tmp_dir, sink::DEFAULT_PREFIX);
+    let file = tmp_dir
+        .path()
+        .join("osm-transit-extractor_stop_areas.jsonl");
+    let mut lines: Vec<String> = std::fs::read_to_string(&file)
+        .unwrap()
+        .lines()
+        .map(|l| l.to_string())
+        .collect();
+    lines.insert(1, r#"{"id":"relation:1"}"#.to_string());
+    std::fs::write(&file, lines.join("\n")).unwrap();
+    let error = json::read_json_lines(&tmp_dir, sink::DEFAULT_PREFIX).unwrap_err();
+    assert!(
+        error.contains("osm-transit-extractor_stop_areas.jsonl:2: missing field"),
+        "{}",
+        error
+    );
+
+    // a missing file
+    std::fs::remove_file(&file).unwrap();
+    assert!(json::read_json_lines(&tmp_dir, sink::DEFAULT_PREFIX).is_err());
+
+    tmp_dir.close().expect("delete temp dir");
+}
@@ -0,0 +1,97 @@
+mod common;
+
+#[test]
+pub fn line_inherits_fields_shared_by_its_routes() {
+    let response = common::extract("line_inheritance.osm.pbf", false);
+    let lines = response.lines.unwrap();
+    assert_eq!(lines[0].colour, "#FF0000".to_string());
+    assert_eq!(lines[0].code, "1".to_string());
+    assert_eq!(lines[0].operator, String::new());
+    assert_eq!(lines[0].network, "Network 1".to_string());
+    assert_eq!(lines[0].stop_area_ids, vec!["relation:30".to_string()]);
+}
+
+#[test]
+pub fn line_conflicts_with_its_routes() {
+    let response = common::extract("line_inheritance.osm.pbf", false);
+    let conflicts = osm_transit_extractor::consolidation::find_line_conflicts(
+        response.lines.as_ref().unwrap(),
+        response.routes.as_ref().unwrap(),
+    );
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(
+        conflicts[0].field,
+        osm_transit_extractor::consolidation::InheritedField::Network
+    );
+    assert_eq!(conflicts[0].route_id, "relation:21".to_string());
+}
+
+#[test]
+pub fn computed_text_colours_are_not_tagged() {
+    let response = common::extract("line_inheritance.osm.pbf", false);
+    // the line is tagged text_colour=#FFFFFF, its route only colour=#FFFF00
+    let routes = response.routes.unwrap();
+    let route = routes.iter().find(|r| r.id == "relation:23").unwrap();
+    assert_eq!(route.text_colour, "#000000");
+    let lines = response.lines.unwrap();
+    let line = lines.iter().find(|l| l.id == "relation:11").unwrap();
+    assert_eq!(line.colour, "#FFFF00");
+    assert_eq!(line.text_colour, "#FFFFFF");
+    let conflicts = osm_transit_extractor::consolidation::find_line_conflicts(&lines, &routes);
+    assert!(conflicts.iter().all(|c| c.line_id != "relation:11"));
+
+    // the line does not inherit the computed text colour of its route
+    let mut routes = routes;
+    let route = routes.iter_mut().find(|r| r.id == "relation:23").unwrap();
+    route.colour = "#0000FF".to_string();
+    route.text_colour = "#FFFFFF".to_string();
+    let mut lines = lines;
+    let line = lines.iter_mut().find(|l| l.id == "relation:11").unwrap();
+    line.all_osm_tags.remove("text_colour");
+    line.text_colour.clear();
+    osm_transit_extractor::consolidation::consolidate_lines(&mut lines, &routes, &[]);
+    let line = lines.iter().find(|l| l.id == "relation:11").unwrap();
+    // contrasting with its own colour
+    assert_eq!(line.text_colour, "#000000");
+    assert!(
+        osm_transit_extractor::consolidation::find_line_conflicts(&lines, &routes)
+            .iter()
+            .all(|c| c.field != osm_transit_extractor::consolidation::InheritedField::TextColour)
+    );
+}
+
+#[test]
+pub fn osm_fixture_synthetic_lines() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    osm_transit_extractor::consolidation::add_synthetic_lines(&mut response);
+    let lines = response.lines.unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(!lines[0].is_synthetic());
+    assert!(lines[1].is_synthetic());
+    assert_eq!(lines[1].id, "synthetic:relation:123".to_string());
+    assert_eq!(lines[1].routes_id, vec!["relation:123".to_string()]);
+    assert!(response
+        .routes
+        .unwrap()
+        .iter()
+        .all(|r| !r.line_id.is_empty()));
+}
+
+#[test]
+pub fn synthetic_line_named_after_its_first_route() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    response.lines = Some(vec![]);
+    let routes = response.routes.as_mut().unwrap();
+    routes.retain(|r| r.network == "RATP");
+    routes.reverse();
+    for r in routes.iter_mut() {
+        r.line_id.clear();
+        r.code.clear();
+        r.name = format!("Route {}", r.id);
+    }
+    osm_transit_extractor::consolidation::add_synthetic_lines(&mut response);
+    let lines = response.lines.unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].id, "synthetic:relation:1257168");
+    assert_eq!(lines[0].name, "Route relation:1257168");
+}
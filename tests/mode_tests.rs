@@ -0,0 +1,144 @@
+mod common;
+
+use osm_transit_extractor::mode::{Mode, SubMode, TransportMode};
+use osm_transit_extractor::tags::TagsMode;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+fn transport_mode(tags: &[(&str, &str)], mode_key: &str) -> TransportMode {
+    let mut osm_tags = osmpbfreader::Tags::default();
+    for (k, v) in tags {
+        osm_tags.insert((*k).into(), (*v).into());
+    }
+    TransportMode::from_tags(&osm_tags, mode_key)
+}
+
+#[test]
+pub fn transport_modes_from_tags() {
+    let train = transport_mode(&[("route", "train"), ("service", "high_speed")], "route");
+    assert_eq!(train.mode, Mode::Train);
+    assert_eq!(train.sub_mode, Some(SubMode::HighSpeed));
+    assert_eq!(train.physical_mode(), "LongDistanceTrain");
+    assert_eq!(train.commercial_mode(), "High speed train");
+    assert_eq!(train.gtfs_route_type(), 2);
+    assert_eq!(train.gtfs_extended_route_type(), 101);
+    assert_eq!(train.netex_mode(), "rail");
+    assert_eq!(train.netex_sub_mode(), Some("highSpeedRail"));
+
+    let commuter = transport_mode(
+        &[("route_master", "railway"), ("service", "commuter")],
+        "route_master",
+    );
+    assert_eq!(commuter.sub_mode, Some(SubMode::Commuter));
+    assert_eq!(commuter.physical_mode(), "RapidTransit");
+    assert_eq!(commuter.gtfs_extended_route_type(), 109);
+
+    let lift = transport_mode(&[("route", "aerialway"), ("aerialway", "t-bar")], "route");
+    assert_eq!(lift.sub_mode, Some(SubMode::DragLift));
+    assert_eq!(lift.physical_mode(), "SuspendedCableCar");
+    assert_eq!(lift.gtfs_route_type(), 6);
+    assert_eq!(lift.gtfs_extended_route_type(), 1305);
+    assert_eq!(lift.netex_mode(), "cableway");
+    assert_eq!(lift.netex_sub_mode(), Some("dragLift"));
+
+    // the service of a bus is not a sub-mode
+    let bus = transport_mode(&[("route", "bus"), ("service", "regional")], "route");
+    assert_eq!(bus.sub_mode, None);
+    assert_eq!(bus.physical_mode(), "Bus");
+    assert_eq!(bus.gtfs_extended_route_type(), 700);
+    assert_eq!(bus.netex_sub_mode(), None);
+
+    let unknown = transport_mode(&[("route", "spaceship")], "route");
+    assert_eq!(unknown.mode, Mode::Unknown);
+    assert_eq!(unknown.gtfs_route_type(), 3);
+}
+
+#[test]
+pub fn gtfs_route_types() {
+    let route_types: Vec<(u16, u16)> = [
+        "tram",
+        "light_rail",
+        "subway",
+        "monorail",
+        "rail",
+        "bus",
+        "coach",
+        "share_taxi",
+        "trolleybus",
+        "ferry",
+        "funicular",
+    ]
+    .iter()
+    .map(|mode| {
+        let transport_mode = TransportMode::new(mode, None);
+        (
+            transport_mode.gtfs_route_type(),
+            transport_mode.gtfs_extended_route_type(),
+        )
+    })
+    .collect();
+    assert_eq!(
+        route_types,
+        vec![
+            (0, 900),
+            (0, 900),
+            (1, 401),
+            (12, 405),
+            (2, 100),
+            (3, 700),
+            (3, 200),
+            (3, 1501),
+            (11, 800),
+            (4, 1200),
+            (7, 1400)
+        ]
+    );
+    assert_eq!(
+        TransportMode::new("bus", Some(SubMode::Regional)).sub_mode,
+        None
+    );
+}
+
+#[test]
+pub fn osm_fixture_modes_csv() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let lines = response.lines.unwrap();
+    osm_transit_extractor::write_lines_to_csv(lines.clone(), &tmp_dir, false);
+    let file_path = tmp_dir.path().join("osm-transit-extractor_lines.csv");
+    let mut reader = csv::Reader::from_reader(BufReader::new(File::open(file_path).unwrap()));
+    let headers = reader.headers().unwrap().clone();
+    let record = reader.records().next().unwrap().unwrap();
+    let column = |name: &str| &record[headers.iter().position(|h| h == name).unwrap()];
+    assert_eq!(column("mode"), "bus");
+    assert_eq!(column("sub_mode"), "");
+    assert_eq!(column("physical_mode"), "Bus");
+    assert_eq!(column("commercial_mode"), "Bus");
+    assert_eq!(column("netex_mode"), "bus");
+    assert_eq!(column("netex_sub_mode"), "");
+    assert_eq!(column("gtfs_route_type"), "3");
+
+    let file_path = tmp_dir.path().join("osm-transit-extractor_lines.csv");
+    assert!(BufReader::new(File::open(file_path).unwrap())
+        .lines()
+        .next()
+        .unwrap()
+        .unwrap()
+        .contains(
+            ",mode,sub_mode,physical_mode,commercial_mode,netex_mode,netex_sub_mode,gtfs_route_type,"
+        ));
+
+    osm_transit_extractor::geojson::write_lines_to_geojson(&lines, &tmp_dir, &TagsMode::None);
+    let file_path = tmp_dir.path().join("osm-transit-extractor_lines.geojson");
+    let collection: serde_json::Value =
+        serde_json::from_reader(File::open(file_path).unwrap()).unwrap();
+    let properties = &collection["features"][0]["properties"];
+    assert_eq!(properties["netex_mode"], "bus");
+    assert!(properties["netex_sub_mode"].is_null());
+    assert_eq!(properties["gtfs_route_type"], 3);
+    tmp_dir.close().expect("delete temp dir");
+}
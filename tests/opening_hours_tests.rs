@@ -0,0 +1,165 @@
+mod common;
+
+use osm_transit_extractor::opening_hours::{OpeningHours, TimeRange, Weekday};
+use osm_transit_extractor::validation::{validate, IssueKind};
+
+fn parse(opening_hours: &str) -> OpeningHours {
+    opening_hours.parse().unwrap()
+}
+
+#[test]
+pub fn parsed_opening_hours() {
+    let calendar = parse("Mo-Fr 06:00-09:30,16:00-20:00; Sa 08:00-20:00; Su,PH off");
+    assert_eq!(calendar.rules.len(), 3);
+    assert_eq!(
+        calendar.rules[0].weekdays,
+        vec![
+            Weekday::Mo,
+            Weekday::Tu,
+            Weekday::We,
+            Weekday::Th,
+            Weekday::Fr
+        ]
+    );
+    assert_eq!(
+        calendar.rules[0].time_ranges,
+        vec![
+            TimeRange {
+                start: 360,
+                end: 570
+            },
+            TimeRange {
+                start: 960,
+                end: 1200
+            }
+        ]
+    );
+    assert!(calendar.rules[2].public_holidays);
+    assert!(calendar.rules[2].off);
+    assert_eq!(
+        calendar.service_days(),
+        vec![
+            Weekday::Mo,
+            Weekday::Tu,
+            Weekday::We,
+            Weekday::Th,
+            Weekday::Fr,
+            Weekday::Sa
+        ]
+    );
+    assert!(calendar.is_open(Weekday::Tu, false, 7 * 60));
+    assert!(!calendar.is_open(Weekday::Tu, false, 12 * 60));
+    assert!(!calendar.is_open(Weekday::Tu, true, 7 * 60));
+    assert!(!calendar.is_open(Weekday::Su, false, 12 * 60));
+}
+
+#[test]
+pub fn normalized_opening_hours() {
+    for (opening_hours, normalized) in &[
+        ("24/7", "00:00-24:00"),
+        ("Mo-Su 05:00-24:00", "05:00-24:00"),
+        ("Fr-Mo 22:00-02:00", "Mo,Fr-Su 22:00-02:00"),
+        (
+            "Mo-Fr 08:00-12:00,Sa 09:00-11:00",
+            "Mo-Fr 08:00-12:00, Sa 09:00-11:00",
+        ),
+        ("Sa,Su; PH off", "Sa,Su; PH off"),
+        ("Mo,Tu,We 7:00-9:00;", "Mo-We 07:00-09:00"),
+        ("SH Mo-Fr 10:00-16:00", "Mo-Fr,SH 10:00-16:00"),
+    ] {
+        assert_eq!(parse(opening_hours).to_string(), *normalized);
+        assert_eq!(parse(normalized), parse(opening_hours));
+    }
+    // after midnight
+    let calendar = parse("Fr 22:00-02:00");
+    assert_eq!(
+        calendar.rules[0].time_ranges,
+        vec![TimeRange {
+            start: 1320,
+            end: 1560
+        }]
+    );
+}
+
+#[test]
+pub fn additional_rules() {
+    // the additional rule does not override the earlier one on Friday
+    let calendar = parse("Mo-Fr 08:00-12:00, Fr 14:00-18:00");
+    assert!(calendar.rules[1].additional);
+    assert_eq!(
+        calendar.time_ranges(Weekday::Fr, false),
+        vec![
+            TimeRange {
+                start: 480,
+                end: 720
+            },
+            TimeRange {
+                start: 840,
+                end: 1080
+            }
+        ]
+    );
+    assert!(calendar.is_open(Weekday::Fr, false, 9 * 60));
+    assert!(calendar.is_open(Weekday::Fr, false, 15 * 60));
+    assert!(!calendar.is_open(Weekday::Th, false, 15 * 60));
+    // while a rule after ; does
+    let calendar = parse("Mo-Fr 08:00-12:00; Fr 14:00-18:00");
+    assert!(!calendar.is_open(Weekday::Fr, false, 9 * 60));
+    assert!(calendar.is_open(Weekday::Fr, false, 15 * 60));
+
+    // an additional off rule removes its time ranges
+    let calendar = parse("Mo-Sa 06:00-20:00, We-Sa 12:00-14:00 off, Su 10:00-12:00");
+    assert!(calendar.is_open(Weekday::Tu, false, 13 * 60));
+    assert!(!calendar.is_open(Weekday::We, false, 13 * 60));
+    assert!(calendar.is_open(Weekday::We, false, 15 * 60));
+    assert!(calendar.is_open(Weekday::Su, false, 11 * 60));
+    assert_eq!(calendar.service_days().len(), 7);
+    let calendar = parse("Mo-Fr 08:00-12:00, We off");
+    assert!(!calendar.service_days().contains(&Weekday::We));
+}
+
+#[test]
+pub fn invalid_opening_hours() {
+    for opening_hours in &[
+        "",
+        "Jan-Mar 08:00-20:00",
+        "Mo-Fr 8h-20h",
+        "Mo-Fr 08:00-25:00",
+        "Mo[1] 08:00-12:00",
+        "sunrise-sunset",
+        "Mo-Fr 08:00+",
+        "Mo-Xy",
+    ] {
+        assert!(
+            opening_hours.parse::<OpeningHours>().is_err(),
+            "{}",
+            opening_hours
+        );
+    }
+}
+
+#[test]
+pub fn route_calendar() {
+    let mut response = common::extract("shape_with_platform.osm.pbf", false);
+    let routes = response.routes.as_mut().unwrap();
+    let calendar = routes[0].calendar.as_ref().unwrap();
+    assert_eq!(calendar.to_string(), "00:00-00:30,05:00-24:00");
+    assert!(validate(&response)
+        .iter()
+        .all(|i| i.kind != IssueKind::InvalidOpeningHours));
+
+    let routes = response.routes.as_mut().unwrap();
+    routes[0].opening_hours = "Mo-Fr sunrise-sunset".to_string();
+    let issues: Vec<String> = validate(&response)
+        .into_iter()
+        .filter(|i| i.kind == IssueKind::InvalidOpeningHours)
+        .map(|i| i.message)
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            "opening_hours Mo-Fr sunrise-sunset is not a valid calendar : unsupported selector sunrise-sunset"
+                .to_string()
+        ]
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+#[test]
+pub fn platforms_paired_with_stop_positions() {
+    let response = common::extract("platform_pairing.osm.pbf", false);
+    let pairs: BTreeMap<&str, &str> = response
+        .stop_points
+        .iter()
+        .map(|sp| (sp.id.as_str(), sp.paired_stop_point_id.as_str()))
+        .collect();
+    // consecutive members of a route
+    assert_eq!(pairs["node:202"], "node:201");
+    assert_eq!(pairs["node:201"], "node:202");
+    assert_eq!(pairs["node:204"], "node:203");
+    // same stop area
+    assert_eq!(pairs["node:206"], "node:205");
+    // same name and close enough
+    assert_eq!(pairs["node:208"], "node:207");
+    // different name, or too far away
+    assert_eq!(pairs["node:209"], "");
+    assert_eq!(pairs["node:210"], "");
+}
+
+#[test]
+pub fn platform_pairing_csv() {
+    let response = common::extract("platform_pairing.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::write_stop_point_pairs_to_csv(
+        &response.stop_points,
+        &tmp_dir,
+        &osm_transit_extractor::sink::CsvOptions::default(),
+    );
+    let file_path = tmp_dir
+        .path()
+        .join("osm-transit-extractor_stop_point_pairs.csv");
+    let reader = BufReader::new(File::open(file_path).unwrap());
+    let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "platform_id,stop_position_id");
+    assert!(lines.contains(&"StopPoint:node:202,StopPoint:node:201".to_string()));
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn platforms_paired_by_proximity_far_from_the_equator() {
+    let response = common::extract("platform_pairing.osm.pbf", false);
+    let mut stop_points: Vec<osm_transit_extractor::StopPoint> = response
+        .stop_points
+        .into_iter()
+        .filter(|sp| sp.id == "node:207" || sp.id == "node:208")
+        .collect();
+    // 40m apart along the 70th parallel
+    stop_points[0].coord = osm_transit_extractor::Coord { lat: 70., lon: 20. };
+    stop_points[1].coord = osm_transit_extractor::Coord {
+        lat: 70.,
+        lon: 20.00105,
+    };
+    for sp in stop_points.iter_mut() {
+        sp.paired_stop_point_id.clear();
+    }
+    osm_transit_extractor::pairing::pair_stop_points(&mut stop_points, &[], &[]);
+    assert!(stop_points
+        .iter()
+        .all(|sp| !sp.paired_stop_point_id.is_empty()));
+}
+
+#[test]
+pub fn halts_counted_once_in_metrics() {
+    let response = common::extract("platform_pairing.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    let route = routes.iter().find(|r| r.id == "relation:30").unwrap();
+    assert_eq!(route.ordered_route_points.len(), 4);
+    let halts = osm_transit_extractor::pairing::route_halts(&route.ordered_route_points);
+    assert_eq!(halts.len(), 2);
+    assert_eq!(
+        osm_transit_extractor::pairing::halt_stop(halts[1]).stop_point_id,
+        "node:203"
+    );
+    assert_eq!(route.metrics.stop_count, 2);
+}
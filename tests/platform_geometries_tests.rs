@@ -0,0 +1,117 @@
+mod common;
+
+use geo_types::Geometry;
+use osm_transit_extractor::geometry::geometry_coords;
+use osm_transit_extractor::Coord;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+fn platform_polygons_response() -> osm_transit_extractor::OsmTcResponse {
+    let mut response = common::extract("platform_polygons.osm.pbf", true);
+    osm_transit_extractor::geometry::compute_stop_areas_hull(
+        &mut response.stop_areas,
+        &response.stop_points,
+    );
+    response
+}
+
+fn approx_coords(coords: &[Coord]) -> Vec<(i64, i64)> {
+    coords
+        .iter()
+        .map(|c| ((c.lon * 1e4).round() as i64, (c.lat * 1e4).round() as i64))
+        .collect()
+}
+
+#[test]
+pub fn platforms_geometries() {
+    let response = platform_polygons_response();
+    let geometries: BTreeMap<&str, &Option<Geometry<f64>>> = response
+        .stop_points
+        .iter()
+        .map(|sp| (sp.id.as_str(), &sp.geometry))
+        .collect();
+    assert_eq!(geometries.len(), 4);
+    assert!(geometries["node:301"].is_none());
+    match geometries["way:71"] {
+        Some(geometry @ Geometry::Polygon(_)) => assert_eq!(
+            approx_coords(&geometry_coords(geometry)),
+            vec![
+                (23000, 488400),
+                (23010, 488400),
+                (23010, 488402),
+                (23000, 488402),
+                (23000, 488400)
+            ]
+        ),
+        other => panic!("{:?} is not a polygon", other),
+    }
+    match geometries["way:72"] {
+        Some(Geometry::LineString(linestring)) => assert_eq!(linestring.0.len(), 2),
+        other => panic!("{:?} is not a linestring", other),
+    }
+    match geometries["relation:60"] {
+        Some(Geometry::Polygon(polygon)) => {
+            // an outer ring made of 2 ways and an inner ring
+            assert_eq!(polygon.exterior().0.len(), 5);
+            assert_eq!(polygon.interiors().len(), 1);
+        }
+        other => panic!("{:?} is not a polygon", other),
+    }
+}
+
+#[test]
+pub fn stop_area_hull_from_platforms_geometries() {
+    let response = platform_polygons_response();
+    assert_eq!(
+        approx_coords(&response.stop_areas[0].hull),
+        vec![
+            (23000, 488400),
+            (23010, 488400),
+            (23020, 488420),
+            (23020, 488430),
+            (23000, 488430),
+            (23000, 488400)
+        ]
+    );
+}
+
+#[test]
+pub fn platforms_geometries_outputs() {
+    let response = platform_polygons_response();
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::write_stop_points_to_csv(&response.stop_points, &tmp_dir, false);
+    let reader = BufReader::new(
+        File::open(tmp_dir.path().join("osm-transit-extractor_stop_points.csv")).unwrap(),
+    );
+    let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+    assert!(lines[0].ends_with(",geometry"));
+    assert!(lines
+        .iter()
+        .any(|l| l.starts_with("StopPoint:way:71,") && l.contains("\"POLYGON((")));
+
+    osm_transit_extractor::geojson::write_stop_areas_to_geojson(
+        &response.stop_areas,
+        &tmp_dir,
+        &osm_transit_extractor::tags::TagsMode::None,
+    );
+    let geojson: serde_json::Value = serde_json::from_reader(
+        File::open(
+            tmp_dir
+                .path()
+                .join("osm-transit-extractor_stop_areas.geojson"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        geojson["features"][0]["geometry"]["type"],
+        serde_json::json!("Polygon")
+    );
+
+    tmp_dir.close().expect("delete temp dir");
+}
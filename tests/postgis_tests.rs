@@ -0,0 +1,72 @@
+mod common;
+
+use std::collections::BTreeMap;
+use tempfile::Builder;
+
+#[test]
+pub fn osm_fixture_postgis_sql() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::postgis::write_postgis_sql(&response, &tmp_dir);
+    let sql = std::fs::read_to_string(tmp_dir.path().join("osm-transit-extractor.sql")).unwrap();
+    assert!(sql.starts_with("BEGIN;"));
+    assert!(sql.trim_end().ends_with("COMMIT;"));
+
+    // number of columns of each table, from its DDL
+    let mut columns = BTreeMap::new();
+    let mut table = None;
+    for line in sql.lines() {
+        if let Some(name) = line.strip_prefix("CREATE TABLE ") {
+            table = Some(name.trim_end_matches(" (").to_string());
+            columns.insert(table.clone().unwrap(), 0);
+        } else if line == ");" {
+            table = None;
+        } else if let Some(t) = &table {
+            if !line.trim_start().starts_with("PRIMARY KEY") {
+                *columns.get_mut(t).unwrap() += 1;
+            }
+        }
+    }
+    // the foreign keys are added after the rows, without checking them
+    assert!(sql
+        .lines()
+        .filter(|l| l.contains("REFERENCES"))
+        .all(|l| l.starts_with("ALTER TABLE") && l.ends_with(" NOT VALID;")));
+    assert!(sql.contains(
+        "ALTER TABLE line_routes ADD FOREIGN KEY (route_id) REFERENCES routes NOT VALID;"
+    ));
+    assert_eq!(columns["stop_points"], 6);
+    assert_eq!(columns["routes"], 35);
+
+    // rows of each COPY block, all with the columns of their table
+    let mut rows = BTreeMap::new();
+    let mut lines = sql.lines();
+    while let Some(line) = lines.next() {
+        if let Some(copy) = line.strip_prefix("COPY ") {
+            let table = copy.trim_end_matches(" FROM stdin;").to_string();
+            let mut count = 0;
+            for row in lines.by_ref().take_while(|l| *l != "\\.") {
+                assert_eq!(row.split('\t').count(), columns[&table], "{}", row);
+                count += 1;
+            }
+            rows.insert(table, count);
+        }
+    }
+    assert_eq!(rows["stop_points"], 77);
+    assert_eq!(rows["routes"], 3);
+    assert_eq!(rows["lines"], 1);
+    assert_eq!(rows["route_points"], 66);
+    assert_eq!(rows["line_routes"], 2);
+
+    assert!(sql.contains("\tSRID=4326;MULTILINESTRING EMPTY\n"));
+    // the route outside of any line
+    assert!(sql
+        .lines()
+        .any(|l| l.starts_with("Route:") && l.contains("\t\\N\t")));
+    assert!(sql.contains(r#"{"STIF:zone":"1","bench":"yes","bus":"yes","#));
+
+    tmp_dir.close().expect("delete temp dir");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use osm_transit_extractor::geometry::route_linestring;
+use std::collections::BTreeMap;
+
+#[test]
+pub fn circular_routes() {
+    let response = common::extract("roundtrip.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    let stops = |id: &str| -> Vec<&str> {
+        routes
+            .iter()
+            .find(|r| r.id == id)
+            .unwrap()
+            .ordered_route_points
+            .iter()
+            .map(|rp| rp.stop_point_id.as_str())
+            .collect()
+    };
+    let roundtrip: Vec<(&str, bool)> = routes
+        .iter()
+        .map(|r| (r.id.as_str(), r.roundtrip))
+        .collect();
+    assert_eq!(
+        roundtrip,
+        vec![
+            ("relation:50", true),
+            ("relation:51", true),
+            ("relation:52", false),
+            // ends at a stop position of the stop area of its first stop
+            ("relation:53", true),
+            ("relation:54", true)
+        ]
+    );
+    // roundtrip=yes, the stops are kept as mapped
+    assert_eq!(
+        stops("relation:50"),
+        vec!["node:301", "node:302", "node:303", "node:304"]
+    );
+    // ends at its first stop
+    assert_eq!(
+        stops("relation:51"),
+        vec!["node:301", "node:304", "node:303", "node:302", "node:301"]
+    );
+    // starts at its origin, the closing stop follows
+    assert_eq!(
+        stops("relation:54"),
+        vec!["node:303", "node:304", "node:301", "node:302", "node:303"]
+    );
+    let directions: Vec<Option<u8>> = routes.iter().map(|r| r.direction_id).collect();
+    // relation:51, with the most stops, is the reference of the line
+    assert_eq!(directions, vec![Some(1), Some(0), None, None, None]);
+}
+
+#[test]
+pub fn circular_routes_shapes() {
+    let response = common::extract("roundtrip.osm.pbf", false);
+    let stop_points: BTreeMap<&str, &osm_transit_extractor::StopPoint> = response
+        .stop_points
+        .iter()
+        .map(|sp| (sp.id.as_str(), sp))
+        .collect();
+    let routes = response.routes.unwrap();
+    for route in &routes[..2] {
+        // the closed way starts between the two first stops
+        let linestring = route_linestring(route, &stop_points);
+        let (first, last) = (&linestring[0], &linestring[linestring.len() - 1]);
+        assert!((first.lat - 48.8).abs() < 1e-6 && (first.lon - 2.3).abs() < 1e-6);
+        assert!((last.lat - 48.8).abs() < 1e-6 && (last.lon - 2.3).abs() < 1e-6);
+
+        let distances: Vec<f64> = route
+            .ordered_route_points
+            .iter()
+            .map(|rp| rp.shape_dist_traveled.unwrap())
+            .collect();
+        assert_eq!(distances[0], 0.);
+        assert!(distances.windows(2).all(|w| w[1] > w[0]));
+    }
+    // the route ending at its first stop ends at the end of the shape
+    let distances = &routes[1].ordered_route_points;
+    assert!((distances[4].shape_dist_traveled.unwrap() - routes[1].metrics.length).abs() < 1.);
+    // towards the second stop
+    let linestring = route_linestring(&routes[0], &stop_points);
+    assert!(linestring[1].lon > 2.3);
+    let linestring = route_linestring(&routes[1], &stop_points);
+    assert!(linestring[1].lat > 48.8);
+    // the shape of the route starting at its origin starts there too
+    let linestring = route_linestring(&routes[4], &stop_points);
+    assert!((linestring[0].lat - 48.81).abs() < 1e-6 && (linestring[0].lon - 2.31).abs() < 1e-6);
+}
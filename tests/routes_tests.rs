@@ -0,0 +1,81 @@
+mod common;
+
+#[test]
+pub fn super_route_is_flattened() {
+    let response = common::extract("super_route.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].id, "relation:20");
+    let stops: Vec<&str> = routes[0]
+        .ordered_route_points
+        .iter()
+        .map(|rp| rp.stop_point_id.as_str())
+        .collect();
+    assert_eq!(stops, vec!["node:101", "node:102", "node:103"]);
+    assert_eq!(routes[0].shape.len(), 2);
+    assert_eq!(routes[0].line_id, "relation:10");
+}
+
+#[test]
+pub fn route_master_dangling_members() {
+    let response = common::extract("super_route.osm.pbf", false);
+    let lines = response.lines.unwrap();
+    assert_eq!(lines[0].routes_id, vec!["relation:20".to_string()]);
+    assert_eq!(lines[0].dangling_routes_id, vec!["relation:99".to_string()]);
+    assert_eq!(lines[0].shape.len(), 2);
+}
+
+#[test]
+pub fn super_route_stops_located_on_shape() {
+    let response = common::extract("super_route.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    let distances: Vec<f64> = routes[0]
+        .ordered_route_points
+        .iter()
+        .map(|rp| rp.shape_dist_traveled.unwrap())
+        .collect();
+    assert_eq!(distances[0], 0.);
+    assert!((distances[1] - 13_311.).abs() < 1.);
+    assert!((distances[2] - 26_614.).abs() < 1.);
+    assert!(routes[0]
+        .ordered_route_points
+        .iter()
+        .all(|rp| rp.distance_to_shape.unwrap() < 1.));
+    let inter_stop_distances = routes[0].inter_stop_distances();
+    assert_eq!(inter_stop_distances[0], None);
+    assert!((inter_stop_distances[1].unwrap() - 13_311.).abs() < 1.);
+}
+
+#[test]
+pub fn super_route_metrics() {
+    let response = common::extract("super_route.osm.pbf", false);
+    let routes = response.routes.unwrap();
+    let metrics = &routes[0].metrics;
+    assert!((metrics.length - 26_614.).abs() < 1.);
+    assert_eq!(metrics.stop_count, 3);
+    assert!((metrics.mean_stop_distance.unwrap() - 13_307.).abs() < 1.);
+    assert!((metrics.max_stop_distance.unwrap() - 13_311.).abs() < 1.);
+    let bbox = metrics.bbox.as_ref().unwrap();
+    assert_eq!((bbox.min_lat, bbox.min_lon), (48.8, 2.3));
+    assert_eq!((bbox.max_lat, bbox.max_lon), (49.0, 2.5));
+    assert!((metrics.origin_destination_distance.unwrap() - 26_614.).abs() < 1.);
+
+    let lines = response.lines.unwrap();
+    assert!((lines[0].metrics.length - 26_614.).abs() < 1.);
+    assert_eq!(lines[0].metrics.stop_count, 3);
+}
+
+#[test]
+pub fn route_of_two_lines_is_kept_in_the_first_one() {
+    let response = common::extract("super_route.osm.pbf", false);
+    let mut routes = response.routes.unwrap();
+    routes[0].line_id.clear();
+    let mut lines = response.lines.unwrap();
+    let mut other_line = lines[0].clone();
+    other_line.id = "relation:11".to_string();
+    lines.push(other_line);
+    osm_transit_extractor::resolve_lines_routes(&mut lines, &mut routes);
+    assert_eq!(routes[0].line_id, "relation:10");
+    assert_eq!(lines[0].routes_id, vec!["relation:20".to_string()]);
+    assert!(lines[1].routes_id.is_empty());
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use osm_transit_extractor::simplification::*;
+use osm_transit_extractor::Coord;
+
+fn zigzag() -> Vec<Coord> {
+    // about 111m between points along the longitude, 1m (then 50m) aside the straight line
+    vec![
+        Coord { lat: 0., lon: 0. },
+        Coord {
+            lat: 0.000009,
+            lon: 0.001,
+        },
+        Coord {
+            lat: 0.,
+            lon: 0.002,
+        },
+        Coord {
+            lat: 0.00045,
+            lon: 0.003,
+        },
+        Coord {
+            lat: 0.,
+            lon: 0.004,
+        },
+    ]
+}
+
+#[test]
+pub fn douglas_peucker_removes_points_within_tolerance() {
+    let simplified = douglas_peucker(&zigzag(), 10.);
+    assert_eq!(simplified.len(), 4);
+    assert_eq!(simplified[1], zigzag()[2]);
+    assert_eq!(douglas_peucker(&zigzag(), 100.).len(), 2);
+    assert_eq!(douglas_peucker(&zigzag(), 0.5).len(), 5);
+}
+
+#[test]
+pub fn visvalingam_removes_small_triangles() {
+    let simplified = visvalingam(&zigzag(), 20.);
+    assert_eq!(simplified.len(), 4);
+    assert!(!simplified.contains(&zigzag()[1]));
+    assert_eq!(visvalingam(&zigzag(), 200.).len(), 2);
+}
+
+#[test]
+pub fn osm_fixture_simplified_and_rounded() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    let vertices = |response: &osm_transit_extractor::OsmTcResponse| -> usize {
+        response
+            .routes
+            .iter()
+            .flatten()
+            .flat_map(|r| &r.shape)
+            .map(|way| way.len())
+            .sum()
+    };
+    let original_vertices = vertices(&response);
+    let options = GeometryOptions {
+        precision: Some(5),
+        tolerance: 5.,
+        ..Default::default()
+    };
+    simplify_osm_tcobjects(&mut response, &options);
+    assert!(vertices(&response) < original_vertices);
+    let is_rounded = |c: &Coord| {
+        (c.lat * 1e5 - (c.lat * 1e5).round()).abs() < 1e-6
+            && (c.lon * 1e5 - (c.lon * 1e5).round()).abs() < 1e-6
+    };
+    assert!(response.stop_points.iter().all(|sp| is_rounded(&sp.coord)));
+    assert!(response
+        .routes
+        .iter()
+        .flatten()
+        .flat_map(|r| r.shape.iter().flatten())
+        .all(is_rounded));
+}
+
+#[test]
+pub fn stops_located_on_simplified_shapes() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    let options = GeometryOptions {
+        tolerance: 50.,
+        ..Default::default()
+    };
+    simplify_osm_tcobjects(&mut response, &options);
+    for route in response.routes.unwrap() {
+        let linestring = osm_transit_extractor::geometry::stitch_shape(&route.shape);
+        let length = osm_transit_extractor::geometry::length(&linestring);
+        assert!(route
+            .ordered_route_points
+            .iter()
+            .filter_map(|rp| rp.shape_dist_traveled)
+            .all(|d| d <= length + 0.1));
+    }
+}
+
+#[test]
+pub fn precision_above_max_keeps_coordinates() {
+    let coord = Coord {
+        lat: 48.123456789012345,
+        lon: 2.123456789012345,
+    };
+    assert_eq!(round_coord(&coord, MAX_PRECISION), coord);
+    assert_eq!(round_coord(&coord, 100), coord);
+}
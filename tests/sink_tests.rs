@@ -0,0 +1,191 @@
+mod common;
+
+use osm_transit_extractor::encoding::GeometryEncoding;
+use osm_transit_extractor::sink::{write_to_sink, CsvOptions, CsvSink, GeoJsonSink, Sink};
+use osm_transit_extractor::tags::TagsMode;
+use osm_transit_extractor::*;
+use tempfile::Builder;
+
+#[derive(Default)]
+struct Counter {
+    objects: usize,
+    finished: bool,
+}
+
+impl Sink for Counter {
+    fn stop_point(&mut self, _: &StopPoint) {
+        self.objects += 1;
+    }
+    fn stop_area(&mut self, _: &StopArea) {
+        self.objects += 1;
+    }
+    fn route(&mut self, _: &Route) {
+        self.objects += 1;
+    }
+    fn line(&mut self, _: &Line) {
+        self.objects += 1;
+    }
+    fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+#[test]
+pub fn custom_sink() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let mut counter = Counter::default();
+    write_to_sink(&response, &mut counter);
+    let expected = response.stop_points.len()
+        + response.stop_areas.len()
+        + response.routes.unwrap().len()
+        + response.lines.unwrap().len();
+    assert_eq!(counter.objects, expected);
+    assert!(counter.finished);
+}
+
+#[test]
+pub fn csv_sink_writes_the_csv_files() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    for tags_mode in &[
+        TagsMode::None,
+        TagsMode::Columns,
+        TagsMode::Json,
+        TagsMode::Whitelist(vec!["name".to_string()]),
+    ] {
+        let tmp_dir = Builder::new()
+            .prefix("osm_transit_extractor")
+            .tempdir()
+            .expect("create temp dir");
+        let options = CsvOptions {
+            tags_mode: tags_mode.clone(),
+            geometry_encoding: GeometryEncoding::Wkt,
+            hulls: true,
+            ..Default::default()
+        };
+        let mut sink = CsvSink::new(&tmp_dir, options.clone());
+        write_to_sink(&response, &mut sink);
+        drop(sink);
+
+        let other_dir = Builder::new()
+            .prefix("osm_transit_extractor")
+            .tempdir()
+            .expect("create temp dir");
+        write_stop_points_to_csv_with_options(&response.stop_points, &other_dir, &options);
+        write_stop_areas_to_csv_with_options(&response.stop_areas, &other_dir, &options);
+        write_stop_areas_stop_point_to_csv(&response.stop_areas, &other_dir);
+        write_stop_point_pairs_to_csv(&response.stop_points, &other_dir, &options);
+        let routes = response.routes.as_ref().unwrap();
+        write_routes_to_csv_with_options(routes, &other_dir, &options);
+        let lines = response.lines.as_ref().unwrap();
+        write_lines_to_csv_with_options(lines, &other_dir, &options);
+
+        let mut files = 0;
+        for entry in std::fs::read_dir(&other_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let streamed = tmp_dir.path().join(path.file_name().unwrap());
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                std::fs::read_to_string(&streamed).unwrap(),
+                "{:?}",
+                path
+            );
+            files += 1;
+        }
+        assert_eq!(files, 9);
+
+        tmp_dir.close().expect("delete temp dir");
+        other_dir.close().expect("delete temp dir");
+    }
+}
+
+#[test]
+pub fn geojson_sink() {
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let mut sink = GeoJsonSink::new(&tmp_dir, TagsMode::None);
+    write_to_sink(&common::extract("osm_fixture.osm.pbf", true), &mut sink);
+    let file = tmp_dir
+        .path()
+        .join("osm-transit-extractor_stop_points.geojson");
+    let collection: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(file).unwrap()).unwrap();
+    assert_eq!(collection["type"], "FeatureCollection");
+    assert_eq!(collection["features"].as_array().unwrap().len(), 77);
+    // no routes given, no routes file
+    assert!(!tmp_dir
+        .path()
+        .join("osm-transit-extractor_routes.geojson")
+        .exists());
+
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn geojson_sink_tags_modes() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let route_properties = |tags_mode: TagsMode| -> serde_json::Value {
+        let tmp_dir = Builder::new()
+            .prefix("osm_transit_extractor")
+            .tempdir()
+            .expect("create temp dir");
+        let mut sink = GeoJsonSink::new(&tmp_dir, tags_mode);
+        write_to_sink(&response, &mut sink);
+        let file = tmp_dir.path().join("osm-transit-extractor_routes.geojson");
+        let collection: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(file).unwrap()).unwrap();
+        collection["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["properties"]["route_id"] == "Route:relation:1257168")
+            .unwrap()["properties"]
+            .clone()
+    };
+
+    let properties = route_properties(TagsMode::None);
+    assert!(properties.get("osm:network").is_none());
+    assert!(properties.get("osm_tags").is_none());
+    assert_eq!(route_properties(TagsMode::Columns)["osm:network"], "RATP");
+    assert_eq!(
+        route_properties(TagsMode::Json)["osm_tags"]["network"],
+        "RATP"
+    );
+    let properties = route_properties(TagsMode::Whitelist(vec![
+        "network".to_string(),
+        "unknown".to_string(),
+    ]));
+    assert_eq!(properties["osm:network"], "RATP");
+    assert!(properties["osm:unknown"].is_null());
+    assert!(properties.get("osm:type").is_none());
+}
+
+#[test]
+pub fn csv_sink_without_routes() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    response.routes = Some(vec![]);
+    response.lines = Some(vec![]);
+    for tags_mode in &[TagsMode::None, TagsMode::Columns] {
+        let tmp_dir = Builder::new()
+            .prefix("osm_transit_extractor")
+            .tempdir()
+            .expect("create temp dir");
+        let options = CsvOptions {
+            tags_mode: tags_mode.clone(),
+            ..Default::default()
+        };
+        let mut sink = CsvSink::new(&tmp_dir, options);
+        write_to_sink(&response, &mut sink);
+        drop(sink);
+        let routes =
+            std::fs::read_to_string(tmp_dir.path().join("osm-transit-extractor_routes.csv"))
+                .unwrap();
+        assert_eq!(routes.lines().count(), 1);
+        let read = csv_reader::read_osm_tcobjects_from_csv(&tmp_dir, sink::DEFAULT_PREFIX).unwrap();
+        assert_eq!(read.routes, Some(vec![]));
+        assert_eq!(read.lines, Some(vec![]));
+
+        tmp_dir.close().expect("delete temp dir");
+    }
+}
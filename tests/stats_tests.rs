@@ -0,0 +1,30 @@
+#[test]
+pub fn osm_fixture_stats() {
+    let osm_path = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/osm_fixture.osm.pbf");
+    let mut parsed_pbf = osmpbfreader::OsmPbfReader::new(std::fs::File::open(&osm_path).unwrap());
+    let mut timings = osm_transit_extractor::stats::PhaseTimings::default();
+    let response =
+        osm_transit_extractor::get_osm_tcobjects_with_timings(&mut parsed_pbf, false, &mut timings);
+    let stats = osm_transit_extractor::stats::compute_stats(&response, timings);
+    assert_eq!(stats.stop_points, 77);
+    assert_eq!(stats.routes, 3);
+    assert_eq!(stats.lines, 1);
+    assert_eq!(stats.stop_points_by_type["Platform"], 12);
+    assert_eq!(stats.routes_by_mode["bus"], 2);
+    assert_eq!(stats.routes_without_shape, 1);
+    assert_eq!(stats.stops_at_null_island, 0);
+    let phases: Vec<&str> = stats.timings.0.iter().map(|t| t.phase.as_str()).collect();
+    for phase in &[
+        "stop_points",
+        "stop_areas",
+        "routes",
+        "lines",
+        "route_points_location",
+        "metrics",
+        "stop_points_pairing",
+    ] {
+        assert!(phases.contains(phase), "missing phase {}", phase);
+    }
+}
@@ -196,3 +196,23 @@ pub fn osm_fixture_lines_csv() {
 
     tmp_dir.close().expect("delete temp dir");
 }
+
+#[test]
+pub fn osm_fixture_routes_line_id() {
+    let osm_path = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/osm_fixture.osm.pbf");
+    let mut parsed_pbf = osmpbfreader::OsmPbfReader::new(std::fs::File::open(&osm_path).unwrap());
+    let response = osm_transit_extractor::get_osm_tcobjects(&mut parsed_pbf, false);
+    let lines = response.lines.unwrap();
+    assert_eq!(lines[0].routes_id.len(), 2);
+    assert!(lines[0].dangling_routes_id.is_empty());
+    for r in response.routes.unwrap() {
+        match r.id.as_ref() {
+            "relation:1257168" | "relation:1257174" => {
+                assert_eq!(r.line_id, "relation:1257187".to_string());
+            }
+            _ => assert_eq!(r.line_id, String::new()),
+        }
+    }
+}
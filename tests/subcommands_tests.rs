@@ -0,0 +1,139 @@
+mod common;
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+use tempfile::Builder;
+
+#[test]
+pub fn osm_fixture_filter_by_mode() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    let filter = osm_transit_extractor::RouteFilter {
+        modes: vec!["tram".to_string()],
+        ..Default::default()
+    };
+    osm_transit_extractor::filter_osm_tcobjects(&mut response, &filter);
+    assert_eq!(response.routes.unwrap().len(), 0);
+    assert_eq!(response.lines.unwrap().len(), 0);
+    assert_eq!(response.stop_points.len(), 77);
+}
+
+#[test]
+pub fn osm_fixture_filter_drops_route_ids_of_lines() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    response
+        .routes
+        .as_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|r| r.id == "relation:1257168")
+        .unwrap()
+        .operator = "Other".to_string();
+    let filter = osm_transit_extractor::RouteFilter {
+        operators: vec!["RATP".to_string()],
+        ..Default::default()
+    };
+    osm_transit_extractor::filter_osm_tcobjects(&mut response, &filter);
+    assert_eq!(response.routes.unwrap().len(), 1);
+    assert_eq!(
+        response.lines.unwrap()[0].routes_id,
+        vec!["relation:1257174".to_string()]
+    );
+}
+
+#[test]
+pub fn osm_fixture_validate() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let issues = osm_transit_extractor::validation::validate(&response);
+    assert!(issues
+        .iter()
+        .all(|i| i.kind != osm_transit_extractor::validation::IssueKind::UnknownRoute));
+}
+
+#[test]
+pub fn osm_fixture_diff_with_itself() {
+    let changes = osm_transit_extractor::diff::diff_osm_tcobjects(
+        &common::extract("osm_fixture.osm.pbf", false),
+        &common::extract("osm_fixture.osm.pbf", false),
+    );
+    assert!(changes.is_empty());
+}
+
+#[test]
+pub fn osm_fixture_gtfs() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::gtfs::write_gtfs(&response, &tmp_dir, &Default::default());
+    let file = File::open(tmp_dir.path().join("trips.txt")).unwrap();
+    assert_eq!(3, BufReader::new(file).lines().count());
+    let file = File::open(tmp_dir.path().join("routes.txt")).unwrap();
+    assert_eq!(2, BufReader::new(file).lines().count());
+    let mut rdr = csv::Reader::from_path(tmp_dir.path().join("agency.txt")).unwrap();
+    for agency in rdr.records() {
+        assert!(!agency.unwrap()[2].is_empty());
+    }
+    // the first and the last stop times of each trip are timed
+    let mut rdr = csv::Reader::from_path(tmp_dir.path().join("stop_times.txt")).unwrap();
+    let stop_times: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+    for (i, stop_time) in stop_times.iter().enumerate() {
+        let first = i == 0 || stop_times[i - 1][0] != stop_time[0];
+        let last = i + 1 == stop_times.len() || stop_times[i + 1][0] != stop_time[0];
+        assert_eq!(first || last, !stop_time[1].is_empty());
+        assert_eq!(stop_time[1], stop_time[2]);
+    }
+    assert_eq!(&stop_times[0][1], "08:00:00");
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn osm_fixture_gtfs_overflowing_travel_time() {
+    let mut response = common::extract("osm_fixture.osm.pbf", false);
+    // a valid duration, but arriving after u32::MAX seconds
+    for route in response.routes.as_mut().unwrap() {
+        route.travel_time = "1193040:00".to_string();
+    }
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::gtfs::write_gtfs(&response, &tmp_dir, &Default::default());
+    for file in &["trips.txt", "stop_times.txt"] {
+        let mut rdr = csv::Reader::from_path(tmp_dir.path().join(file)).unwrap();
+        assert_eq!(rdr.records().count(), 0, "{}", file);
+    }
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn osm_fixture_geojson() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    osm_transit_extractor::geojson::write_routes_to_geojson(
+        response.routes.as_ref().unwrap(),
+        &tmp_dir,
+        &osm_transit_extractor::tags::TagsMode::None,
+    );
+    let file_path = tmp_dir.path().join("osm-transit-extractor_routes.geojson");
+    let collection: serde_json::Value =
+        serde_json::from_reader(File::open(file_path).unwrap()).unwrap();
+    assert_eq!(collection["features"].as_array().unwrap().len(), 3);
+    tmp_dir.close().expect("delete temp dir");
+}
+
+#[test]
+pub fn parse_durations() {
+    use osm_transit_extractor::gtfs::parse_duration;
+    assert_eq!(parse_duration("45"), Some(2700));
+    assert_eq!(parse_duration("01:30"), Some(5400));
+    assert_eq!(parse_duration("1:02:03"), Some(3723));
+    assert_eq!(parse_duration("1:75"), None);
+    assert_eq!(parse_duration("PT45M"), None);
+    assert_eq!(parse_duration(""), None);
+}
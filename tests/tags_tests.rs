@@ -0,0 +1,82 @@
+mod common;
+
+use osm_transit_extractor::sink::CsvOptions;
+use osm_transit_extractor::tags::TagsMode;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tempfile::Builder;
+
+fn read_csv(path: &std::path::Path) -> Vec<csv::StringRecord> {
+    let mut rdr = csv::Reader::from_path(path).unwrap();
+    let header = rdr.headers().unwrap().clone();
+    std::iter::once(header)
+        .chain(rdr.records().map(|r| r.unwrap()))
+        .collect()
+}
+
+#[test]
+pub fn osm_fixture_tags_modes() {
+    let response = common::extract("osm_fixture.osm.pbf", false);
+    let tmp_dir = Builder::new()
+        .prefix("osm_transit_extractor")
+        .tempdir()
+        .expect("create temp dir");
+    let stop_points_file = tmp_dir.path().join("osm-transit-extractor_stop_points.csv");
+
+    // all tags as columns
+    osm_transit_extractor::write_stop_points_to_csv(&response.stop_points, &tmp_dir, true);
+    let header = &read_csv(&stop_points_file)[0];
+    assert!(header.len() > 20);
+    assert!(header.iter().any(|h| h == "osm:bench"));
+
+    // all tags as JSON
+    osm_transit_extractor::write_stop_points_to_csv_with_options(
+        &response.stop_points,
+        &tmp_dir,
+        &CsvOptions {
+            tags_mode: TagsMode::Json,
+            ..Default::default()
+        },
+    );
+    let rows = read_csv(&stop_points_file);
+    assert_eq!(rows[0].len(), 7);
+    assert_eq!(&rows[0][6], "osm_tags");
+    let tags: serde_json::Value = serde_json::from_str(&rows[1][6]).unwrap();
+    assert_eq!(tags["highway"], "bus_stop");
+    assert_eq!(tags["name"], rows[1][3]);
+
+    // only the given tags, in the given order
+    osm_transit_extractor::write_stop_points_to_csv_with_options(
+        &response.stop_points,
+        &tmp_dir,
+        &CsvOptions {
+            tags_mode: TagsMode::Whitelist(vec![
+                "wheelchair".to_string(),
+                "unknown_key".to_string(),
+            ]),
+            ..Default::default()
+        },
+    );
+    let rows = read_csv(&stop_points_file);
+    assert_eq!(
+        rows[0].iter().skip(6).collect::<Vec<_>>(),
+        vec!["osm:wheelchair", "osm:unknown_key"]
+    );
+    assert!(rows.iter().skip(1).any(|r| &r[6] == "yes"));
+    assert!(rows.iter().skip(1).all(|r| r[7].is_empty()));
+
+    let routes = response.routes.unwrap();
+    osm_transit_extractor::write_routes_to_csv_with_options(
+        &routes,
+        &tmp_dir,
+        &CsvOptions {
+            tags_mode: TagsMode::Json,
+            ..Default::default()
+        },
+    );
+    let file = File::open(tmp_dir.path().join("osm-transit-extractor_routes.csv")).unwrap();
+    let header = BufReader::new(file).lines().next().unwrap().unwrap();
+    assert!(header.ends_with(",origin_destination_distance,osm_tags"));
+
+    tmp_dir.close().expect("delete temp dir");
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use osm_transit_extractor::geometry::convex_hull;
+use osm_transit_extractor::wkt_format::*;
+use osm_transit_extractor::Coord;
+
+fn coord(lon: f64, lat: f64) -> Coord {
+    Coord { lat, lon }
+}
+
+#[test]
+pub fn empty_and_degenerate_shapes() {
+    assert_eq!(shape_to_wkt(&[]), "MULTILINESTRING EMPTY");
+    assert_eq!(
+        shape_to_wkt(&[vec![coord(2.3, 48.8)]]),
+        "MULTILINESTRING EMPTY"
+    );
+    assert_eq!(
+        shape_to_wkt(&[vec![coord(2.3, 48.8), coord(2.4, 48.9)], vec![]]),
+        "MULTILINESTRING((2.3 48.8,2.4 48.9))"
+    );
+    assert!(wkt_to_shape("MULTILINESTRING EMPTY").unwrap().is_empty());
+    assert!(wkt_to_shape("POINT(2.3 48.8)").is_err());
+    assert!(wkt_to_shape("MULTILINESTRING((2.3 48.8,").is_err());
+}
+
+#[test]
+pub fn osm_fixture_shapes_round_trip() {
+    let osm_path = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/osm_fixture.osm.pbf");
+    let mut parsed_pbf = osmpbfreader::OsmPbfReader::new(std::fs::File::open(&osm_path).unwrap());
+    let routes = osm_transit_extractor::get_routes_from_osm(&mut parsed_pbf);
+    for route in routes {
+        let shape: Vec<Vec<Coord>> = route
+            .shape
+            .iter()
+            .filter(|way| way.len() >= 2)
+            .cloned()
+            .collect();
+        assert_eq!(wkt_to_shape(&shape_to_wkt(&route.shape)).unwrap(), shape);
+    }
+}
+
+#[test]
+pub fn hulls() {
+    assert_eq!(hull_to_wkt(&convex_hull(&[])), "POLYGON EMPTY");
+    assert_eq!(hull_to_wkt(&convex_hull(&[coord(2., 48.)])), "POINT(2 48)");
+    let aligned = [coord(2., 48.), coord(2.2, 48.2), coord(2.1, 48.1)];
+    assert_eq!(
+        hull_to_wkt(&convex_hull(&aligned)),
+        "LINESTRING(2 48,2.2 48.2)"
+    );
+    let square = [
+        coord(0., 0.),
+        coord(1., 1.),
+        coord(0., 1.),
+        coord(0.5, 0.5),
+        coord(1., 0.),
+    ];
+    assert_eq!(
+        hull_to_wkt(&convex_hull(&square)),
+        "POLYGON((0 0,1 0,1 1,0 1,0 0))"
+    );
+}
+
+#[test]
+pub fn osm_fixture_stop_areas_hull() {
+    let mut response = common::extract("osm_fixture.osm.pbf", true);
+    // the hulls are only computed on demand
+    assert!(response.stop_areas.iter().all(|sa| sa.hull.is_empty()));
+    osm_transit_extractor::geometry::compute_stop_areas_hull(
+        &mut response.stop_areas,
+        &response.stop_points,
+    );
+    assert!(response.stop_areas.iter().any(|sa| !sa.hull.is_empty()));
+    for sa in &response.stop_areas {
+        assert!(wkt_to_geometry(&hull_to_wkt(&sa.hull)).is_ok() || sa.hull.is_empty());
+    }
+}